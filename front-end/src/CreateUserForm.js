@@ -1,8 +1,16 @@
 import React, { useState, useEffect } from "react";
 import axios from "axios";
 
+// the service requires an API key, issued with `submission api-keys create`. It is entered at
+// runtime and kept for the browser session only, so that no key ships with the build.
+const API_KEY_STORAGE = "apiKey";
+
 function CreateUserForm() {
-  const [userId, setUserId] = useState("");
+  const [apiKey, setApiKey] = useState(
+    sessionStorage.getItem(API_KEY_STORAGE) || "",
+  );
+
+  const [displayName, setDisplayName] = useState("");
   const [balance, setBalance] = useState("");
   const [userResponse, setUserResponse] = useState("");
 
@@ -14,29 +22,39 @@ function CreateUserForm() {
   const [users, setUsers] = useState([]);
   const [transactions, setTransactions] = useState([]);
 
+  const authorization = () => ({
+    headers: { Authorization: `Bearer ${apiKey}` },
+  });
+
   const handleUserSubmit = async (e) => {
     e.preventDefault();
 
     // Validation: Check for negative values
-    if (parseInt(userId) < 0 || parseInt(balance) < 0) {
-      setUserResponse("User ID and balance cannot be negative.");
+    if (parseFloat(balance) < 0) {
+      setUserResponse("Balance cannot be negative.");
       return;
     }
 
     try {
-      const response = await axios.post("http://localhost:9095/users", {
-        id: parseInt(userId),
-        balance: parseInt(balance),
-      });
+      const response = await axios.post(
+        "http://localhost:9095/users",
+        {
+          // ids are assigned by the service
+          display_name: displayName || undefined,
+          // amounts are sent as entered, so that decimal places are not lost
+          balance: balance,
+        },
+        authorization(),
+      );
 
-      setUserResponse(response.data);
+      setUserResponse(`Created user ${response.data.id}`);
       fetchUsers();
-      setUserId("");
+      setDisplayName("");
       setBalance("");
     } catch (error) {
       console.error("Error:", error);
       setUserResponse(
-        error.response ? error.response.data : "An error occurred",
+        error.response ? error.response.data.message : "An error occurred",
       );
     }
   };
@@ -48,18 +66,22 @@ function CreateUserForm() {
     if (
       parseInt(fromId) < 0 ||
       parseInt(toId) < 0 ||
-      parseInt(transactionAmount) < 0
+      parseFloat(transactionAmount) < 0
     ) {
       setTransactionResponse("From ID, To ID, and amount cannot be negative.");
       return;
     }
 
     try {
-      await axios.post("http://localhost:9095/transactions", {
-        from_id: parseInt(fromId),
-        to_id: parseInt(toId),
-        amount: parseInt(transactionAmount),
-      });
+      await axios.post(
+        "http://localhost:9095/transactions",
+        {
+          from_id: parseInt(fromId),
+          to_id: parseInt(toId),
+          amount: transactionAmount,
+        },
+        authorization(),
+      );
 
       setTransactionResponse("Transaction submitted");
       fetchTransactions();
@@ -70,44 +92,59 @@ function CreateUserForm() {
     } catch (error) {
       console.error("Error:", error);
       setTransactionResponse(
-        error.response ? error.response.data : "An error occurred",
+        error.response ? error.response.data.message : "An error occurred",
       );
     }
   };
 
   const fetchUsers = () => {
     axios
-      .get("http://localhost:9095/users")
-      .then((response) => setUsers(response.data))
+      .get("http://localhost:9095/users", authorization())
+      .then((response) => setUsers(response.data.items))
       .catch((error) => console.error("Error fetching users:", error));
   };
 
   const fetchTransactions = () => {
     axios
-      .get("http://localhost:9095/transactions")
+      .get("http://localhost:9095/transactions", authorization())
       .then((response) => setTransactions(response.data))
       .catch((error) => console.error("Error fetching transactions:", error));
   };
 
   useEffect(() => {
-    fetchUsers();
-    fetchTransactions();
-  }, []);
+    sessionStorage.setItem(API_KEY_STORAGE, apiKey);
+    if (apiKey) {
+      fetchUsers();
+      fetchTransactions();
+    }
+  }, [apiKey]);
 
   return (
     <div
       style={{ display: "flex", flexDirection: "column", alignItems: "center" }}
     >
+      {/* API Key */}
+      <div>
+        <label>
+          API Key:
+          <input
+            type="password"
+            value={apiKey}
+            onChange={(e) => setApiKey(e.target.value.trim())}
+          />
+        </label>
+      </div>
+
       {/* User Creation Form */}
       <div>
         <h2>Create User</h2>
         <form onSubmit={handleUserSubmit}>
           <label>
-            User ID:
+            Display Name:
             <input
-              type="number"
-              value={userId}
-              onChange={(e) => setUserId(e.target.value)}
+              type="text"
+              value={displayName}
+              onChange={(e) => setDisplayName(e.target.value)}
             />
           </label>
           <br />
@@ -168,13 +205,17 @@ function CreateUserForm() {
           <thead>
             <tr>
               <th>ID</th>
+              <th>Name</th>
+              <th>Asset</th>
               <th>Balance</th>
             </tr>
           </thead>
           <tbody>
             {users.map((user) => (
-              <tr key={user.id}>
+              <tr key={`${user.id}-${user.asset}`}>
                 <td>{user.id}</td>
+                <td>{user.display_name}</td>
+                <td>{user.asset}</td>
                 <td>{user.balance}</td>
               </tr>
             ))}
@@ -190,6 +231,7 @@ function CreateUserForm() {
             <tr>
               <th>From ID</th>
               <th>To ID</th>
+              <th>Asset</th>
               <th>Amount</th>
             </tr>
           </thead>
@@ -198,6 +240,7 @@ function CreateUserForm() {
               <tr key={transaction.from_id}>
                 <td>{transaction.from_id}</td>
                 <td>{transaction.to_id}</td>
+                <td>{transaction.asset}</td>
                 <td>{transaction.amount}</td>
               </tr>
             ))}
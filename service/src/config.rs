@@ -1,7 +1,9 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use serde_derive::{Deserialize, Serialize};
 use std::fs;
 
+use crate::db::{Amount, FeePolicy, FeeSchedule};
+
 #[derive(Parser, Debug)]
 pub struct CLIArguments {
     /// Path to configuration file.
@@ -11,6 +13,40 @@ pub struct CLIArguments {
     /// If set, the service will drop all tables in config.db_path before proceeding with catchup.
     #[clap(long)]
     pub start_anew: bool,
+
+    /// If set, the service runs the given command against its DB, as set up already, instead of
+    /// serving requests.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// [Command] is an administrative task run from the command line.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Manage the API keys that clients authenticate with.
+    #[clap(subcommand)]
+    ApiKeys(ApiKeyCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ApiKeyCommand {
+    /// Issue a new API key, printing it this once.
+    Create {
+        /// Name telling the key apart, such as the client it is issued to.
+        #[clap(long, value_parser)]
+        name: String,
+        /// If set, the key also reaches the admin endpoints and registers assets.
+        #[clap(long)]
+        admin: bool,
+    },
+    /// List the API keys issued, without revealing them.
+    List,
+    /// Revoke an API key for good.
+    Revoke {
+        /// Id of the key, as listed.
+        #[clap(value_parser)]
+        id: u64,
+    },
 }
 
 /// [Config] defines configuration for this service.
@@ -28,6 +64,84 @@ pub struct Config {
     pub logs_dir: String,
     // listening port for the Service service.
     pub port_number: u16,
+    // origins allowed to call the Service from a browser, none if left empty.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    // number of seconds for which a replayed Idempotency-Key returns the original response.
+    #[serde(default = "default_idempotency_window_secs")]
+    pub idempotency_window_secs: u64,
+    // balance an account has to keep after sending funds, unless overridden for the account.
+    #[serde(default = "default_min_balance")]
+    pub min_balance: Amount,
+    // amount by which an account may fall below its minimum balance, unless overridden for the account.
+    #[serde(default)]
+    pub overdraft_limit: Amount,
+    // number of seconds for which a hold reserves funds, unless the request asks otherwise.
+    #[serde(default = "default_hold_ttl_secs")]
+    pub hold_ttl_secs: u64,
+    // number of seconds between two passes releasing the funds of expired holds.
+    #[serde(default = "default_hold_expiry_interval_secs")]
+    pub hold_expiry_interval_secs: u64,
+    // number of transactions a batch may hold, within the 10 MiB limit on request bodies.
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    // number of seconds between two passes running the schedules that are due.
+    #[serde(default = "default_schedule_interval_secs")]
+    pub schedule_interval_secs: u64,
+    // number of seconds before retrying a scheduled transfer that fell short of balance or failed on the service's side, doubling on every retry.
+    #[serde(default = "default_schedule_retry_backoff_secs")]
+    pub schedule_retry_backoff_secs: u64,
+    // number of retries of a scheduled transfer that fell short of balance or failed on the service's side, before giving up on it.
+    #[serde(default = "default_schedule_max_retries")]
+    pub schedule_max_retries: u32,
+    // external_ref of the account paying interest, required once accounts earn any.
+    #[serde(default)]
+    pub interest_account_ref: Option<String>,
+    // number of seconds between two passes accruing interest through the day before.
+    #[serde(default = "default_interest_interval_secs")]
+    pub interest_interval_secs: u64,
+    // external_ref of the account credited with transfer fees, required along with fee_schedules.
+    #[serde(default)]
+    pub fee_account_ref: Option<String>,
+    // fees charged on transfers per asset and account tier. Transfers no schedule matches are free.
+    #[serde(default)]
+    pub fee_schedules: Vec<FeeSchedule>,
+}
+
+fn default_idempotency_window_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_hold_ttl_secs() -> u64 {
+    15 * 60
+}
+
+fn default_hold_expiry_interval_secs() -> u64 {
+    5
+}
+
+fn default_max_batch_size() -> usize {
+    500
+}
+
+fn default_schedule_interval_secs() -> u64 {
+    5
+}
+
+fn default_schedule_retry_backoff_secs() -> u64 {
+    60
+}
+
+fn default_schedule_max_retries() -> u32 {
+    5
+}
+
+fn default_interest_interval_secs() -> u64 {
+    60 * 60
+}
+
+fn default_min_balance() -> Amount {
+    Amount::from(5)
 }
 
 pub(crate) fn load_config(config_path: &str) -> std::result::Result<Config, String> {
@@ -38,6 +152,58 @@ pub(crate) fn load_config(config_path: &str) -> std::result::Result<Config, Stri
                 Err(_) => return Err("config.toml is not a proper toml file.".to_string()),
             };
 
+            for (name, amount) in [
+                ("min_balance", ret.min_balance),
+                ("overdraft_limit", ret.overdraft_limit),
+            ] {
+                if let Err(e) = amount.check() {
+                    return Err(format!("{} in config.toml is invalid: {}", name, e));
+                }
+            }
+
+            if ret.hold_ttl_secs == 0 || ret.hold_expiry_interval_secs == 0 {
+                return Err(
+                    "hold_ttl_secs and hold_expiry_interval_secs in config.toml must be positive."
+                        .to_string(),
+                );
+            }
+
+            // a batch of no transactions would turn every batch away
+            if ret.max_batch_size == 0 {
+                return Err("max_batch_size in config.toml must be positive.".to_string());
+            }
+
+            if ret.schedule_interval_secs == 0 || ret.schedule_retry_backoff_secs == 0 {
+                return Err(
+                    "schedule_interval_secs and schedule_retry_backoff_secs in config.toml must be positive."
+                        .to_string(),
+                );
+            }
+
+            if ret.interest_interval_secs == 0 {
+                return Err("interest_interval_secs in config.toml must be positive.".to_string());
+            }
+
+            // warp refuses origins that are not of the form scheme://host[:port]
+            if let Some(origin) = ret
+                .allowed_origins
+                .iter()
+                .find(|origin| !origin.starts_with("http://") && !origin.starts_with("https://"))
+            {
+                return Err(format!(
+                    "allowed_origins in config.toml must be http or https origins, not {}.",
+                    origin
+                ));
+            }
+
+            let fee_policy = FeePolicy {
+                account_ref: ret.fee_account_ref.clone(),
+                schedules: ret.fee_schedules.clone(),
+            };
+            if let Err(e) = fee_policy.check() {
+                return Err(format!("fee_schedules in config.toml are invalid: {}", e));
+            }
+
             Ok(ret)
         }
         Err(e) => Err(format!(
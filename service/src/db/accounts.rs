@@ -1,78 +1,383 @@
 //! Helpers processing HTTP requests related to accounts.
 
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::Row;
 
-use crate::db::{bigint_to_u64, sql, u64_to_bigint, Database};
-use crate::error_codes::Error as ServiceAPIError;
+use crate::db::{
+    bigint_to_id, check_amount, cursor_id, decode_cursor, default_asset, encode_cursor,
+    id_to_bigint, sql, validation_result, Amount, Database, IdempotencyHeader, IdempotencyKey,
+    Page,
+};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
 
 impl Database {
-    pub async fn get_account_info(&self, id: Option<u64>) -> Result<Vec<User>, ServiceAPIError> {
-        let client = self
-            .pool
-            .get()
+    /// Returns the holdings of an account, one [User] per asset.
+    pub async fn get_account_info(&self, id: u64) -> Result<Vec<User>, ServiceAPIError> {
+        let id = id_to_bigint(id)?;
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let account_query_result = client
+            .query(sql::SELECT_ACCOUNT_INFO_BY_ID, &[&id])
             .await
-            .map_err(|_| ServiceAPIError::ResourceBusy)?;
-
-        let mut users = Vec::new();
-
-        let account_query_result = if id.is_some() {
-            client
-                .query(
-                    sql::SELECT_ACCOUNT_INFO_BY_ID,
-                    &[&u64_to_bigint(id.unwrap())],
-                )
-                .await
-                .map_err(|_| ServiceAPIError::DatabaseQueryError)?
-        } else {
-            client
-                .query(sql::SELECT_ACCOUNT_INFO_BY_ID, &[&Option::<i64>::None])
-                .await
-                .map_err(|_| ServiceAPIError::DatabaseQueryError)?
-        };
+            .map_err(|e| ServiceAPIError::database("querying account", e))?;
 
         if account_query_result.is_empty() {
             return Err(ServiceAPIError::SenderDoesNotExist);
         }
 
-        for row in account_query_result {
-            users.push(User {
-                id: bigint_to_u64(row.get::<_, i64>("id")),
-                balance: bigint_to_u64(row.get::<_, i64>("balance")),
-            })
+        account_query_result.iter().map(User::from_row).collect()
+    }
+
+    /// Recomputes the balances of an account from its postings in the journal, next to the
+    /// cached `Holding.balance` of each asset, so that the two can be reconciled.
+    pub async fn recompute_balance(&self, id: u64) -> Result<Vec<LedgerBalance>, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let balance_query_result = client
+            .query(sql::SELECT_LEDGER_BALANCE, &[&id_to_bigint(id)?])
+            .await
+            .map_err(|e| ServiceAPIError::database("recomputing balance", e))?;
+
+        if balance_query_result.is_empty() {
+            return Err(ServiceAPIError::SenderDoesNotExist);
         }
 
-        Ok(users)
+        Ok(balance_query_result
+            .iter()
+            .map(|row| {
+                let balance = row.get::<_, Amount>("balance");
+                let ledger_balance = row.get::<_, Amount>("ledger_balance");
+
+                LedgerBalance {
+                    id,
+                    asset: row.get("asset"),
+                    balance,
+                    ledger_balance,
+                    consistent: balance == ledger_balance,
+                }
+            })
+            .collect())
     }
 
-    pub async fn create_account(&self, user: User) -> Result<String, ServiceAPIError> {
-        let mut client = self
-            .pool
-            .get()
+    /// Returns the holdings of `filter.asset` matching `filter` in the requested order,
+    /// starting after the cursor `after` if given.
+    pub async fn list_accounts(
+        &self,
+        filter: &AccountFilter,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Page<User>, ServiceAPIError> {
+        filter.validate()?;
+
+        let order = filter.order.unwrap_or_default();
+        let after = after
+            .map(decode_cursor::<2>)
+            .transpose()?
+            .map(|[key, id]| cursor_id(id).map(|id| (key, id)))
+            .transpose()?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let (key, key_type, cmp, dir) = order.sql_fragments();
+        let query = sql::SELECT_ACCOUNTS_PAGE
+            .replace("{key}", key)
+            .replace("{key_type}", key_type)
+            .replace("{cmp}", cmp)
+            .replace("{dir}", dir);
+
+        // one extra row is fetched to find out whether there is a next page
+        let account_query_result = client
+            .query(
+                &query,
+                &[
+                    &filter.asset.clone().unwrap_or_else(default_asset),
+                    &filter.min_balance,
+                    &filter.max_balance,
+                    &after.map(|(key, _)| key),
+                    &after.map(|(_, id)| id),
+                    &(limit as i64 + 1),
+                    &filter.external_ref,
+                ],
+            )
             .await
-            .map_err(|_| ServiceAPIError::ResourceBusy)?;
+            .map_err(|e| ServiceAPIError::database("listing accounts", e))?;
+
+        let mut items = account_query_result
+            .iter()
+            .map(User::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items
+                .last()
+                .map(|user| encode_cursor(&[order.sort_key(user), Decimal::from(user.id)]))
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
+    }
+
+    /// Opens an account with an id allocated by DB and returns its opening holding.
+    pub async fn create_account(
+        &self,
+        account: NewAccount,
+        idempotency_key: Option<IdempotencyHeader>,
+    ) -> Result<User, ServiceAPIError> {
+        account.validate()?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
         let db_transaction = client
             .transaction()
             .await
-            .map_err(|_| ServiceAPIError::DatabaseQueryError)?;
+            .map_err(|e| ServiceAPIError::database("beginning account creation", e))?;
 
-        db_transaction
-            .execute(
+        // a retried request returns the response of the original one instead of failing
+        // with `AccountExists`
+        let idempotency_key = idempotency_key
+            .map(|key| IdempotencyKey::new(key, POST_USER_ROUTE, &account))
+            .transpose()?;
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.replay_response(&db_transaction, key).await? {
+                return Ok(response);
+            }
+        }
+
+        let create_result = db_transaction
+            .query_one(
                 sql::CREATE_NEW_USER,
-                &[&u64_to_bigint(user.id), &u64_to_bigint(user.balance)],
+                &[
+                    &account.asset,
+                    &account.balance,
+                    &account.external_ref,
+                    &account.display_name,
+                    &account.owner_ref,
+                    &account.metadata,
+                    &account.tier.as_deref().unwrap_or(DEFAULT_TIER),
+                ],
             )
             .await
-            .map_err(|_| ServiceAPIError::AccountExists)?;
+            .map_err(|e| match e.code().map(|state| state.code()) {
+                Some(sql::ASSET_DOES_NOT_EXIST_STATE) => ServiceAPIError::AssetDoesNotExist,
+                Some(sql::AMOUNT_PRECISION_STATE) => ServiceAPIError::InvalidAmount,
+                _ => match ServiceAPIError::database("creating account", e) {
+                    ServiceAPIError::UniqueViolation(_) => ServiceAPIError::AccountExists,
+                    e => e,
+                },
+            })?;
+
+        let account_query_result = db_transaction
+            .query_one(
+                sql::SELECT_ACCOUNT_INFO_BY_ID,
+                &[&create_result.get::<_, i64>("_id")],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying created account", e))?;
+        let response = User::from_row(&account_query_result)?;
+
+        if let Some(key) = &idempotency_key {
+            self.record_response(&db_transaction, key, &response)
+                .await?;
+        }
+
         db_transaction
             .commit()
             .await
-            .map_err(|_| ServiceAPIError::ResourceBusy)?;
+            .map_err(|e| ServiceAPIError::database("committing account creation", e))?;
 
-        Ok(String::from("Account created successfully"))
+        Ok(response)
     }
 }
 
+/// `POST_USER_ROUTE` scopes idempotency keys used for creating accounts.
+const POST_USER_ROUTE: &str = "POST /users";
+
+/// [NewAccount] opens an account with `balance` of `asset`. Requests that leave out `asset`
+/// refer to [crate::db::DEFAULT_ASSET]. Ids are allocated by DB, so an `id` is rejected as an
+/// unknown field, whereas `external_ref` keeps a reference of the client's and is unique. `tier`
+/// selects the fee schedules applying to the account, [DEFAULT_TIER] if left out.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewAccount {
+    pub external_ref: Option<String>,
+    pub display_name: Option<String>,
+    pub owner_ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    #[serde(default = "empty_metadata")]
+    pub metadata: serde_json::Value,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub balance: Amount,
+}
+
+impl NewAccount {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        for (field, value) in [
+            ("external_ref", &self.external_ref),
+            ("display_name", &self.display_name),
+            ("owner_ref", &self.owner_ref),
+            ("tier", &self.tier),
+        ] {
+            match value {
+                Some(value) if value.trim().is_empty() => {
+                    errors.push(FieldError::new(field, "cannot be empty"))
+                }
+                Some(value) if value.chars().count() > MAX_TEXT_LENGTH => {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("cannot exceed {} characters", MAX_TEXT_LENGTH),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        if !self.metadata.is_object() {
+            errors.push(FieldError::new("metadata", "must be a JSON object"));
+        } else if self.metadata.to_string().len() > MAX_METADATA_BYTES {
+            errors.push(FieldError::new(
+                "metadata",
+                format!("cannot exceed {} bytes", MAX_METADATA_BYTES),
+            ));
+        }
+
+        check_amount(&mut errors, "balance", self.balance);
+
+        validation_result(errors)
+    }
+}
+
+fn empty_metadata() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+/// `DEFAULT_TIER` is the tier of accounts opened without one.
+pub const DEFAULT_TIER: &str = "standard";
+
+/// `MAX_TEXT_LENGTH` denotes the most characters of the references and display name of an account.
+const MAX_TEXT_LENGTH: usize = 256;
+
+/// `MAX_METADATA_BYTES` denotes the largest metadata of an account, serialized as JSON.
+const MAX_METADATA_BYTES: usize = 16 * 1024;
+
+/// [User] is the balance of an account in one asset, along with the attributes of the account.
+/// `ledger` is the balance on record, of which funds on hold are not `available` for transfers.
+/// `balance` repeats `ledger` for clients predating holds.
 #[derive(Clone, Deserialize, Serialize)]
 pub struct User {
+    // creation order of the account, which random ids do not reflect
+    #[serde(skip)]
+    number: i64,
+    pub id: u64,
+    pub external_ref: Option<String>,
+    pub display_name: Option<String>,
+    pub owner_ref: Option<String>,
+    pub metadata: serde_json::Value,
+    pub tier: String,
+    pub created_at: DateTime<Utc>,
+    pub asset: String,
+    pub balance: Amount,
+    pub ledger: Amount,
+    pub available: Amount,
+}
+
+impl User {
+    fn from_row(row: &Row) -> Result<User, ServiceAPIError> {
+        Ok(User {
+            number: row.get::<_, i64>("number"),
+            id: bigint_to_id(row.get::<_, i64>("id"))?,
+            external_ref: row.get("external_ref"),
+            display_name: row.get("display_name"),
+            owner_ref: row.get("owner_ref"),
+            metadata: row.get("metadata"),
+            tier: row.get("tier"),
+            created_at: row.get::<_, DateTime<Utc>>("created_at"),
+            asset: row.get("asset"),
+            balance: row.get::<_, Amount>("balance"),
+            ledger: row.get::<_, Amount>("balance"),
+            available: row.get::<_, Amount>("available"),
+        })
+    }
+}
+
+/// [LedgerBalance] compares the cached balance of an account with the one derived from the journal.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LedgerBalance {
     pub id: u64,
-    pub balance: u64,
+    pub asset: String,
+    pub balance: Amount,
+    pub ledger_balance: Amount,
+    pub consistent: bool,
+}
+
+/// [AccountFilter] narrows down and orders the accounts returned by [Database::list_accounts].
+/// Accounts are listed by their holding of `asset`, [crate::db::DEFAULT_ASSET] unless given.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AccountFilter {
+    pub asset: Option<String>,
+    pub external_ref: Option<String>,
+    pub min_balance: Option<Amount>,
+    pub max_balance: Option<Amount>,
+    pub order: Option<AccountOrder>,
+}
+
+impl AccountFilter {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        if [self.min_balance, self.max_balance]
+            .into_iter()
+            .flatten()
+            .any(|balance| balance.check().is_err())
+        {
+            return Err(ServiceAPIError::InvalidFilter);
+        }
+
+        if let (Some(min_balance), Some(max_balance)) = (self.min_balance, self.max_balance) {
+            if min_balance > max_balance {
+                return Err(ServiceAPIError::InvalidFilter);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// [AccountOrder] denotes the order in which accounts are listed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountOrder {
+    CreatedAsc,
+    #[default]
+    CreatedDesc,
+    IdAsc,
+    IdDesc,
+    BalanceAsc,
+    BalanceDesc,
+}
+
+impl AccountOrder {
+    /// Returns the sort column and its type, the keyset comparison operator and the sort direction.
+    fn sql_fragments(self) -> (&'static str, &'static str, &'static str, &'static str) {
+        match self {
+            AccountOrder::CreatedAsc => ("number", "BIGINT", ">", "asc"),
+            AccountOrder::CreatedDesc => ("number", "BIGINT", "<", "desc"),
+            AccountOrder::IdAsc => ("account_id", "BIGINT", ">", "asc"),
+            AccountOrder::IdDesc => ("account_id", "BIGINT", "<", "desc"),
+            AccountOrder::BalanceAsc => ("balance", "NUMERIC", ">", "asc"),
+            AccountOrder::BalanceDesc => ("balance", "NUMERIC", "<", "desc"),
+        }
+    }
+
+    fn sort_key(self, user: &User) -> Decimal {
+        match self {
+            AccountOrder::CreatedAsc | AccountOrder::CreatedDesc => Decimal::from(user.number),
+            AccountOrder::IdAsc | AccountOrder::IdDesc => Decimal::from(user.id),
+            AccountOrder::BalanceAsc | AccountOrder::BalanceDesc => user.balance.value(),
+        }
+    }
 }
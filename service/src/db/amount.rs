@@ -0,0 +1,176 @@
+//! Fixed-point amounts of an asset.
+
+use rust_decimal::Decimal;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::{error::Error, fmt};
+use tokio_postgres::types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+/// [Amount] is a non-negative quantity of an asset with at most [Amount::MAX_DECIMALS] decimal
+/// places, stored as `NUMERIC(28, 8)` on DB.
+///
+/// Amounts are serialized as JSON strings, so that clients parsing JSON numbers as doubles do not
+/// lose precision. Strings and integers are accepted on input, whereas fractional JSON numbers and
+/// strings that are not plain decimals are rejected. Deserialized amounts are not checked against
+/// the bounds above until [Amount::check], so that payloads can report every invalid field at once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Amount(Decimal);
+
+impl Amount {
+    /// `MAX_DECIMALS` denotes the most decimal places any asset can be divided into.
+    pub const MAX_DECIMALS: u32 = 8;
+
+    /// `MAX` is the largest amount that fits `NUMERIC(28, 8)`.
+    pub const MAX: Amount = Amount(Decimal::from_parts(
+        0x0FFF_FFFF,
+        0x3E25_0261,
+        0x204F_CE5E,
+        false,
+        8,
+    ));
+
+    pub fn new(value: Decimal) -> Result<Amount, AmountError> {
+        let value = value.normalize();
+        if value.is_sign_negative() && !value.is_zero() {
+            return Err(AmountError::Negative);
+        }
+        if value.scale() > Amount::MAX_DECIMALS {
+            return Err(AmountError::TooManyDecimals);
+        }
+        if value > Amount::MAX.0 {
+            return Err(AmountError::OutOfRange);
+        }
+
+        Ok(Amount(value))
+    }
+
+    /// Checks that a deserialized amount is within the bounds of [Amount].
+    pub fn check(self) -> Result<Amount, AmountError> {
+        Amount::new(self.0)
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn value(self) -> Decimal {
+        self.0
+    }
+
+    /// Parses a plain decimal such as `12.50`, leaving bounds to [Amount::check].
+    fn parse(value: &str) -> Result<Amount, AmountError> {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        // exponents and separators are not amounts a client means to send
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return Err(AmountError::Malformed);
+        }
+
+        match Decimal::from_str_exact(value) {
+            Ok(value) => Ok(Amount(value.normalize())),
+            // saturated values still fail the check, rather than being rounded into range
+            Err(rust_decimal::Error::ExceedsMaximumPossibleValue) => Ok(Amount(Decimal::MAX)),
+            Err(rust_decimal::Error::LessThanMinimumPossibleValue) => Ok(Amount(Decimal::MIN)),
+            Err(rust_decimal::Error::Underflow) => Err(AmountError::TooManyDecimals),
+            Err(_) => Err(AmountError::Malformed),
+        }
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(value: u64) -> Amount {
+        Amount(Decimal::from(value))
+    }
+}
+
+/// [AmountError] denotes why a value is not a valid [Amount].
+#[derive(Debug, PartialEq, Eq)]
+pub enum AmountError {
+    Negative,
+    TooManyDecimals,
+    OutOfRange,
+    Malformed,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::Negative => write!(f, "amount cannot be negative"),
+            AmountError::TooManyDecimals => write!(
+                f,
+                "amount cannot have more than {} decimal places",
+                Amount::MAX_DECIMALS
+            ),
+            AmountError::OutOfRange => write!(f, "amount cannot exceed {}", Amount::MAX),
+            AmountError::Malformed => write!(f, "amount must be a decimal number"),
+        }
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        struct AmountVisitor;
+
+        impl<'de> Visitor<'de> for AmountVisitor {
+            type Value = Amount;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a decimal string or an integer")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Amount, E> {
+                Amount::parse(value).map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Amount, E> {
+                Ok(Amount::from(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Amount, E> {
+                Ok(Amount(Decimal::from(value)))
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Amount, E> {
+                Err(E::custom(
+                    "fractional amounts must be sent as strings to keep their precision",
+                ))
+            }
+        }
+
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+impl<'a> FromSql<'a> for Amount {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Amount, Box<dyn Error + Sync + Send>> {
+        // values on DB are within `NUMERIC(28, 8)`, so they are read back as they are. Balances of
+        // accounts allowed an overdraft are the only negative ones.
+        Ok(Amount(Decimal::from_sql(ty, raw)?.normalize()))
+    }
+
+    accepts!(NUMERIC);
+}
+
+impl ToSql for Amount {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        self.0.to_sql(ty, out)
+    }
+
+    accepts!(NUMERIC);
+    to_sql_checked!();
+}
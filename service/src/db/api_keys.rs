@@ -0,0 +1,148 @@
+//! Helpers issuing, revoking and checking the API keys that clients authenticate with.
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_postgres::Row;
+
+use crate::db::{bigint_to_id, id_to_bigint, sql, validation_result, Database};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+impl Database {
+    /// Issues a new key named `name`, reaching the admin endpoints too if `admin`. The key itself
+    /// is returned this once, as only its hash is stored.
+    pub async fn create_api_key(
+        &self,
+        name: &str,
+        admin: bool,
+    ) -> Result<NewApiKey, ServiceAPIError> {
+        let mut errors = Vec::new();
+        if name.trim().is_empty() || name.chars().count() > MAX_API_KEY_NAME_LENGTH {
+            errors.push(FieldError::new(
+                "name",
+                format!(
+                    "must hold between 1 and {} characters",
+                    MAX_API_KEY_NAME_LENGTH
+                ),
+            ));
+        }
+        validation_result(errors)?;
+
+        // two random UUIDs make up 244 random bits
+        let key = format!(
+            "{}{}{}",
+            API_KEY_PREFIX,
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let prefix = &key[..API_KEY_PREFIX.len() + SHOWN_KEY_CHARACTERS];
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let api_key_query_result = client
+            .query_one(
+                sql::CREATE_NEW_API_KEY,
+                &[&name, &prefix, &hash_api_key(&key), &admin],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("creating API key", e))?;
+
+        Ok(NewApiKey {
+            api_key: ApiKey::from_row(&api_key_query_result)?,
+            key,
+        })
+    }
+
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKey>, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let api_key_query_result = client
+            .query(sql::SELECT_API_KEYS, &[])
+            .await
+            .map_err(|e| ServiceAPIError::database("listing API keys", e))?;
+
+        api_key_query_result.iter().map(ApiKey::from_row).collect()
+    }
+
+    /// Revokes the key `id` for good, returning `None` if there is no such key.
+    pub async fn revoke_api_key(&self, id: u64) -> Result<Option<ApiKey>, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let api_key_query_result = client
+            .query_opt(sql::REVOKE_API_KEY, &[&id_to_bigint(id)?])
+            .await
+            .map_err(|e| ServiceAPIError::database("revoking API key", e))?;
+
+        api_key_query_result
+            .as_ref()
+            .map(ApiKey::from_row)
+            .transpose()
+    }
+
+    /// Returns the key `key`, unless it is unknown or has been revoked.
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let api_key_query_result = client
+            .query_opt(sql::SELECT_ACTIVE_API_KEY, &[&hash_api_key(key)])
+            .await
+            .map_err(|e| ServiceAPIError::database("authenticating API key", e))?;
+
+        match api_key_query_result {
+            Some(row) => ApiKey::from_row(&row),
+            None => Err(ServiceAPIError::Unauthorized),
+        }
+    }
+}
+
+/// [ApiKey] describes an issued key without revealing it. `prefix` holds its first characters,
+/// so that operators can tell keys apart.
+#[derive(Deserialize, Serialize)]
+pub struct ApiKey {
+    pub id: u64,
+    pub name: String,
+    pub prefix: String,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub admin: bool,
+}
+
+impl ApiKey {
+    fn from_row(row: &Row) -> Result<ApiKey, ServiceAPIError> {
+        Ok(ApiKey {
+            id: bigint_to_id(row.get::<_, i64>("number"))?,
+            name: row.get("name"),
+            prefix: row.get("prefix"),
+            created_at: row.get::<_, DateTime<Utc>>("created_at"),
+            revoked_at: row.get::<_, Option<DateTime<Utc>>>("revoked_at"),
+            admin: row.get("admin"),
+        })
+    }
+}
+
+/// [NewApiKey] is a key just issued, along with the secret clients send as a bearer token.
+#[derive(Deserialize, Serialize)]
+pub struct NewApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+/// Helpers
+///
+/// Keys are random enough for a single round of SHA-256 to protect them at rest.
+fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// `API_KEY_PREFIX` starts every key, so that leaked keys are easy to spot.
+const API_KEY_PREFIX: &str = "tocos_";
+
+/// `SHOWN_KEY_CHARACTERS` is the number of random characters of a key kept in the clear.
+const SHOWN_KEY_CHARACTERS: usize = 8;
+
+/// `MAX_API_KEY_NAME_LENGTH` bounds the names of keys, as DB does.
+const MAX_API_KEY_NAME_LENGTH: usize = 64;
@@ -0,0 +1,76 @@
+//! Helpers processing HTTP requests related to the asset registry.
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::db::{sql, validation_result, Database};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+impl Database {
+    pub async fn list_assets(&self) -> Result<Vec<Asset>, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let asset_query_result = client
+            .query(sql::SELECT_ASSETS, &[])
+            .await
+            .map_err(|e| ServiceAPIError::database("listing assets", e))?;
+
+        Ok(asset_query_result
+            .iter()
+            .map(|row| Asset {
+                symbol: row.get("symbol"),
+                decimals: row.get::<_, i16>("decimals") as u8,
+                name: row.get("name"),
+                issuer: row.get("issuer"),
+            })
+            .collect())
+    }
+
+    /// Registers `asset`, unless the fee schedules charging it are finer than its decimals.
+    pub async fn create_asset(&self, asset: Asset) -> Result<Asset, ServiceAPIError> {
+        let mut errors = Vec::new();
+        if let Err(e) = self
+            .fee_policy
+            .check_decimals(&asset.symbol, u32::from(asset.decimals))
+        {
+            errors.push(FieldError::new("decimals", e));
+        }
+        validation_result(errors)?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        client
+            .execute(
+                sql::CREATE_NEW_ASSET,
+                &[
+                    &asset.symbol,
+                    &i16::from(asset.decimals),
+                    &asset.name,
+                    &asset.issuer,
+                ],
+            )
+            .await
+            .map_err(|e| match ServiceAPIError::database("creating asset", e) {
+                ServiceAPIError::UniqueViolation(_) => ServiceAPIError::AssetExists,
+                e => e,
+            })?;
+
+        Ok(asset)
+    }
+}
+
+/// [Asset] is an entry of the asset registry. Amounts of an asset are counted in units of
+/// `10^-decimals`.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Asset {
+    pub symbol: String,
+    pub decimals: u8,
+    pub name: String,
+    pub issuer: Option<String>,
+}
+
+/// `DEFAULT_ASSET` is the asset that requests not naming one refer to, registered on setup.
+pub const DEFAULT_ASSET: &str = "TOCOS";
+
+pub fn default_asset() -> String {
+    DEFAULT_ASSET.to_string()
+}
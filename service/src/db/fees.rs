@@ -0,0 +1,262 @@
+//! Methods charging fees on transfers according to configurable fee schedules.
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::GenericClient;
+
+use crate::db::{id_to_bigint, sql, Amount, Database, Transaction};
+use crate::error_codes::Error as ServiceAPIError;
+
+impl Database {
+    /// Returns the fee `tx` would cost its sender, without moving any funds.
+    pub async fn quote_tx(&self, tx: Transaction) -> Result<FeeQuote, ServiceAPIError> {
+        tx.validate()?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let basis_query_result = client
+            .query_one(
+                sql::SELECT_FEE_BASIS,
+                &[&id_to_bigint(tx.from_id)?, &tx.asset],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying fee basis", e))?;
+        if basis_query_result
+            .get::<_, Option<String>>("tier")
+            .is_none()
+        {
+            return Err(ServiceAPIError::SenderDoesNotExist);
+        }
+        if basis_query_result
+            .get::<_, Option<i16>>("decimals")
+            .is_none()
+        {
+            return Err(ServiceAPIError::AssetDoesNotExist);
+        }
+
+        let fee = self.fee_for(&*client, &tx).await?;
+
+        Ok(FeeQuote {
+            from_id: tx.from_id,
+            asset: tx.asset,
+            amount: tx.amount,
+            fee,
+            total: Amount::new(tx.amount.value() + fee.value())
+                .map_err(|_| ServiceAPIError::AmountOutOfRange)?,
+        })
+    }
+
+    /// Returns the fee the sender of `tx` pays under [FeePolicy]. Splits are charged on the whole
+    /// amount debited, across every leg.
+    pub async fn fee_for<C: GenericClient>(
+        &self,
+        client: &C,
+        tx: &Transaction,
+    ) -> Result<Amount, ServiceAPIError> {
+        self.fee_on(client, tx.from_id, &tx.asset, tx.amount.value())
+            .await
+    }
+
+    /// Returns the fee `from_id` pays under [FeePolicy] for sending `amount` of `asset`. Senders or
+    /// assets that do not exist are not charged, as the transfer itself rejects them.
+    pub async fn fee_on<C: GenericClient>(
+        &self,
+        client: &C,
+        from_id: u64,
+        asset: &str,
+        amount: Decimal,
+    ) -> Result<Amount, ServiceAPIError> {
+        if self.fee_policy.schedules.is_empty() {
+            return Ok(Amount::default());
+        }
+
+        let basis_query_result = client
+            .query_one(sql::SELECT_FEE_BASIS, &[&id_to_bigint(from_id)?, &asset])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying fee basis", e))?;
+        let (tier, decimals) = match (
+            basis_query_result.get::<_, Option<String>>("tier"),
+            basis_query_result.get::<_, Option<i16>>("decimals"),
+        ) {
+            (Some(tier), Some(decimals)) => (tier, decimals as u32),
+            _ => return Ok(Amount::default()),
+        };
+
+        Amount::new(self.fee_policy.fee(asset, &tier, amount, decimals))
+            .map_err(|_| ServiceAPIError::AmountOutOfRange)
+    }
+}
+
+/// [FeePolicy] is the set of fee schedules charging transfers, along with the `external_ref` of
+/// the account credited with the fees.
+#[derive(Clone, Debug, Default)]
+pub struct FeePolicy {
+    pub account_ref: Option<String>,
+    pub schedules: Vec<FeeSchedule>,
+}
+
+impl FeePolicy {
+    /// Returns the fee for sending `amount` of `asset` from an account of `tier`, rounded up to
+    /// `decimals` places. Schedules naming `tier` take precedence over those for any tier.
+    pub fn fee(&self, asset: &str, tier: &str, amount: Decimal, decimals: u32) -> Decimal {
+        let for_asset = || self.schedules.iter().filter(|s| s.asset == asset);
+        let schedule = match for_asset()
+            .find(|s| s.tier.as_deref() == Some(tier))
+            .or_else(|| for_asset().find(|s| s.tier.is_none()))
+        {
+            Some(schedule) => schedule,
+            None => return Decimal::ZERO,
+        };
+
+        // the band with the highest threshold not above `amount` applies, if any
+        let (flat, percentage) = schedule
+            .bands
+            .iter()
+            .rev()
+            .find(|band| band.from_amount.value() <= amount)
+            .map_or((schedule.flat, schedule.percentage), |band| {
+                (band.flat, band.percentage)
+            });
+
+        let mut fee = (flat.value() + amount * percentage.value() / Decimal::ONE_HUNDRED)
+            .round_dp_with_strategy(decimals, RoundingStrategy::AwayFromZero);
+        if let Some(min_fee) = schedule.min_fee {
+            fee = fee.max(min_fee.value());
+        }
+        if let Some(max_fee) = schedule.max_fee {
+            fee = fee.min(max_fee.value());
+        }
+
+        fee.normalize()
+    }
+
+    /// Checks that the schedules are sound, as read from `config.toml`.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.schedules.is_empty() && self.account_ref.is_none() {
+            return Err("fee_account_ref is required along with fee_schedules".to_string());
+        }
+
+        for schedule in &self.schedules {
+            let name = format!(
+                "fee schedule of {} for {} tier",
+                schedule.asset,
+                schedule.tier.as_deref().unwrap_or("any")
+            );
+
+            let amounts = [schedule.flat, schedule.percentage]
+                .into_iter()
+                .chain(schedule.min_fee)
+                .chain(schedule.max_fee)
+                .chain(
+                    schedule
+                        .bands
+                        .iter()
+                        .flat_map(|band| [band.from_amount, band.flat, band.percentage]),
+                );
+            for amount in amounts {
+                if let Err(e) = amount.check() {
+                    return Err(format!("{} is invalid: {}", name, e));
+                }
+            }
+
+            if schedule.percentage.value() > Decimal::ONE_HUNDRED
+                || (schedule.bands.iter()).any(|b| b.percentage.value() > Decimal::ONE_HUNDRED)
+            {
+                return Err(format!("{} charges more than 100 percent", name));
+            }
+            if let (Some(min_fee), Some(max_fee)) = (schedule.min_fee, schedule.max_fee) {
+                if min_fee > max_fee {
+                    return Err(format!("{} has a min_fee above its max_fee", name));
+                }
+            }
+            if schedule
+                .bands
+                .windows(2)
+                .any(|bands| bands[0].from_amount >= bands[1].from_amount)
+            {
+                return Err(format!("{} lists bands out of order", name));
+            }
+
+            let duplicates = self
+                .schedules
+                .iter()
+                .filter(|s| s.asset == schedule.asset && s.tier == schedule.tier)
+                .count();
+            if duplicates > 1 {
+                return Err(format!("{} is given more than once", name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the amounts the schedules of `asset` charge and set bands from fit its
+    /// `decimals`, as fees that do not would fail every transfer of the asset.
+    pub fn check_decimals(&self, asset: &str, decimals: u32) -> Result<(), String> {
+        for schedule in self.schedules.iter().filter(|s| s.asset == asset) {
+            let amounts = [schedule.flat]
+                .into_iter()
+                .chain(schedule.min_fee)
+                .chain(schedule.max_fee)
+                .chain(
+                    schedule
+                        .bands
+                        .iter()
+                        .flat_map(|band| [band.from_amount, band.flat]),
+                );
+            for amount in amounts {
+                if amount.value().scale() > decimals {
+                    return Err(format!(
+                        "fee schedule of {} for {} tier has {}, finer than the {} decimals of {}",
+                        schedule.asset,
+                        schedule.tier.as_deref().unwrap_or("any"),
+                        amount.value(),
+                        decimals,
+                        asset
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// [FeeSchedule] charges transfers of `asset` sent by accounts of `tier`, or of any tier if left
+/// out. The fee is `flat` plus `percentage` of the amount, or what the band the amount falls into
+/// charges instead, bounded by `min_fee` and `max_fee`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeSchedule {
+    pub asset: String,
+    pub tier: Option<String>,
+    #[serde(default)]
+    pub flat: Amount,
+    #[serde(default)]
+    pub percentage: Amount,
+    pub min_fee: Option<Amount>,
+    pub max_fee: Option<Amount>,
+    #[serde(default)]
+    pub bands: Vec<FeeBand>,
+}
+
+/// [FeeBand] charges `flat` plus `percentage` of amounts from `from_amount` up to the next band.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeBand {
+    pub from_amount: Amount,
+    #[serde(default)]
+    pub flat: Amount,
+    #[serde(default)]
+    pub percentage: Amount,
+}
+
+/// [FeeQuote] is what a transfer would cost its sender: `amount` along with the `fee` on top.
+#[derive(Deserialize, Serialize)]
+pub struct FeeQuote {
+    pub from_id: u64,
+    pub asset: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub total: Amount,
+}
@@ -0,0 +1,347 @@
+//! Methods processing HTTP requests related to holds, which reserve funds for a transfer that is
+//! captured or voided later on.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::Row;
+
+use crate::db::{
+    bigint_to_id, check_transfer, default_asset, error_detail, id_to_bigint, sql, transfer_error,
+    validation_result, Amount, Database, IdempotencyHeader, IdempotencyKey,
+};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+impl Database {
+    /// Reserves `hold.amount` of the sender's balance, checked as [Database::post_tx] checks a
+    /// transfer. Reserved funds are no longer available, yet stay on the ledger until captured.
+    pub async fn place_hold(
+        &self,
+        hold: NewHold,
+        idempotency_key: Option<IdempotencyHeader>,
+    ) -> Result<HoldRecord, ServiceAPIError> {
+        hold.validate()?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning hold", e))?;
+
+        let idempotency_key = idempotency_key
+            .map(|key| IdempotencyKey::new(key, POST_HOLD_ROUTE, &hold))
+            .transpose()?;
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.replay_response(&db_transaction, key).await? {
+                return Ok(response);
+            }
+        }
+
+        let ttl_secs = hold.ttl_secs.unwrap_or(self.hold_ttl_secs);
+        let hold_result = db_transaction
+            .query_one(
+                sql::CREATE_NEW_HOLD,
+                &[
+                    &id_to_bigint(hold.from_id)?,
+                    &id_to_bigint(hold.to_id)?,
+                    &hold.asset,
+                    &hold.amount,
+                    &self.balance_policy.min_balance,
+                    &self.balance_policy.overdraft_limit,
+                    &(ttl_secs as i64),
+                ],
+            )
+            .await
+            .map_err(|e| transfer_error(e, &hold.asset))?;
+
+        let hold_query_result = db_transaction
+            .query_one(
+                sql::SELECT_HOLD_BY_NUMBER,
+                &[&hold_result.get::<_, i64>("_number")],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying created hold", e))?;
+        let response = HoldRecord::from_row(&hold_query_result)?;
+
+        if let Some(key) = &idempotency_key {
+            self.record_response(&db_transaction, key, &response)
+                .await?;
+        }
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing hold", e))?;
+
+        Ok(response)
+    }
+
+    pub async fn get_hold(&self, id: u64) -> Result<HoldRecord, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let hold_query_result = client
+            .query_opt(sql::SELECT_HOLD_BY_NUMBER, &[&id_to_bigint(id)?])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying hold", e))?
+            .ok_or(ServiceAPIError::HoldDoesNotExist)?;
+
+        HoldRecord::from_row(&hold_query_result)
+    }
+
+    /// Transfers `capture.amount` of the funds on hold to its recipient, or all of them if left
+    /// out, and releases the rest.
+    pub async fn capture_hold(
+        &self,
+        id: u64,
+        capture: Capture,
+    ) -> Result<HoldRecord, ServiceAPIError> {
+        capture.validate()?;
+
+        self.settle_hold(id, HoldStatus::Captured, capture.amount)
+            .await
+    }
+
+    /// Releases the funds on hold without transferring any of them.
+    pub async fn void_hold(&self, id: u64) -> Result<HoldRecord, ServiceAPIError> {
+        self.settle_hold(id, HoldStatus::Voided, None).await
+    }
+
+    /// Releases the funds of holds past their expiry, up to [EXPIRED_HOLDS_BATCH] of them, and
+    /// returns how many were released. Each hold is released in a DB transaction of its own.
+    pub async fn expire_holds(&self) -> Result<usize, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let expired_query_result = client
+            .query(sql::SELECT_EXPIRED_HOLDS, &[&EXPIRED_HOLDS_BATCH])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying expired holds", e))?;
+        drop(client);
+
+        let mut released = 0;
+        for row in expired_query_result {
+            let id = bigint_to_id(row.get::<_, i64>("number"))?;
+
+            match self.settle_hold(id, HoldStatus::Expired, None).await {
+                Ok(_) => released += 1,
+                // the hold was captured or voided in the meantime
+                Err(ServiceAPIError::HoldNotPending(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(released)
+    }
+
+    async fn settle_hold(
+        &self,
+        id: u64,
+        status: HoldStatus,
+        amount: Option<Amount>,
+    ) -> Result<HoldRecord, ServiceAPIError> {
+        let number = id_to_bigint(id)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning hold settlement", e))?;
+
+        let hold_query_result = db_transaction
+            .query_opt(sql::SELECT_HOLD_BY_NUMBER, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying hold", e))?
+            .ok_or(ServiceAPIError::HoldDoesNotExist)?;
+        let hold = HoldRecord::from_row(&hold_query_result)?;
+
+        // captures are charged on the amount captured, the whole hold unless given
+        let fee = match status {
+            HoldStatus::Captured => {
+                let captured = amount.unwrap_or(hold.amount).value();
+                self.fee_on(&db_transaction, hold.from_id, &hold.asset, captured)
+                    .await?
+            }
+            _ => Amount::default(),
+        };
+
+        db_transaction
+            .execute(
+                sql::SETTLE_HOLD,
+                &[
+                    &number,
+                    &status.as_db(),
+                    &amount,
+                    &self.balance_policy.min_balance,
+                    &self.balance_policy.overdraft_limit,
+                    &fee,
+                    &self.fee_policy.account_ref,
+                ],
+            )
+            .await
+            .map_err(|e| match e.code().map(|state| state.code()) {
+                Some(sql::HOLD_DOES_NOT_EXIST_STATE) => ServiceAPIError::HoldDoesNotExist,
+                Some(sql::FEE_ACCOUNT_DOES_NOT_EXIST_STATE) => ServiceAPIError::FeeAccountMissing,
+                Some(sql::NOT_ENOUGH_BALANCE_STATE) => transfer_error(e, &hold.asset),
+                Some(sql::HOLD_NOT_PENDING_STATE) => {
+                    ServiceAPIError::HoldNotPending(error_detail(&e))
+                }
+                Some(sql::CAPTURE_EXCEEDS_HOLD_STATE) => ServiceAPIError::CaptureExceedsHold(
+                    error_detail::<Decimal>(&e).map(|held| held.normalize()),
+                ),
+                Some(sql::ACCOUNT_FROZEN_STATE) => ServiceAPIError::AccountFrozen(error_detail(&e)),
+                Some(sql::ACCOUNT_CLOSED_STATE) => ServiceAPIError::AccountClosed(error_detail(&e)),
+                Some(sql::AMOUNT_PRECISION_STATE) => ServiceAPIError::InvalidAmount,
+                _ => ServiceAPIError::database("settling hold", e),
+            })?;
+
+        let hold_query_result = db_transaction
+            .query_one(sql::SELECT_HOLD_BY_NUMBER, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying settled hold", e))?;
+        let response = HoldRecord::from_row(&hold_query_result)?;
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing hold settlement", e))?;
+
+        Ok(response)
+    }
+}
+
+/// [NewHold] reserves `amount` of `asset` for a transfer from one account to another, for
+/// `ttl_secs` seconds or [crate::config::Config::hold_ttl_secs] if left out.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewHold {
+    pub from_id: u64,
+    pub to_id: u64,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub amount: Amount,
+    pub ttl_secs: Option<u64>,
+}
+
+impl NewHold {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        check_transfer(&mut errors, self.from_id, self.to_id, self.amount);
+        if let Some(ttl_secs) = self.ttl_secs {
+            if ttl_secs == 0 || ttl_secs > MAX_HOLD_TTL_SECS {
+                errors.push(FieldError::new(
+                    "ttl_secs",
+                    format!("must be between 1 and {}", MAX_HOLD_TTL_SECS),
+                ));
+            }
+        }
+
+        validation_result(errors)
+    }
+}
+
+/// [Capture] transfers `amount` of the funds on hold, or all of them if left out.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Capture {
+    pub amount: Option<Amount>,
+}
+
+impl Capture {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        match self.amount.map(Amount::check) {
+            Some(Ok(amount)) if amount.is_zero() => {
+                errors.push(FieldError::new("amount", "must be greater than zero"))
+            }
+            Some(Err(e)) => errors.push(FieldError::new("amount", e)),
+            _ => {}
+        }
+
+        validation_result(errors)
+    }
+}
+
+/// [HoldRecord] is a hold as stored on DB. Captured holds name the transaction they resulted in.
+#[derive(Deserialize, Serialize)]
+pub struct HoldRecord {
+    pub id: u64,
+    pub from_id: u64,
+    pub to_id: u64,
+    pub asset: String,
+    pub amount: Amount,
+    pub status: HoldStatus,
+    pub captured_amount: Option<Amount>,
+    pub transaction_id: Option<u64>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub settled_at: Option<DateTime<Utc>>,
+}
+
+impl HoldRecord {
+    fn from_row(row: &Row) -> Result<HoldRecord, ServiceAPIError> {
+        Ok(HoldRecord {
+            id: bigint_to_id(row.get::<_, i64>("number"))?,
+            from_id: bigint_to_id(row.get::<_, i64>("from_id"))?,
+            to_id: bigint_to_id(row.get::<_, i64>("to_id"))?,
+            asset: row.get("asset"),
+            amount: row.get::<_, Amount>("amount"),
+            status: HoldStatus::from_db(row.get::<_, &str>("status"))?,
+            captured_amount: row.get::<_, Option<Amount>>("captured_amount"),
+            transaction_id: row
+                .get::<_, Option<i64>>("transaction_number")
+                .map(bigint_to_id)
+                .transpose()?,
+            expires_at: row.get::<_, DateTime<Utc>>("expires_at"),
+            created_at: row.get::<_, DateTime<Utc>>("created_at"),
+            settled_at: row.get::<_, Option<DateTime<Utc>>>("settled_at"),
+        })
+    }
+}
+
+/// [HoldStatus] denotes whether the funds of a hold are still reserved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldStatus {
+    /// Funds are reserved and can be captured or voided.
+    Pending,
+    /// Funds, or part of them, have been transferred to the recipient.
+    Captured,
+    /// Funds have been released on request.
+    Voided,
+    /// Funds have been released as the hold was not settled in time.
+    Expired,
+}
+
+impl HoldStatus {
+    fn as_db(self) -> &'static str {
+        match self {
+            HoldStatus::Pending => "pending",
+            HoldStatus::Captured => "captured",
+            HoldStatus::Voided => "voided",
+            HoldStatus::Expired => "expired",
+        }
+    }
+
+    fn from_db(status: &str) -> Result<HoldStatus, ServiceAPIError> {
+        match status {
+            "pending" => Ok(HoldStatus::Pending),
+            "captured" => Ok(HoldStatus::Captured),
+            "voided" => Ok(HoldStatus::Voided),
+            "expired" => Ok(HoldStatus::Expired),
+            _ => {
+                log::error!("unknown hold status on record: {}", status);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// `POST_HOLD_ROUTE` scopes idempotency keys used for placing holds.
+const POST_HOLD_ROUTE: &str = "POST /holds";
+
+/// `MAX_HOLD_TTL_SECS` denotes the longest a hold can reserve funds for.
+const MAX_HOLD_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// `EXPIRED_HOLDS_BATCH` denotes the most expired holds released by one [Database::expire_holds].
+const EXPIRED_HOLDS_BATCH: i64 = 100;
@@ -0,0 +1,115 @@
+//! Helpers deduplicating retried requests that carry an `Idempotency-Key` header.
+
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_postgres::Transaction as DbTransaction;
+
+use crate::db::{id_to_bigint, sql, Database};
+use crate::error_codes::Error as ServiceAPIError;
+
+impl Database {
+    /// Returns the response recorded for `key` by the same API key within the idempotency
+    /// window, if any.
+    ///
+    /// Must be called inside the DB transaction that performs the request, as it holds
+    /// a lock on the key until that transaction ends.
+    pub async fn replay_response<T: DeserializeOwned>(
+        &self,
+        db_transaction: &DbTransaction<'_>,
+        key: &IdempotencyKey,
+    ) -> Result<Option<T>, ServiceAPIError> {
+        let api_key = id_to_bigint(key.api_key_id)?;
+
+        db_transaction
+            .execute(sql::LOCK_IDEMPOTENCY_KEY, &[&api_key, &key.key])
+            .await
+            .map_err(|e| ServiceAPIError::database("locking idempotency key", e))?;
+
+        let query_result = db_transaction
+            .query(
+                sql::SELECT_IDEMPOTENT_RESPONSE,
+                &[&api_key, &key.key, &(self.idempotency_window_secs as f64)],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying idempotent response", e))?;
+
+        let row = match query_result.first() {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        if row.get::<_, String>("request_hash") != key.request_hash {
+            return Err(ServiceAPIError::IdempotencyKeyConflict);
+        }
+
+        serde_json::from_str(row.get::<_, &str>("response"))
+            .map(Some)
+            .map_err(|_| ServiceAPIError::SerializationFailure)
+    }
+
+    /// Records `response` against `key`, so that replays of the request return it.
+    pub async fn record_response<T: Serialize>(
+        &self,
+        db_transaction: &DbTransaction<'_>,
+        key: &IdempotencyKey,
+        response: &T,
+    ) -> Result<(), ServiceAPIError> {
+        let response =
+            serde_json::to_string(response).map_err(|_| ServiceAPIError::SerializationFailure)?;
+
+        db_transaction
+            .execute(
+                sql::UPSERT_IDEMPOTENT_RESPONSE,
+                &[
+                    &id_to_bigint(key.api_key_id)?,
+                    &key.key,
+                    &key.request_hash,
+                    &response,
+                ],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("recording idempotent response", e))?;
+
+        Ok(())
+    }
+}
+
+/// [IdempotencyHeader] is the `Idempotency-Key` header of a request, along with the API key it
+/// was sent with, as every API key picks its idempotency keys apart from the others.
+pub struct IdempotencyHeader {
+    pub api_key_id: u64,
+    pub key: String,
+}
+
+/// [IdempotencyKey] pairs a client-supplied key with a hash of the request it was sent with.
+pub struct IdempotencyKey {
+    pub api_key_id: u64,
+    pub key: String,
+    pub request_hash: String,
+}
+
+impl IdempotencyKey {
+    pub fn new<T: Serialize>(
+        header: IdempotencyHeader,
+        route: &str,
+        request: &T,
+    ) -> Result<IdempotencyKey, ServiceAPIError> {
+        let body =
+            serde_json::to_vec(request).map_err(|_| ServiceAPIError::SerializationFailure)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(route.as_bytes());
+        hasher.update(body);
+        let request_hash = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(IdempotencyKey {
+            api_key_id: header.api_key_id,
+            key: header.key,
+            request_hash,
+        })
+    }
+}
@@ -0,0 +1,420 @@
+//! Methods processing HTTP requests related to interest products, and accruing the interest they
+//! pay on the balances of the accounts attached to them.
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::{error::SqlState, Row};
+
+use crate::db::{
+    bigint_to_id, check_amount, default_asset, id_to_bigint, sql, validation_result, Amount,
+    Database,
+};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+impl Database {
+    pub async fn list_interest_products(&self) -> Result<Vec<InterestProduct>, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let product_query_result = client
+            .query(sql::SELECT_INTEREST_PRODUCTS, &[])
+            .await
+            .map_err(|e| ServiceAPIError::database("listing interest products", e))?;
+
+        product_query_result
+            .iter()
+            .map(InterestProduct::from_row)
+            .collect()
+    }
+
+    pub async fn create_interest_product(
+        &self,
+        product: InterestProduct,
+    ) -> Result<InterestProduct, ServiceAPIError> {
+        product.validate()?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        client
+            .execute(
+                sql::CREATE_NEW_INTEREST_PRODUCT,
+                &[
+                    &product.code,
+                    &product.asset,
+                    &product.annual_rate,
+                    &product.compounding.as_db(),
+                    &product.accrual_basis.as_db(),
+                ],
+            )
+            .await
+            .map_err(|e| {
+                if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) {
+                    return ServiceAPIError::AssetDoesNotExist;
+                }
+                match ServiceAPIError::database("creating interest product", e) {
+                    ServiceAPIError::UniqueViolation(_) => ServiceAPIError::InterestProductExists,
+                    e => e,
+                }
+            })?;
+
+        Ok(product)
+    }
+
+    /// Returns the interest product the account `id` earns interest under, along with what it has
+    /// accrued and been paid.
+    pub async fn get_account_interest(&self, id: u64) -> Result<AccountInterest, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let interest_query_result = client
+            .query_opt(sql::SELECT_ACCOUNT_INTEREST, &[&id_to_bigint(id)?])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying account interest", e))?
+            .ok_or(ServiceAPIError::SenderDoesNotExist)?;
+
+        let payments_query_result = client
+            .query(
+                sql::SELECT_INTEREST_PAYMENTS,
+                &[&id_to_bigint(id)?, &RECENT_INTEREST_PAYMENTS],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying interest payments", e))?;
+
+        Ok(AccountInterest {
+            id,
+            product: interest_query_result.get("interest_product"),
+            since: interest_query_result.get("interest_since"),
+            accrued_through: interest_query_result.get("accrued_through"),
+            accrued: Amount::new(interest_query_result.get::<_, Decimal>("accrued"))
+                .map_err(|_| ServiceAPIError::AmountOutOfRange)?,
+            payments: payments_query_result
+                .iter()
+                .map(InterestPaymentRecord::from_row)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Attaches the account `id` to an interest product, which it earns interest under from the
+    /// current day on, or detaches it from its product. The days over that the account has not
+    /// accrued interest for yet are accrued under its former product first, and interest accrued
+    /// already is still paid.
+    pub async fn set_account_interest(
+        &self,
+        id: u64,
+        enrollment: InterestEnrollment,
+    ) -> Result<AccountInterest, ServiceAPIError> {
+        let number = id_to_bigint(id)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning interest enrollment", e))?;
+
+        let first_day = db_transaction
+            .query_opt(sql::SELECT_ACCOUNT_FIRST_UNACCRUED_DAY, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying first unaccrued day", e))?
+            .and_then(|row| row.get::<_, Option<NaiveDate>>("day"));
+        if let Some(mut day) = first_day {
+            let yesterday = Utc::now().date_naive() - Duration::days(1);
+            while day <= yesterday {
+                db_transaction
+                    .query_one(sql::ACCRUE_INTEREST, &[&day, &Some(number)])
+                    .await
+                    .map_err(|e| ServiceAPIError::database("accruing interest", e))?;
+                day += Duration::days(1);
+            }
+        }
+
+        db_transaction
+            .query_opt(
+                sql::UPDATE_ACCOUNT_INTEREST,
+                &[&number, &enrollment.product],
+            )
+            .await
+            .map_err(|e| {
+                if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) {
+                    return ServiceAPIError::InterestProductDoesNotExist;
+                }
+                ServiceAPIError::database("updating account interest", e)
+            })?
+            .ok_or(ServiceAPIError::SenderDoesNotExist)?;
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing interest enrollment", e))?;
+
+        self.get_account_interest(id).await
+    }
+
+    /// Accrues interest for every day up to `through`, which defaults to the day before and has
+    /// to be over, and pays the interest of every month over by then. Every day is accrued in a
+    /// database transaction of its own, and neither days nor months are paid twice, so that runs
+    /// can be repeated and resume where a failed one stopped.
+    pub async fn accrue_interest(
+        &self,
+        through: Option<NaiveDate>,
+    ) -> Result<InterestRun, ServiceAPIError> {
+        let today = Utc::now().date_naive();
+        let through = through.unwrap_or(today - Duration::days(1));
+        if through >= today {
+            return Err(ServiceAPIError::ValidationFailed(vec![FieldError::new(
+                "through",
+                "must be a day that is over",
+            )]));
+        }
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let first_day = client
+            .query_one(sql::SELECT_FIRST_UNACCRUED_DAY, &[])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying first unaccrued day", e))?
+            .get::<_, Option<NaiveDate>>("day");
+
+        let mut run = InterestRun {
+            through,
+            accruals: 0,
+            payments: 0,
+        };
+
+        let mut day = first_day.unwrap_or(through + Duration::days(1));
+        while day <= through {
+            let db_transaction = client
+                .transaction()
+                .await
+                .map_err(|e| ServiceAPIError::database("beginning interest accrual", e))?;
+
+            // months are paid before the next one accrues, as paid interest earns interest itself
+            if day.day() == 1 {
+                run.payments += self
+                    .pay_interest(&db_transaction, day - Duration::days(1))
+                    .await?;
+            }
+
+            let accrual_result = db_transaction
+                .query_one(sql::ACCRUE_INTEREST, &[&day, &None::<i64>])
+                .await
+                .map_err(|e| ServiceAPIError::database("accruing interest", e))?;
+            run.accruals += accrual_result.get::<_, i32>("_accrued") as u64;
+
+            db_transaction
+                .commit()
+                .await
+                .map_err(|e| ServiceAPIError::database("committing interest accrual", e))?;
+
+            day += Duration::days(1);
+        }
+
+        // months accrued by earlier runs may still be unpaid, e.g. when paying them failed
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning interest payment", e))?;
+        run.payments += self.pay_interest(&db_transaction, through).await?;
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing interest payment", e))?;
+
+        Ok(run)
+    }
+
+    /// Pays the interest of the months over by `through`, returning the number of payments.
+    async fn pay_interest(
+        &self,
+        db_transaction: &tokio_postgres::Transaction<'_>,
+        through: NaiveDate,
+    ) -> Result<u64, ServiceAPIError> {
+        let payment_result = db_transaction
+            .query_one(sql::PAY_INTEREST, &[&through, &self.interest_account_ref])
+            .await
+            .map_err(|e| match e.code().map(|state| state.code()) {
+                Some(sql::INTEREST_ACCOUNT_DOES_NOT_EXIST_STATE) => {
+                    log::error!("paying interest failed: {}", e);
+                    ServiceAPIError::InterestAccountMissing
+                }
+                _ => ServiceAPIError::database("paying interest", e),
+            })?;
+
+        Ok(payment_result.get::<_, i32>("_paid") as u64)
+    }
+}
+
+/// [InterestProduct] pays `annual_rate` percent a year on balances of `asset`. Interest accrues
+/// every day on the end-of-day balance and is paid at the end of every month.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct InterestProduct {
+    pub code: String,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub annual_rate: Amount,
+    pub compounding: Compounding,
+    pub accrual_basis: AccrualBasis,
+}
+
+impl InterestProduct {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        let code_is_valid = (1..=32).contains(&self.code.len())
+            && self
+                .code
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+        if !code_is_valid {
+            errors.push(FieldError::new(
+                "code",
+                "must be 1 to 32 lowercase letters, digits, '_' or '-'",
+            ));
+        }
+
+        check_amount(&mut errors, "annual_rate", self.annual_rate);
+        if self.annual_rate.value() > Decimal::ONE_HUNDRED {
+            errors.push(FieldError::new("annual_rate", "cannot exceed 100 percent"));
+        }
+
+        validation_result(errors)
+    }
+
+    fn from_row(row: &Row) -> Result<InterestProduct, ServiceAPIError> {
+        Ok(InterestProduct {
+            code: row.get("code"),
+            asset: row.get("asset"),
+            annual_rate: row.get::<_, Amount>("annual_rate"),
+            compounding: Compounding::from_db(row.get::<_, &str>("compounding"))?,
+            accrual_basis: AccrualBasis::from_db(row.get::<_, &str>("accrual_basis"))?,
+        })
+    }
+}
+
+/// [Compounding] denotes whether interest accrued but not paid yet earns interest itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compounding {
+    /// Interest accrued on a day earns interest from the next day on.
+    Daily,
+    /// Interest earns interest once it has been paid, from the month after the one it pays for.
+    Monthly,
+}
+
+impl Compounding {
+    fn as_db(self) -> &'static str {
+        match self {
+            Compounding::Daily => "daily",
+            Compounding::Monthly => "monthly",
+        }
+    }
+
+    fn from_db(compounding: &str) -> Result<Compounding, ServiceAPIError> {
+        match compounding {
+            "daily" => Ok(Compounding::Daily),
+            "monthly" => Ok(Compounding::Monthly),
+            _ => {
+                log::error!("unknown compounding on record: {}", compounding);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// [AccrualBasis] denotes how many days the annual rate is spread over: 365 or 360 days, or the
+/// actual number of days of the year a day falls into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AccrualBasis {
+    #[serde(rename = "actual_365")]
+    Actual365,
+    #[serde(rename = "actual_360")]
+    Actual360,
+    #[serde(rename = "actual_actual")]
+    ActualActual,
+}
+
+impl AccrualBasis {
+    fn as_db(self) -> &'static str {
+        match self {
+            AccrualBasis::Actual365 => "actual_365",
+            AccrualBasis::Actual360 => "actual_360",
+            AccrualBasis::ActualActual => "actual_actual",
+        }
+    }
+
+    fn from_db(basis: &str) -> Result<AccrualBasis, ServiceAPIError> {
+        match basis {
+            "actual_365" => Ok(AccrualBasis::Actual365),
+            "actual_360" => Ok(AccrualBasis::Actual360),
+            "actual_actual" => Ok(AccrualBasis::ActualActual),
+            _ => {
+                log::error!("unknown accrual basis on record: {}", basis);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// [InterestEnrollment] names the interest product to attach an account to, or none to detach it.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct InterestEnrollment {
+    pub product: Option<String>,
+}
+
+/// [AccountInterest] is the interest product of an account, if any, and the day it has earned
+/// interest `since`. `accrued` is the interest accrued up to `accrued_through` that has not been
+/// paid yet, at full precision, whereas `payments` lists the latest monthly payments.
+#[derive(Serialize)]
+pub struct AccountInterest {
+    pub id: u64,
+    pub product: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub accrued_through: Option<NaiveDate>,
+    pub accrued: Amount,
+    pub payments: Vec<InterestPaymentRecord>,
+}
+
+/// [InterestPaymentRecord] is the interest accrued over the month starting on `period`, paid as
+/// `amount` by `transaction_id`, unless it rounded down to nothing.
+#[derive(Serialize)]
+pub struct InterestPaymentRecord {
+    pub period: NaiveDate,
+    pub asset: String,
+    pub accrued: Amount,
+    pub amount: Amount,
+    pub transaction_id: Option<u64>,
+}
+
+impl InterestPaymentRecord {
+    fn from_row(row: &Row) -> Result<InterestPaymentRecord, ServiceAPIError> {
+        Ok(InterestPaymentRecord {
+            period: row.get("period"),
+            asset: row.get("asset"),
+            accrued: row.get::<_, Amount>("accrued"),
+            amount: row.get::<_, Amount>("amount"),
+            transaction_id: row
+                .get::<_, Option<i64>>("transaction_number")
+                .map(bigint_to_id)
+                .transpose()?,
+        })
+    }
+}
+
+/// [InterestRunRequest] asks for interest to be accrued through a day, the day before if left out.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct InterestRunRequest {
+    pub through: Option<NaiveDate>,
+}
+
+/// [InterestRun] counts the days accrued for every account, and the monthly payments made, by one
+/// run of [Database::accrue_interest].
+#[derive(Serialize)]
+pub struct InterestRun {
+    pub through: NaiveDate,
+    pub accruals: u64,
+    pub payments: u64,
+}
+
+/// `RECENT_INTEREST_PAYMENTS` denotes the most payments [Database::get_account_interest] lists.
+const RECENT_INTEREST_PAYMENTS: i64 = 12;
@@ -0,0 +1,213 @@
+//! Helpers processing HTTP requests related to freezing, unfreezing and closing accounts.
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::Row;
+
+use crate::db::{
+    bigint_to_id, check_id, error_detail, id_to_bigint, sql, validation_result, Database,
+};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+impl Database {
+    /// Moves the account `id` to `status` and records who did it and why. Closing an account
+    /// sweeps its balances to `change.sweep_to` if given, and fails if it still holds funds otherwise.
+    pub async fn change_account_status(
+        &self,
+        id: u64,
+        status: AccountStatus,
+        change: StatusChangeRequest,
+    ) -> Result<StatusChange, ServiceAPIError> {
+        change.validate(id, status)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning status change", e))?;
+
+        let change_result = db_transaction
+            .query_one(
+                sql::CHANGE_ACCOUNT_STATUS,
+                &[
+                    &id_to_bigint(id)?,
+                    &status.as_db(),
+                    &change.actor,
+                    &change.reason,
+                    &change.sweep_to.map(id_to_bigint).transpose()?,
+                ],
+            )
+            .await
+            .map_err(|e| match e.code().map(|state| state.code()) {
+                Some(sql::ACCOUNT_DOES_NOT_EXIST_STATE) => ServiceAPIError::SenderDoesNotExist,
+                Some(sql::INVALID_STATUS_TRANSITION_STATE) => {
+                    ServiceAPIError::InvalidStatusTransition {
+                        from: error_detail(&e),
+                        to: status.as_db().to_string(),
+                    }
+                }
+                Some(sql::ACCOUNT_NOT_EMPTY_STATE) => ServiceAPIError::AccountNotEmpty,
+                Some(sql::RECIPIENT_DOES_NOT_EXIST_STATE) => ServiceAPIError::RecipientDoesNotExist,
+                Some(sql::ACCOUNT_FROZEN_STATE) => ServiceAPIError::AccountFrozen(error_detail(&e)),
+                Some(sql::ACCOUNT_CLOSED_STATE) => ServiceAPIError::AccountClosed(error_detail(&e)),
+                _ => ServiceAPIError::database("changing account status", e),
+            })?;
+
+        let change_query_result = db_transaction
+            .query_one(
+                sql::SELECT_STATUS_CHANGE_BY_NUMBER,
+                &[&change_result.get::<_, i64>("_number")],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying recorded status change", e))?;
+        let response = StatusChange::from_row(&change_query_result)?;
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing status change", e))?;
+
+        Ok(response)
+    }
+
+    /// Returns the status of the account `id`, along with every change it went through.
+    pub async fn get_account_status(
+        &self,
+        id: u64,
+    ) -> Result<AccountStatusHistory, ServiceAPIError> {
+        let account = id_to_bigint(id)?;
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let status_query_result = client
+            .query_opt(sql::SELECT_ACCOUNT_STATUS, &[&account])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying account status", e))?
+            .ok_or(ServiceAPIError::SenderDoesNotExist)?;
+
+        let changes_query_result = client
+            .query(sql::SELECT_STATUS_CHANGES, &[&account])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying account status changes", e))?;
+
+        Ok(AccountStatusHistory {
+            id,
+            status: AccountStatus::from_db(status_query_result.get("status"))?,
+            changes: changes_query_result
+                .iter()
+                .map(StatusChange::from_row)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// [AccountStatus] denotes whether funds can move in and out of an account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// Account sends and receives funds.
+    Active,
+    /// Account is blocked, e.g. while compromised, and can be unfrozen.
+    Frozen,
+    /// Account is closed for good.
+    Closed,
+}
+
+impl AccountStatus {
+    fn as_db(self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Frozen => "frozen",
+            AccountStatus::Closed => "closed",
+        }
+    }
+
+    fn from_db(status: &str) -> Result<AccountStatus, ServiceAPIError> {
+        match status {
+            "active" => Ok(AccountStatus::Active),
+            "frozen" => Ok(AccountStatus::Frozen),
+            "closed" => Ok(AccountStatus::Closed),
+            _ => {
+                log::error!("unknown account status on record: {}", status);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// [StatusChangeRequest] names who changes the status of an account and why. `sweep_to` only
+/// applies to closing an account.
+#[derive(Deserialize, Serialize)]
+pub struct StatusChangeRequest {
+    pub actor: String,
+    pub reason: String,
+    pub sweep_to: Option<u64>,
+}
+
+impl StatusChangeRequest {
+    fn validate(&self, id: u64, status: AccountStatus) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        if self.actor.trim().is_empty() {
+            errors.push(FieldError::new("actor", "cannot be empty"));
+        }
+        if self.reason.trim().is_empty() {
+            errors.push(FieldError::new("reason", "cannot be empty"));
+        }
+
+        if let Some(sweep_to) = self.sweep_to {
+            check_id(&mut errors, "sweep_to", sweep_to);
+            if status != AccountStatus::Closed {
+                errors.push(FieldError::new(
+                    "sweep_to",
+                    "only accounts being closed can be swept",
+                ));
+            } else if sweep_to == id {
+                errors.push(FieldError::new(
+                    "sweep_to",
+                    "must differ from the account being closed",
+                ));
+            }
+        }
+
+        validation_result(errors)
+    }
+}
+
+/// [StatusChange] is a recorded change of the status of an account.
+#[derive(Serialize)]
+pub struct StatusChange {
+    pub id: u64,
+    pub account_id: u64,
+    pub from_status: AccountStatus,
+    pub to_status: AccountStatus,
+    pub actor: String,
+    pub reason: String,
+    pub swept_to: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StatusChange {
+    fn from_row(row: &Row) -> Result<StatusChange, ServiceAPIError> {
+        Ok(StatusChange {
+            id: bigint_to_id(row.get::<_, i64>("number"))?,
+            account_id: bigint_to_id(row.get::<_, i64>("account_id"))?,
+            from_status: AccountStatus::from_db(row.get("from_status"))?,
+            to_status: AccountStatus::from_db(row.get("to_status"))?,
+            actor: row.get("actor"),
+            reason: row.get("reason"),
+            swept_to: row
+                .get::<_, Option<i64>>("swept_to")
+                .map(bigint_to_id)
+                .transpose()?,
+            created_at: row.get::<_, DateTime<Utc>>("created_at"),
+        })
+    }
+}
+
+/// [AccountStatusHistory] is the current status of an account and the changes that led to it.
+#[derive(Serialize)]
+pub struct AccountStatusHistory {
+    pub id: u64,
+    pub status: AccountStatus,
+    pub changes: Vec<StatusChange>,
+}
@@ -2,22 +2,188 @@
 pub(crate) mod open;
 pub use open::*;
 
+/// Defines methods for querying the asset registry from DB.
+pub(crate) mod assets;
+pub use assets::*;
+
 /// Defines methods for querying attributes related to accounts from DB.
 pub(crate) mod accounts;
 pub use accounts::*;
 
+/// Defines methods for freezing, unfreezing and closing accounts.
+pub(crate) mod lifecycle;
+pub use lifecycle::*;
+
+/// Defines methods for querying and overriding the balance policy of accounts.
+pub(crate) mod policies;
+pub use policies::*;
+
 /// Defines methods for querying attributes related to transactions from DB.
 pub(crate) mod transactions;
 pub use transactions::*;
 
+/// Defines methods for charging fees on transfers.
+pub(crate) mod fees;
+pub use fees::*;
+
+/// Defines methods for reserving funds with holds and settling them.
+pub(crate) mod holds;
+pub use holds::*;
+
+/// Defines methods for scheduling transfers, once or on a recurring basis, and running them.
+pub(crate) mod schedules;
+pub use schedules::*;
+
+/// Defines the cron-like recurrences of schedules.
+pub(crate) mod recurrence;
+pub use recurrence::*;
+
+/// Defines methods for accruing and paying interest on balances under interest products.
+pub(crate) mod interest;
+pub use interest::*;
+
+/// Defines methods for issuing, revoking and checking the API keys clients authenticate with.
+pub(crate) mod api_keys;
+
+/// Defines methods for deduplicating retried requests.
+pub(crate) mod idempotency;
+pub use idempotency::*;
+
+/// Defines the fixed-point amounts stored on DB.
+pub(crate) mod amount;
+pub use amount::*;
+
 /// Defines all SQL queries used to query information from DB.
 pub(crate) mod sql;
 
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+/// [Page] is one window of a listing, along with the cursor to pass for fetching the next one.
+#[derive(Deserialize, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
 /// Helpers
-pub fn bigint_to_u64(val: i64) -> u64 {
-    val as u64
+///
+/// Cursors encode the keyset of the last item of a page, 32 hex digits per key. Keys are decimals,
+/// so that they can hold both ids and amounts.
+pub fn encode_cursor(keys: &[Decimal]) -> String {
+    keys.iter()
+        .map(|key| format!("{:032x}", u128::from_le_bytes(key.serialize())))
+        .collect()
+}
+
+pub fn decode_cursor<const N: usize>(cursor: &str) -> Result<[Decimal; N], ServiceAPIError> {
+    if cursor.len() != 32 * N || !cursor.is_ascii() {
+        return Err(ServiceAPIError::InvalidCursor);
+    }
+
+    let mut keys = [Decimal::ZERO; N];
+    for (i, key) in keys.iter_mut().enumerate() {
+        let bytes = u128::from_str_radix(&cursor[32 * i..32 * (i + 1)], 16)
+            .map_err(|_| ServiceAPIError::InvalidCursor)?
+            .to_le_bytes();
+
+        // bytes that are not the serialization of a decimal were not produced by `encode_cursor`
+        *key = Decimal::deserialize(bytes);
+        if key.serialize() != bytes {
+            return Err(ServiceAPIError::InvalidCursor);
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Converts a key of a cursor back into the BIGINT id it was encoded from.
+pub fn cursor_id(key: Decimal) -> Result<i64, ServiceAPIError> {
+    if !key.fract().is_zero() {
+        return Err(ServiceAPIError::InvalidCursor);
+    }
+
+    i64::try_from(key).map_err(|_| ServiceAPIError::InvalidCursor)
+}
+
+/// Converts an id to BIGINT, rejecting ids beyond its range rather than wrapping them around.
+pub fn id_to_bigint(id: u64) -> Result<i64, ServiceAPIError> {
+    i64::try_from(id).map_err(|_| ServiceAPIError::IdOutOfRange)
+}
+
+/// Converts a BIGINT read from DB back to an id. Ids are never negative on DB.
+pub fn bigint_to_id(value: i64) -> Result<u64, ServiceAPIError> {
+    u64::try_from(value).map_err(|_| {
+        log::error!("negative id on record: {}", value);
+        ServiceAPIError::SerializationFailure
+    })
+}
+
+/// Parses the DETAIL a stored procedure raised an error with, such as the id it failed on.
+pub fn error_detail<T: FromStr>(e: &tokio_postgres::Error) -> Option<T> {
+    e.as_db_error()
+        .and_then(|e| e.detail())
+        .and_then(|detail| detail.parse().ok())
+}
+
+/// Classifies the errors `CheckTransfer` raises while moving `asset`.
+pub fn transfer_error(e: tokio_postgres::Error, asset: &str) -> ServiceAPIError {
+    match e.code().map(|state| state.code()) {
+        Some(sql::SENDER_DOES_NOT_EXIST_STATE) => ServiceAPIError::SenderDoesNotExist,
+        Some(sql::RECIPIENT_DOES_NOT_EXIST_STATE) => ServiceAPIError::RecipientDoesNotExist,
+        Some(sql::NOT_ENOUGH_BALANCE_STATE) => ServiceAPIError::NotEnoughBalance {
+            asset: asset.to_string(),
+            // `TransferFunds` reports the balance the sender needed in the detail
+            required: error_detail::<Decimal>(&e).map(|required| required.normalize()),
+        },
+        Some(sql::ACCOUNT_FROZEN_STATE) => ServiceAPIError::AccountFrozen(error_detail(&e)),
+        Some(sql::ACCOUNT_CLOSED_STATE) => ServiceAPIError::AccountClosed(error_detail(&e)),
+        Some(sql::ASSET_MISMATCH_STATE) => ServiceAPIError::AssetMismatch,
+        Some(sql::ASSET_DOES_NOT_EXIST_STATE) => ServiceAPIError::AssetDoesNotExist,
+        Some(sql::AMOUNT_PRECISION_STATE) => ServiceAPIError::InvalidAmount,
+        _ => ServiceAPIError::database("transferring funds", e),
+    }
+}
+
+/// Records why `id` is invalid as the value of `field`, if it is.
+pub fn check_id(errors: &mut Vec<FieldError>, field: &'static str, id: u64) {
+    if id_to_bigint(id).is_err() {
+        errors.push(FieldError::new(field, "id is beyond the storage range"));
+    }
+}
+
+/// Records why `amount` is invalid as the value of `field`, if it is.
+pub fn check_amount(errors: &mut Vec<FieldError>, field: &'static str, amount: Amount) {
+    if let Err(e) = amount.check() {
+        errors.push(FieldError::new(field, e));
+    }
+}
+
+/// Records why moving `amount` from `from_id` to `to_id` is invalid, if it is.
+pub fn check_transfer(errors: &mut Vec<FieldError>, from_id: u64, to_id: u64, amount: Amount) {
+    check_id(errors, "from_id", from_id);
+    check_id(errors, "to_id", to_id);
+    if from_id == to_id {
+        errors.push(FieldError::new("to_id", "must differ from from_id"));
+    }
+
+    match amount.check() {
+        Ok(amount) if amount.is_zero() => {
+            errors.push(FieldError::new("amount", "must be greater than zero"))
+        }
+        Ok(_) => {}
+        Err(e) => errors.push(FieldError::new("amount", e)),
+    }
 }
 
-pub fn u64_to_bigint(val: u64) -> i64 {
-    val as i64
+/// Fails with the collected `errors`, if any.
+pub fn validation_result(errors: Vec<FieldError>) -> Result<(), ServiceAPIError> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ServiceAPIError::ValidationFailed(errors))
+    }
 }
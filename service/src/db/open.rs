@@ -10,21 +10,28 @@ use std::str::FromStr;
 #[derive(Clone)]
 pub struct Database {
     pub pool: Pool<PgConnectionManager<tokio_postgres::NoTls>>,
+    pub idempotency_window_secs: u64,
+    pub balance_policy: BalancePolicy,
+    pub hold_ttl_secs: u64,
+    pub max_batch_size: usize,
+    pub fee_policy: FeePolicy,
+    pub schedule_policy: SchedulePolicy,
+    pub interest_account_ref: Option<String>,
 }
 
-use crate::db::sql;
+use crate::db::{sql, BalancePolicy, FeePolicy, SchedulePolicy};
 
 impl Database {
-    pub async fn open(start_anew: bool, config: &str) -> Result<Database, tokio_postgres::Error> {
-        let config = Config::from_str(config)?;
-        let manager = PgConnectionManager::new(config, tokio_postgres::NoTls);
-        let pool = Pool::builder()
-            .max_open(MAX_OPEN_CONNECTIONS)
-            .max_idle(MAX_IDLE_CONNECTIONS)
-            .get_timeout(Some(std::time::Duration::from_secs(15)))
-            .build(manager);
+    /// Connects to DB and sets up its schema, dropping every table first if `start_anew`.
+    pub async fn open(
+        start_anew: bool,
+        config: &str,
+        service_config: &crate::config::Config,
+    ) -> Result<Database, tokio_postgres::Error> {
+        let db = Database::connect(config, service_config).await?;
 
-        let client = pool
+        let client = db
+            .pool
             .get()
             .await
             .expect("Irrecoverable error: Failed to set up connection pool.");
@@ -41,7 +48,58 @@ impl Database {
             .await
             .expect("Irrecoverable error: Failed to set up database.");
 
-        Ok(Database { pool })
+        // fee schedules of assets registered already must fit them, as registering checks later on
+        let asset_query_result = client
+            .query(sql::SELECT_ASSETS, &[])
+            .await
+            .expect("Irrecoverable error: Failed to query assets.");
+        for row in asset_query_result {
+            let symbol = row.get::<_, &str>("symbol");
+            let decimals = row.get::<_, i16>("decimals") as u32;
+            if let Err(e) = db.fee_policy.check_decimals(symbol, decimals) {
+                panic!(
+                    "Irrecoverable error: fee_schedules in config.toml are invalid: {}",
+                    e
+                );
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Connects to DB as it is, leaving its schema alone. Administrative commands connect this
+    /// way, as they may run against DB while the service is serving requests.
+    pub async fn connect(
+        config: &str,
+        service_config: &crate::config::Config,
+    ) -> Result<Database, tokio_postgres::Error> {
+        let config = Config::from_str(config)?;
+        let manager = PgConnectionManager::new(config, tokio_postgres::NoTls);
+        let pool = Pool::builder()
+            .max_open(MAX_OPEN_CONNECTIONS)
+            .max_idle(MAX_IDLE_CONNECTIONS)
+            .get_timeout(Some(std::time::Duration::from_secs(15)))
+            .build(manager);
+
+        Ok(Database {
+            pool,
+            idempotency_window_secs: service_config.idempotency_window_secs,
+            balance_policy: BalancePolicy {
+                min_balance: service_config.min_balance,
+                overdraft_limit: service_config.overdraft_limit,
+            },
+            hold_ttl_secs: service_config.hold_ttl_secs,
+            max_batch_size: service_config.max_batch_size,
+            fee_policy: FeePolicy {
+                account_ref: service_config.fee_account_ref.clone(),
+                schedules: service_config.fee_schedules.clone(),
+            },
+            schedule_policy: SchedulePolicy {
+                retry_backoff_secs: service_config.schedule_retry_backoff_secs,
+                max_retries: service_config.schedule_max_retries,
+            },
+            interest_account_ref: service_config.interest_account_ref.clone(),
+        })
     }
 }
 
@@ -0,0 +1,125 @@
+//! Helpers processing HTTP requests related to the balance policy of accounts.
+
+use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::Row;
+
+use crate::db::{check_amount, id_to_bigint, sql, validation_result, Amount, Database};
+use crate::error_codes::Error as ServiceAPIError;
+
+impl Database {
+    /// Returns the policy [Database::post_tx] enforces on the account `id`.
+    pub async fn get_policy(&self, id: u64) -> Result<AccountPolicy, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let policy_query_result = client
+            .query_opt(sql::SELECT_ACCOUNT_POLICY, &[&id_to_bigint(id)?])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying account policy", e))?
+            .ok_or(ServiceAPIError::SenderDoesNotExist)?;
+
+        Ok(AccountPolicy::new(
+            id,
+            self.balance_policy,
+            PolicyOverrides::from_row(&policy_query_result),
+        ))
+    }
+
+    /// Replaces the overrides of the account `id`. Fields left out fall back to the default policy.
+    pub async fn set_policy(
+        &self,
+        id: u64,
+        overrides: PolicyOverrides,
+    ) -> Result<AccountPolicy, ServiceAPIError> {
+        overrides.validate()?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let policy_query_result = client
+            .query_opt(
+                sql::UPDATE_ACCOUNT_POLICY,
+                &[
+                    &id_to_bigint(id)?,
+                    &overrides.min_balance,
+                    &overrides.overdraft_limit,
+                ],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("updating account policy", e))?
+            .ok_or(ServiceAPIError::SenderDoesNotExist)?;
+
+        Ok(AccountPolicy::new(
+            id,
+            self.balance_policy,
+            PolicyOverrides::from_row(&policy_query_result),
+        ))
+    }
+}
+
+/// [BalancePolicy] bounds how low a transfer may take the balance of its sender: down to
+/// `min_balance`, less the `overdraft_limit` the sender is allowed. The default policy comes from
+/// [crate::config::Config] and applies to every asset an account holds.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct BalancePolicy {
+    pub min_balance: Amount,
+    pub overdraft_limit: Amount,
+}
+
+impl BalancePolicy {
+    fn with_overrides(self, overrides: &PolicyOverrides) -> BalancePolicy {
+        BalancePolicy {
+            min_balance: overrides.min_balance.unwrap_or(self.min_balance),
+            overdraft_limit: overrides.overdraft_limit.unwrap_or(self.overdraft_limit),
+        }
+    }
+}
+
+/// [PolicyOverrides] are the parts of [BalancePolicy] set for one account, e.g. for a customer tier.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct PolicyOverrides {
+    pub min_balance: Option<Amount>,
+    pub overdraft_limit: Option<Amount>,
+}
+
+impl PolicyOverrides {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        if let Some(min_balance) = self.min_balance {
+            check_amount(&mut errors, "min_balance", min_balance);
+        }
+        if let Some(overdraft_limit) = self.overdraft_limit {
+            check_amount(&mut errors, "overdraft_limit", overdraft_limit);
+        }
+
+        validation_result(errors)
+    }
+
+    fn from_row(row: &Row) -> PolicyOverrides {
+        PolicyOverrides {
+            min_balance: row.get("min_balance"),
+            overdraft_limit: row.get("overdraft_limit"),
+        }
+    }
+}
+
+/// [AccountPolicy] is the policy in effect for an account, along with the overrides it stems from.
+#[derive(Serialize)]
+pub struct AccountPolicy {
+    pub id: u64,
+    pub min_balance: Amount,
+    pub overdraft_limit: Amount,
+    pub overrides: PolicyOverrides,
+}
+
+impl AccountPolicy {
+    fn new(id: u64, default: BalancePolicy, overrides: PolicyOverrides) -> AccountPolicy {
+        let policy = default.with_overrides(&overrides);
+
+        AccountPolicy {
+            id,
+            min_balance: policy.min_balance,
+            overdraft_limit: policy.overdraft_limit,
+            overrides,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Cron-like recurrences of scheduled transfers.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use std::str::FromStr;
+
+/// [Recurrence] is a cron expression of five fields, `minute hour day-of-month month day-of-week`,
+/// evaluated in UTC. Fields take `*`, values, ranges such as `1-5` and lists of them, each
+/// optionally stepped as in `*/15`. Days of the week run from 0 (Sunday) to 6, or 7 for Sunday
+/// again. As in cron, restricting both the day of the month and of the week matches either one.
+/// `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are shorthands of common expressions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recurrence {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Recurrence {
+    /// Returns the first whole minute strictly after `after` that the recurrence comes round on,
+    /// unless it does not within [MAX_LOOKAHEAD_DAYS], e.g. on the 30th of February.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        let mut date = start.date_naive();
+        for _ in 0..MAX_LOOKAHEAD_DAYS {
+            if self.matches_day(date) {
+                // only the first day is partly over
+                let (first_hour, first_minute) = if date == start.date_naive() {
+                    (start.hour(), start.minute())
+                } else {
+                    (0, 0)
+                };
+
+                for hour in (first_hour..24).filter(|hour| is_set(self.hours, *hour)) {
+                    let from_minute = if hour == first_hour { first_minute } else { 0 };
+                    if let Some(minute) = (from_minute..60).find(|m| is_set(self.minutes, *m)) {
+                        return Some(Utc.from_utc_datetime(&date.and_hms_opt(hour, minute, 0)?));
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !is_set(self.months, date.month()) {
+            return false;
+        }
+
+        let day = is_set(self.days, date.day());
+        let weekday = is_set(self.weekdays, date.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Recurrence, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" => "0 0 1 1 *",
+            expression => expression,
+        };
+
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(
+                "must have five fields: minute, hour, day of month, month and day of week"
+                    .to_string(),
+            );
+        }
+
+        let mut weekdays = parse_field(fields[4], "day of week", 0, 7)?;
+        // 7 is Sunday as well as 0
+        if is_set(weekdays, 7) {
+            weekdays |= 1;
+        }
+
+        Ok(Recurrence {
+            minutes: parse_field(fields[0], "minute", 0, 59)?,
+            hours: parse_field(fields[1], "hour", 0, 23)?,
+            days: parse_field(fields[2], "day of month", 1, 31)?,
+            months: parse_field(fields[3], "month", 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+}
+
+/// Helpers
+///
+/// Fields are parsed into bit sets, one bit per value they match.
+fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<u64, String> {
+    let value = |value: &str| {
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| {
+                format!(
+                    "{} must be between {} and {}, not {}",
+                    name, min, max, value
+                )
+            })
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("{} has an invalid step in {}", name, part)),
+            },
+            None => (part, 1),
+        };
+
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            // a stepped value such as `5/15` runs up to the last value
+            None if step > 1 => (value(range)?, max),
+            None => {
+                let value = value(range)?;
+                (value, value)
+            }
+        };
+        if first > last {
+            return Err(format!("{} has an inverted range in {}", name, part));
+        }
+
+        for value in (first..=last).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn is_set(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// `MAX_LOOKAHEAD_DAYS` bounds the search for the next occurrence, long enough to reach the 29th
+/// of February from any day.
+const MAX_LOOKAHEAD_DAYS: usize = 8 * 366;
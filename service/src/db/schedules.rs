@@ -0,0 +1,530 @@
+//! Methods processing HTTP requests related to schedules, which transfer funds later on, either
+//! once or every time a recurrence comes round, and running them when they are due.
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::Row;
+
+use crate::db::{
+    bigint_to_id, check_transfer, cursor_id, decode_cursor, default_asset, encode_cursor,
+    id_to_bigint, sql, transfer_error, validation_result, Amount, Database, IdempotencyHeader,
+    IdempotencyKey, Page, Recurrence, Transaction,
+};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
+
+impl Database {
+    /// Schedules a transfer checked as [Database::post_tx] checks one, apart from the sender's
+    /// balance which is only checked when the schedule runs.
+    pub async fn create_schedule(
+        &self,
+        schedule: NewSchedule,
+        idempotency_key: Option<IdempotencyHeader>,
+    ) -> Result<ScheduleRecord, ServiceAPIError> {
+        let first_run_at = schedule.validate(Utc::now())?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning schedule", e))?;
+
+        let idempotency_key = idempotency_key
+            .map(|key| IdempotencyKey::new(key, POST_SCHEDULE_ROUTE, &schedule))
+            .transpose()?;
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.replay_response(&db_transaction, key).await? {
+                return Ok(response);
+            }
+        }
+
+        let schedule_result = db_transaction
+            .query_one(
+                sql::CREATE_NEW_SCHEDULE,
+                &[
+                    &id_to_bigint(schedule.from_id)?,
+                    &id_to_bigint(schedule.to_id)?,
+                    &schedule.asset,
+                    &schedule.amount,
+                    &schedule.recurrence,
+                    &first_run_at,
+                ],
+            )
+            .await
+            .map_err(|e| transfer_error(e, &schedule.asset))?;
+
+        let schedule_query_result = db_transaction
+            .query_one(
+                sql::SELECT_SCHEDULE_BY_NUMBER,
+                &[&schedule_result.get::<_, i64>("_number")],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying created schedule", e))?;
+        let response = ScheduleRecord::from_row(&schedule_query_result)?;
+
+        if let Some(key) = &idempotency_key {
+            self.record_response(&db_transaction, key, &response)
+                .await?;
+        }
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing schedule", e))?;
+
+        Ok(response)
+    }
+
+    pub async fn get_schedule(&self, id: u64) -> Result<ScheduleRecord, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let schedule_query_result = client
+            .query_opt(sql::SELECT_SCHEDULE_BY_NUMBER, &[&id_to_bigint(id)?])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying schedule", e))?
+            .ok_or(ServiceAPIError::ScheduleDoesNotExist)?;
+
+        ScheduleRecord::from_row(&schedule_query_result)
+    }
+
+    /// Returns one page of schedules, latest first, narrowed down by `filter`.
+    pub async fn list_schedules(
+        &self,
+        filter: &ScheduleFilter,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Page<ScheduleRecord>, ServiceAPIError> {
+        let after = after
+            .map(decode_cursor::<1>)
+            .transpose()?
+            .map(|[number]| cursor_id(number))
+            .transpose()?;
+        let from_id = filter
+            .from_id
+            .map(id_to_bigint)
+            .transpose()
+            .map_err(|_| ServiceAPIError::InvalidFilter)?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        // one extra row is fetched to find out whether there is a next page
+        let schedules_query_result = client
+            .query(
+                sql::SELECT_SCHEDULES_PAGE,
+                &[
+                    &from_id,
+                    &filter.status.map(ScheduleStatus::as_db),
+                    &after,
+                    &(limit as i64 + 1),
+                ],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying schedules", e))?;
+
+        let mut items = schedules_query_result
+            .iter()
+            .map(ScheduleRecord::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items
+                .last()
+                .map(|schedule| encode_cursor(&[Decimal::from(schedule.id)]))
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
+    }
+
+    /// Runs the schedules that are due, up to [DUE_SCHEDULES_BATCH] of them, and returns how many
+    /// ran, whether or not their transfer went through. Each schedule runs in a DB transaction of
+    /// its own, so that one failing to be updated does not hold up the rest.
+    pub async fn run_due_schedules(&self) -> Result<usize, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let due_query_result = client
+            .query(sql::SELECT_DUE_SCHEDULES, &[&DUE_SCHEDULES_BATCH])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying due schedules", e))?;
+        drop(client);
+
+        let mut runs = 0;
+        for row in due_query_result {
+            let id = bigint_to_id(row.get::<_, i64>("number"))?;
+
+            match self.run_schedule(id).await {
+                Ok(true) => runs += 1,
+                Ok(false) => {}
+                Err(e) => log::error!("running schedule {} failed: {}", id, e.code()),
+            }
+        }
+
+        Ok(runs)
+    }
+
+    /// Transfers the funds of a due schedule and moves it on to its next run, and returns whether
+    /// it was still due. Transfers falling short of balance or failing on the service's side are
+    /// retried with exponential backoff up to [SchedulePolicy::max_retries] times, after which
+    /// recurring schedules skip the run and one-off schedules fail. Transfers failing for any
+    /// other reason of the request's fail the schedule at once.
+    async fn run_schedule(&self, id: u64) -> Result<bool, ServiceAPIError> {
+        let number = id_to_bigint(id)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let mut db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning schedule run", e))?;
+
+        let schedule_query_result = match db_transaction
+            .query_opt(sql::LOCK_DUE_SCHEDULE, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("locking due schedule", e))?
+        {
+            Some(row) => row,
+            None => return Ok(false),
+        };
+        let recurrence = recurrence_of(&schedule_query_result)?;
+        let attempts = schedule_query_result.get::<_, i32>("attempts");
+        let tx = Transaction {
+            from_id: bigint_to_id(schedule_query_result.get::<_, i64>("from_id"))?,
+            to_id: Some(bigint_to_id(schedule_query_result.get::<_, i64>("to_id"))?),
+            asset: schedule_query_result.get("asset"),
+            amount: schedule_query_result.get::<_, Amount>("amount"),
+            legs: Vec::new(),
+        };
+
+        let now = Utc::now();
+        let next_occurrence = recurrence.and_then(|recurrence| recurrence.next_after(now));
+
+        // a failed transfer rolls back to the savepoint, so that the schedule can still be updated
+        let savepoint = db_transaction
+            .savepoint("schedule_run")
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning schedule transfer", e))?;
+        let (status, next_run_at, attempts, last_error) = match self.transfer(&savepoint, &tx).await
+        {
+            Ok(record) => {
+                savepoint
+                    .execute(
+                        sql::LINK_TX_TO_SCHEDULE,
+                        &[&id_to_bigint(record.id)?, &number],
+                    )
+                    .await
+                    .map_err(|e| ServiceAPIError::database("linking scheduled transaction", e))?;
+                savepoint
+                    .commit()
+                    .await
+                    .map_err(|e| ServiceAPIError::database("releasing schedule transfer", e))?;
+
+                match next_occurrence {
+                    Some(next_run_at) => (ScheduleStatus::Active, Some(next_run_at), 0, None),
+                    None => (ScheduleStatus::Completed, None, 0, None),
+                }
+            }
+            Err(e) => {
+                savepoint
+                    .rollback()
+                    .await
+                    .map_err(|e| ServiceAPIError::database("rolling back schedule transfer", e))?;
+                if e.is_server_error() {
+                    log::error!("scheduled transfer {} failed: {}", id, e.code());
+                } else {
+                    log::info!("scheduled transfer {} failed: {}", id, e.code());
+                }
+
+                let attempts = attempts + 1;
+                let retryable =
+                    e.is_server_error() || matches!(e, ServiceAPIError::NotEnoughBalance { .. });
+                let last_error = Some(e.code());
+                if retryable && attempts as u32 <= self.schedule_policy.max_retries {
+                    let retry_at = now + self.schedule_policy.backoff(attempts as u32);
+                    (ScheduleStatus::Active, Some(retry_at), attempts, last_error)
+                } else {
+                    match next_occurrence {
+                        Some(next_run_at) if retryable => {
+                            (ScheduleStatus::Active, Some(next_run_at), 0, last_error)
+                        }
+                        _ => (ScheduleStatus::Failed, None, attempts, last_error),
+                    }
+                }
+            }
+        };
+
+        db_transaction
+            .execute(
+                sql::UPDATE_SCHEDULE,
+                &[
+                    &number,
+                    &status.as_db(),
+                    &next_run_at,
+                    &attempts,
+                    &last_error,
+                ],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("updating schedule", e))?;
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing schedule run", e))?;
+
+        Ok(true)
+    }
+
+    /// Pauses an active schedule, resumes a paused one or cancels either of them for good.
+    /// Resumed recurring schedules skip the runs they missed meanwhile, whereas resumed one-off
+    /// schedules past their time run at once.
+    pub async fn change_schedule_status(
+        &self,
+        id: u64,
+        to: ScheduleStatus,
+    ) -> Result<ScheduleRecord, ServiceAPIError> {
+        let number = id_to_bigint(id)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning schedule status change", e))?;
+
+        let schedule_query_result = db_transaction
+            .query_opt(sql::LOCK_SCHEDULE, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("locking schedule", e))?
+            .ok_or(ServiceAPIError::ScheduleDoesNotExist)?;
+        let from = ScheduleStatus::from_db(schedule_query_result.get::<_, &str>("status"))?;
+
+        if !from.can_become(to) {
+            return Err(ServiceAPIError::InvalidStatusTransition {
+                from: Some(from.as_db().to_string()),
+                to: to.as_db().to_string(),
+            });
+        }
+
+        let mut next_run_at = schedule_query_result.get::<_, Option<DateTime<Utc>>>("next_run_at");
+        match to {
+            ScheduleStatus::Active => {
+                let now = Utc::now();
+                if let Some(recurrence) = recurrence_of(&schedule_query_result)? {
+                    if !matches!(next_run_at, Some(at) if at >= now) {
+                        next_run_at = recurrence.next_after(now).or(next_run_at);
+                    }
+                }
+            }
+            ScheduleStatus::Cancelled => next_run_at = None,
+            _ => {}
+        }
+
+        db_transaction
+            .execute(
+                sql::UPDATE_SCHEDULE,
+                &[
+                    &number,
+                    &to.as_db(),
+                    &next_run_at,
+                    &schedule_query_result.get::<_, i32>("attempts"),
+                    &schedule_query_result.get::<_, Option<String>>("last_error"),
+                ],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("changing schedule status", e))?;
+
+        let schedule_query_result = db_transaction
+            .query_one(sql::SELECT_SCHEDULE_BY_NUMBER, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying changed schedule", e))?;
+        let response = ScheduleRecord::from_row(&schedule_query_result)?;
+
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing schedule status change", e))?;
+
+        Ok(response)
+    }
+}
+
+/// Parses the recurrence of a schedule read from DB, which was checked on creating it.
+fn recurrence_of(row: &Row) -> Result<Option<Recurrence>, ServiceAPIError> {
+    row.get::<_, Option<&str>>("recurrence")
+        .map(|recurrence| {
+            recurrence.parse::<Recurrence>().map_err(|e| {
+                log::error!("invalid recurrence on record: {}: {}", recurrence, e);
+                ServiceAPIError::SerializationFailure
+            })
+        })
+        .transpose()
+}
+
+/// [SchedulePolicy] denotes how often, and how far apart, runs of a schedule falling short of
+/// balance or failing on the service's side are retried. The `n`th retry waits
+/// `retry_backoff_secs` times `2^(n - 1)` seconds.
+#[derive(Clone, Copy, Debug)]
+pub struct SchedulePolicy {
+    pub retry_backoff_secs: u64,
+    pub max_retries: u32,
+}
+
+impl SchedulePolicy {
+    fn backoff(self, attempts: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+        let secs = self.retry_backoff_secs.saturating_mul(factor);
+
+        Duration::seconds(i64::try_from(secs).unwrap_or(i64::MAX / 1000))
+    }
+}
+
+/// [NewSchedule] transfers `amount` of `asset` from one account to another at `start_at`, or at
+/// once if left out. Schedules given a cron `recurrence` (see [Recurrence]) run every time it
+/// comes round from `start_at` on instead.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewSchedule {
+    pub from_id: u64,
+    pub to_id: u64,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub amount: Amount,
+    pub recurrence: Option<String>,
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+impl NewSchedule {
+    /// Checks the schedule and returns when it first runs.
+    fn validate(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        check_transfer(&mut errors, self.from_id, self.to_id, self.amount);
+
+        let start_at = self.start_at.unwrap_or(now);
+        let mut first_run_at = start_at;
+        if let Some(recurrence) = &self.recurrence {
+            match recurrence.parse::<Recurrence>() {
+                // the recurrence may come round right at `start_at`
+                Ok(recurrence) => {
+                    match recurrence.next_after(start_at - Duration::nanoseconds(1)) {
+                        Some(at) => first_run_at = at,
+                        None => errors.push(FieldError::new("recurrence", "never comes round")),
+                    }
+                }
+                Err(e) => errors.push(FieldError::new("recurrence", e)),
+            }
+        }
+
+        validation_result(errors)?;
+
+        Ok(first_run_at)
+    }
+}
+
+/// [ScheduleFilter] narrows down the schedules returned by [Database::list_schedules].
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScheduleFilter {
+    pub from_id: Option<u64>,
+    pub status: Option<ScheduleStatus>,
+}
+
+/// [ScheduleRecord] is a schedule as stored on DB, along with the latest transaction it created.
+/// `attempts` counts the failed tries of the current run, and `last_error` names the code of the
+/// latest failure.
+#[derive(Deserialize, Serialize)]
+pub struct ScheduleRecord {
+    pub id: u64,
+    pub from_id: u64,
+    pub to_id: u64,
+    pub asset: String,
+    pub amount: Amount,
+    pub recurrence: Option<String>,
+    pub status: ScheduleStatus,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub last_transaction_id: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ScheduleRecord {
+    fn from_row(row: &Row) -> Result<ScheduleRecord, ServiceAPIError> {
+        Ok(ScheduleRecord {
+            id: bigint_to_id(row.get::<_, i64>("number"))?,
+            from_id: bigint_to_id(row.get::<_, i64>("from_id"))?,
+            to_id: bigint_to_id(row.get::<_, i64>("to_id"))?,
+            asset: row.get("asset"),
+            amount: row.get::<_, Amount>("amount"),
+            recurrence: row.get("recurrence"),
+            status: ScheduleStatus::from_db(row.get::<_, &str>("status"))?,
+            next_run_at: row.get::<_, Option<DateTime<Utc>>>("next_run_at"),
+            attempts: row.get::<_, i32>("attempts") as u32,
+            last_error: row.get("last_error"),
+            last_transaction_id: row
+                .get::<_, Option<i64>>("last_transaction_number")
+                .map(bigint_to_id)
+                .transpose()?,
+            created_at: row.get::<_, DateTime<Utc>>("created_at"),
+        })
+    }
+}
+
+/// [ScheduleStatus] denotes whether a schedule may still run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    /// The schedule runs once `next_run_at` has come.
+    Active,
+    /// The schedule does not run until resumed.
+    Paused,
+    /// The one-off schedule has run.
+    Completed,
+    /// The schedule ran into an error it cannot get past by retrying.
+    Failed,
+    /// The schedule has been stopped for good on request.
+    Cancelled,
+}
+
+impl ScheduleStatus {
+    fn can_become(self, to: ScheduleStatus) -> bool {
+        matches!(
+            (self, to),
+            (ScheduleStatus::Active, ScheduleStatus::Paused)
+                | (ScheduleStatus::Paused, ScheduleStatus::Active)
+                | (
+                    ScheduleStatus::Active | ScheduleStatus::Paused,
+                    ScheduleStatus::Cancelled
+                )
+        )
+    }
+
+    fn as_db(self) -> &'static str {
+        match self {
+            ScheduleStatus::Active => "active",
+            ScheduleStatus::Paused => "paused",
+            ScheduleStatus::Completed => "completed",
+            ScheduleStatus::Failed => "failed",
+            ScheduleStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn from_db(status: &str) -> Result<ScheduleStatus, ServiceAPIError> {
+        match status {
+            "active" => Ok(ScheduleStatus::Active),
+            "paused" => Ok(ScheduleStatus::Paused),
+            "completed" => Ok(ScheduleStatus::Completed),
+            "failed" => Ok(ScheduleStatus::Failed),
+            "cancelled" => Ok(ScheduleStatus::Cancelled),
+            _ => {
+                log::error!("unknown schedule status on record: {}", status);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// `POST_SCHEDULE_ROUTE` scopes idempotency keys used for creating schedules.
+const POST_SCHEDULE_ROUTE: &str = "POST /schedules";
+
+/// `DUE_SCHEDULES_BATCH` denotes the most schedules run by one [Database::run_due_schedules].
+const DUE_SCHEDULES_BATCH: i64 = 100;
@@ -1,14 +1,82 @@
 //! A set of SQL statements related to querying accounts.
 
 pub const SELECT_ACCOUNT_INFO_BY_ID: &str = "
-SELECT * FROM Account
-WHERE id = COALESCE($1, id)
-ORDER BY id desc;
+SELECT Account.*, Holding.asset, Holding.balance, Holding.balance - Holding.held AS available
+FROM Holding
+JOIN Account ON Account.id = Holding.account_id
+WHERE account_id = $1
+ORDER BY asset;
+";
+
+// `{key}`, `{key_type}`, `{cmp}` and `{dir}` are filled in from [crate::db::AccountOrder], never from user input.
+pub const SELECT_ACCOUNTS_PAGE: &str = "
+SELECT Account.*, Holding.asset, Holding.balance, Holding.balance - Holding.held AS available
+FROM Holding
+JOIN Account ON Account.id = Holding.account_id
+WHERE asset = $1
+AND balance >= COALESCE($2, balance)
+AND balance <= COALESCE($3, balance)
+AND ($4::NUMERIC IS NULL OR ({key}, account_id) {cmp} ($4::{key_type}, $5))
+AND ($7::TEXT IS NULL OR external_ref = $7)
+ORDER BY {key} {dir}, account_id {dir}
+LIMIT $6;
 ";
 
 pub const CREATE_NEW_USER: &str = "
 CALL InsertUser(
-    $1, -- id
-    $2  -- balance
+    $1,  -- asset
+    $2,  -- balance
+    $3,  -- external_ref
+    $4,  -- display_name
+    $5,  -- owner_ref
+    $6,  -- metadata
+    $7,  -- tier
+    NULL -- id allocated to the account (out)
+);
+";
+
+pub const SELECT_LEDGER_BALANCE: &str = "
+SELECT Holding.account_id AS id, Holding.asset, Holding.balance,
+    COALESCE(SUM(Posting.amount), 0) AS ledger_balance
+FROM Holding
+LEFT JOIN Posting ON Posting.account_id = Holding.account_id AND Posting.asset = Holding.asset
+WHERE Holding.account_id = $1
+GROUP BY Holding.account_id, Holding.asset, Holding.balance
+ORDER BY Holding.asset;
+";
+
+pub const CHANGE_ACCOUNT_STATUS: &str = "
+CALL ChangeAccountStatus(
+    $1,  -- id
+    $2,  -- status
+    $3,  -- actor
+    $4,  -- reason
+    $5,  -- account to sweep balances to, if closing
+    NULL -- number of the recorded status change (out)
 );
 ";
+
+pub const SELECT_ACCOUNT_STATUS: &str = "
+SELECT status FROM Account
+WHERE id = $1;
+";
+
+pub const SELECT_STATUS_CHANGES: &str = "
+SELECT * FROM AccountStatusChange
+WHERE account_id = $1
+ORDER BY number asc;
+";
+
+pub const SELECT_STATUS_CHANGE_BY_NUMBER: &str = "
+SELECT * FROM AccountStatusChange
+WHERE number = $1;
+";
+
+/// SQLSTATE raised by `ChangeAccountStatus` when the account does not exist.
+pub const ACCOUNT_DOES_NOT_EXIST_STATE: &str = "AC001";
+
+/// SQLSTATE raised by `ChangeAccountStatus` when the account cannot move to the requested status.
+pub const INVALID_STATUS_TRANSITION_STATE: &str = "AC002";
+
+/// SQLSTATE raised by `ChangeAccountStatus` when an account to be closed still holds funds.
+pub const ACCOUNT_NOT_EMPTY_STATE: &str = "AC003";
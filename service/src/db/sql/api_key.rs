@@ -0,0 +1,24 @@
+//! A set of SQL statements related to the API keys clients authenticate with.
+
+pub const CREATE_NEW_API_KEY: &str = "
+INSERT INTO ApiKey(name, prefix, key_hash, admin)
+VALUES ($1, $2, $3, $4)
+RETURNING *;
+";
+
+pub const SELECT_API_KEYS: &str = "
+SELECT * FROM ApiKey
+ORDER BY number;
+";
+
+// Revoking a key that has been revoked already keeps the time it was first revoked at.
+pub const REVOKE_API_KEY: &str = "
+UPDATE ApiKey SET revoked_at = COALESCE(revoked_at, now())
+WHERE number = $1
+RETURNING *;
+";
+
+pub const SELECT_ACTIVE_API_KEY: &str = "
+SELECT * FROM ApiKey
+WHERE key_hash = $1 AND revoked_at IS NULL;
+";
@@ -0,0 +1,22 @@
+//! A set of SQL statements related to the asset registry.
+
+pub const SELECT_ASSETS: &str = "
+SELECT * FROM Asset
+ORDER BY symbol;
+";
+
+pub const SELECT_ASSET_DECIMALS: &str = "
+SELECT decimals FROM Asset
+WHERE symbol = $1;
+";
+
+pub const CREATE_NEW_ASSET: &str = "
+INSERT INTO Asset(symbol, decimals, name, issuer)
+VALUES ($1, $2, $3, $4);
+";
+
+/// SQLSTATE raised by `CheckAmount` when the asset is not registered.
+pub const ASSET_DOES_NOT_EXIST_STATE: &str = "AS001";
+
+/// SQLSTATE raised by `CheckAmount` when an amount has more decimal places than its asset.
+pub const AMOUNT_PRECISION_STATE: &str = "AS002";
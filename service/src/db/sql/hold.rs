@@ -0,0 +1,48 @@
+//! A set of SQL statements related to placing and settling holds.
+
+pub const CREATE_NEW_HOLD: &str = "
+CALL PlaceHold(
+    $1,  -- from_id
+    $2,  -- to_id
+    $3,  -- asset
+    $4,  -- amount
+    $5,  -- default min_balance
+    $6,  -- default overdraft_limit
+    $7,  -- seconds until the hold expires
+    NULL -- number of the created hold (out)
+);
+";
+
+pub const SETTLE_HOLD: &str = "
+CALL SettleHold(
+    $1,  -- number
+    $2,  -- status to settle the hold with
+    $3,  -- amount to capture, the whole hold if NULL
+    $4,  -- default min_balance
+    $5,  -- default overdraft_limit
+    $6,  -- fee paid by the sender on capture
+    $7,  -- external_ref of the account credited with the fee
+    NULL -- number of the transaction a capture created (out)
+);
+";
+
+pub const SELECT_HOLD_BY_NUMBER: &str = "
+SELECT * FROM Hold
+WHERE number = $1;
+";
+
+pub const SELECT_EXPIRED_HOLDS: &str = "
+SELECT number FROM Hold
+WHERE status = 'pending' AND expires_at <= now()
+ORDER BY expires_at
+LIMIT $1;
+";
+
+/// SQLSTATE raised by `SettleHold` when the hold does not exist.
+pub const HOLD_DOES_NOT_EXIST_STATE: &str = "HD001";
+
+/// SQLSTATE raised by `SettleHold` when the hold has been settled or has expired.
+pub const HOLD_NOT_PENDING_STATE: &str = "HD002";
+
+/// SQLSTATE raised by `SettleHold` when a capture exceeds the amount on hold.
+pub const CAPTURE_EXCEEDS_HOLD_STATE: &str = "HD003";
@@ -0,0 +1,21 @@
+//! A set of SQL statements related to deduplicating retried requests.
+
+// Serializes concurrent requests carrying the same key with the same API key until the first one commits.
+pub const LOCK_IDEMPOTENCY_KEY: &str = "
+SELECT pg_advisory_xact_lock(hashtextextended($2, $1));
+";
+
+pub const SELECT_IDEMPOTENT_RESPONSE: &str = "
+SELECT request_hash, response FROM IdempotencyKey
+WHERE api_key = $1 AND key = $2
+AND created_at > now() - make_interval(secs => $3);
+";
+
+pub const UPSERT_IDEMPOTENT_RESPONSE: &str = "
+INSERT INTO IdempotencyKey(api_key, key, request_hash, response)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (api_key, key) DO UPDATE
+SET request_hash = EXCLUDED.request_hash,
+    response = EXCLUDED.response,
+    created_at = now();
+";
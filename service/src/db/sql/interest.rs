@@ -0,0 +1,95 @@
+//! A set of SQL statements related to interest products and paying interest on balances.
+
+pub const SELECT_INTEREST_PRODUCTS: &str = "
+SELECT * FROM InterestProduct
+ORDER BY code;
+";
+
+pub const CREATE_NEW_INTEREST_PRODUCT: &str = "
+INSERT INTO InterestProduct(code, asset, annual_rate, compounding, accrual_basis)
+VALUES ($1, $2, $3, $4, $5);
+";
+
+// Attaching the product an account has already keeps the day it has earned interest since.
+pub const UPDATE_ACCOUNT_INTEREST: &str = "
+UPDATE Account SET
+    interest_since = CASE
+        WHEN $2::TEXT IS NULL THEN NULL
+        WHEN interest_product IS NOT DISTINCT FROM $2 THEN interest_since
+        ELSE (now() AT TIME ZONE 'UTC')::DATE
+    END,
+    interest_product = $2
+WHERE id = $1
+RETURNING id;
+";
+
+pub const SELECT_ACCOUNT_INTEREST: &str = "
+SELECT
+    Account.interest_product,
+    Account.interest_since,
+    (SELECT max(day) FROM InterestAccrual WHERE account_id = Account.id) AS accrued_through,
+    (
+        SELECT COALESCE(SUM(amount), 0) FROM InterestAccrual
+        WHERE account_id = Account.id AND NOT EXISTS (
+            SELECT 1 FROM InterestPayment
+            WHERE InterestPayment.account_id = InterestAccrual.account_id
+            AND InterestPayment.period = date_trunc('month', InterestAccrual.day)::DATE
+            AND InterestPayment.asset = InterestAccrual.asset
+        )
+    ) AS accrued
+FROM Account
+WHERE id = $1;
+";
+
+pub const SELECT_INTEREST_PAYMENTS: &str = "
+SELECT * FROM InterestPayment
+WHERE account_id = $1
+ORDER BY period desc, asset
+LIMIT $2;
+";
+
+// The first day some account earning interest has not accrued it for yet, NULL if none earns any.
+pub const SELECT_FIRST_UNACCRUED_DAY: &str = "
+SELECT min(COALESCE(
+    (
+        SELECT max(day) + 1 FROM InterestAccrual
+        WHERE account_id = Account.id AND day >= Account.interest_since
+    ),
+    Account.interest_since
+)) AS day
+FROM Account
+WHERE interest_product IS NOT NULL AND status <> 'closed';
+";
+
+// Locks the account, so that it keeps its product until it has accrued the days it is owed.
+pub const SELECT_ACCOUNT_FIRST_UNACCRUED_DAY: &str = "
+SELECT COALESCE(
+    (
+        SELECT max(day) + 1 FROM InterestAccrual
+        WHERE account_id = Account.id AND day >= Account.interest_since
+    ),
+    Account.interest_since
+) AS day
+FROM Account
+WHERE id = $1 AND interest_product IS NOT NULL AND status <> 'closed'
+FOR UPDATE;
+";
+
+pub const ACCRUE_INTEREST: &str = "
+CALL AccrueInterest(
+    $1,  -- day
+    $2,  -- account to accrue for, every account if NULL
+    NULL -- number of accounts accrued for (out)
+);
+";
+
+pub const PAY_INTEREST: &str = "
+CALL PayInterest(
+    $1,  -- last day of the months to pay
+    $2,  -- external_ref of the account paying interest
+    NULL -- number of payments made (out)
+);
+";
+
+/// SQLSTATE raised by `PayInterest` when the interest account is not set up.
+pub const INTEREST_ACCOUNT_DOES_NOT_EXIST_STATE: &str = "IN001";
@@ -6,6 +6,34 @@ pub use account::*;
 pub(crate) mod transaction;
 pub use transaction::*;
 
+/// `hold` defines SQL queries related to holds reserving funds
+pub(crate) mod hold;
+pub use hold::*;
+
+/// `schedule` defines SQL queries related to scheduled transfers
+pub(crate) mod schedule;
+pub use schedule::*;
+
+/// `interest` defines SQL queries related to interest products and paying interest
+pub(crate) mod interest;
+pub use interest::*;
+
+/// `asset` defines SQL queries related to the asset registry
+pub(crate) mod asset;
+pub use asset::*;
+
+/// `policy` defines SQL queries related to the balance policy of accounts
+pub(crate) mod policy;
+pub use policy::*;
+
+/// `api_key` defines SQL queries related to the API keys clients authenticate with
+pub(crate) mod api_key;
+pub use api_key::*;
+
+/// `idempotency` defines SQL queries related to deduplicating retried requests
+pub(crate) mod idempotency;
+pub use idempotency::*;
+
 /// `setup` defines data structures and materialized views related to setting up DB schema.
 pub(crate) mod setup;
 pub use setup::*;
@@ -0,0 +1,12 @@
+//! A set of SQL statements related to the balance policy of accounts.
+
+pub const SELECT_ACCOUNT_POLICY: &str = "
+SELECT min_balance, overdraft_limit FROM Account
+WHERE id = $1;
+";
+
+pub const UPDATE_ACCOUNT_POLICY: &str = "
+UPDATE Account SET min_balance = $2, overdraft_limit = $3
+WHERE id = $1
+RETURNING min_balance, overdraft_limit;
+";
@@ -0,0 +1,65 @@
+//! A set of SQL statements related to scheduled transfers.
+
+pub const CREATE_NEW_SCHEDULE: &str = "
+CALL InsertSchedule(
+    $1,  -- from_id
+    $2,  -- to_id
+    $3,  -- asset
+    $4,  -- amount
+    $5,  -- cron expression, NULL for a one-off schedule
+    $6,  -- first run
+    NULL -- number of the created schedule (out)
+);
+";
+
+pub const SELECT_SCHEDULE_BY_NUMBER: &str = "
+SELECT Schedule.*, (
+    SELECT max(number) FROM Transaction WHERE schedule_number = Schedule.number
+) AS last_transaction_number
+FROM Schedule
+WHERE number = $1;
+";
+
+pub const SELECT_SCHEDULES_PAGE: &str = "
+SELECT Schedule.*, (
+    SELECT max(number) FROM Transaction WHERE schedule_number = Schedule.number
+) AS last_transaction_number
+FROM Schedule
+WHERE ($1::BIGINT IS NULL OR from_id = $1)
+AND ($2::TEXT IS NULL OR status = $2)
+AND number < COALESCE($3, 9223372036854775807)
+ORDER BY number desc
+LIMIT $4;
+";
+
+pub const LOCK_SCHEDULE: &str = "
+SELECT * FROM Schedule
+WHERE number = $1
+FOR UPDATE;
+";
+
+// Returns nothing once the schedule has been paused, cancelled or run in the meantime.
+pub const LOCK_DUE_SCHEDULE: &str = "
+SELECT * FROM Schedule
+WHERE number = $1 AND status = 'active' AND next_run_at <= now()
+FOR UPDATE;
+";
+
+pub const SELECT_DUE_SCHEDULES: &str = "
+SELECT number FROM Schedule
+WHERE status = 'active' AND next_run_at <= now()
+ORDER BY next_run_at
+LIMIT $1;
+";
+
+pub const UPDATE_SCHEDULE: &str = "
+UPDATE Schedule
+SET status = $2, next_run_at = $3, attempts = $4, last_error = $5
+WHERE number = $1;
+";
+
+pub const LINK_TX_TO_SCHEDULE: &str = "
+UPDATE Transaction
+SET schedule_number = $2
+WHERE number = $1;
+";
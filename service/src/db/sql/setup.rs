@@ -3,103 +3,1252 @@
 pub const SETUP_DATABASE: &str = "
 -------- Schema and SP Setup ----------
 
+-- The default asset is 'TOCOS', see `crate::db::DEFAULT_ASSET`
+CREATE TABLE IF NOT EXISTS Asset(
+    symbol TEXT,
+    decimals SMALLINT NOT NULL,
+    name TEXT NOT NULL,
+    issuer TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (symbol),
+    CHECK (symbol ~ '^[A-Z0-9]{1,12}$'),
+    CHECK (decimals BETWEEN 0 AND 8)
+);
+
+INSERT INTO Asset(symbol, decimals, name, issuer)
+VALUES ('TOCOS', 0, 'Tocos', 'Tocos Bank')
+ON CONFLICT DO NOTHING;
+
+-- An interest product pays `annual_rate` percent a year on balances of `asset`, accrued every day
+-- on the end-of-day balance and paid monthly, see `AccrueInterest` and `PayInterest`. Interest
+-- accrued but not paid yet earns interest itself when `compounding` is 'daily', and only once paid
+-- when 'monthly'. `accrual_basis` is the number of days the annual rate is spread over.
+CREATE TABLE IF NOT EXISTS InterestProduct(
+    code TEXT,
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    annual_rate NUMERIC(28, 8) NOT NULL,
+    compounding TEXT NOT NULL,
+    accrual_basis TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (code),
+    CHECK (code ~ '^[a-z0-9_-]{1,32}$'),
+    CHECK (annual_rate BETWEEN 0 AND 100),
+    CHECK (compounding IN ('daily', 'monthly')),
+    CHECK (accrual_basis IN ('actual_365', 'actual_360', 'actual_actual'))
+);
+
+-- `min_balance` and `overdraft_limit` override the default balance policy when set,
+-- see `crate::db::BalancePolicy`
+-- Ids are allocated by `InsertUser`, whereas `external_ref` is an optional reference of the client's
 CREATE TABLE IF NOT EXISTS Account(
     number BIGSERIAL,
     id BIGINT,  
-    balance BIGINT,        
+    external_ref TEXT UNIQUE,
+    display_name TEXT,
+    owner_ref TEXT,
+    metadata JSONB NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'active',
+    min_balance NUMERIC(28, 8),
+    overdraft_limit NUMERIC(28, 8),
+    tier TEXT NOT NULL DEFAULT 'standard',
+    interest_product TEXT REFERENCES InterestProduct(code),
+    interest_since DATE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
 
-    PRIMARY KEY (id)
+    PRIMARY KEY (id),
+    CHECK (id >= 0),
+    CHECK (jsonb_typeof(metadata) = 'object'),
+    CHECK (status IN ('active', 'frozen', 'closed')),
+    CHECK (min_balance >= 0),
+    CHECK (overdraft_limit >= 0),
+    -- accounts earn interest from the day their product was attached on
+    CHECK ((interest_product IS NULL) = (interest_since IS NULL))
 );
 
-DROP PROCEDURE IF EXISTS InsertUser(
-    IN _id BIGINT,         
-    IN _balance BIGINT);
+-- Databases set up before the columns above were added gain them in place, and have their balances
+-- moved to the ledger once Transaction is set up. Each step is a no-op on databases set up since.
+ALTER TABLE Account
+    ADD COLUMN IF NOT EXISTS external_ref TEXT UNIQUE,
+    ADD COLUMN IF NOT EXISTS display_name TEXT,
+    ADD COLUMN IF NOT EXISTS owner_ref TEXT,
+    ADD COLUMN IF NOT EXISTS metadata JSONB NOT NULL DEFAULT '{}'
+        CHECK (jsonb_typeof(metadata) = 'object'),
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active'
+        CHECK (status IN ('active', 'frozen', 'closed')),
+    ADD COLUMN IF NOT EXISTS min_balance NUMERIC(28, 8) CHECK (min_balance >= 0),
+    ADD COLUMN IF NOT EXISTS overdraft_limit NUMERIC(28, 8) CHECK (overdraft_limit >= 0),
+    ADD COLUMN IF NOT EXISTS tier TEXT NOT NULL DEFAULT 'standard',
+    ADD COLUMN IF NOT EXISTS interest_product TEXT REFERENCES InterestProduct(code),
+    ADD COLUMN IF NOT EXISTS interest_since DATE
+        CHECK ((interest_product IS NULL) = (interest_since IS NULL)),
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now();
 
-CREATE PROCEDURE InsertUser(
-    IN _id BIGINT,         
-    IN _balance BIGINT)  
+-- Audit trail of the status of accounts, along with who changed it and why
+CREATE TABLE IF NOT EXISTS AccountStatusChange(
+    number BIGSERIAL,
+    account_id BIGINT NOT NULL REFERENCES Account(id),
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    swept_to BIGINT REFERENCES Account(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (number)
+);
+
+-- Balance of an account in one asset, cached from its postings. It may be negative for
+-- accounts allowed an overdraft. `held` is the part of it reserved by pending holds, which
+-- stays on the ledger but is not available to transfers.
+CREATE TABLE IF NOT EXISTS Holding(
+    account_id BIGINT REFERENCES Account(id),
+    asset TEXT REFERENCES Asset(symbol),
+    balance NUMERIC(28, 8) NOT NULL DEFAULT 0,
+    held NUMERIC(28, 8) NOT NULL DEFAULT 0,
+
+    PRIMARY KEY (account_id, asset),
+    CHECK (held >= 0)
+);
+
+CREATE TABLE IF NOT EXISTS Journal(
+    number BIGSERIAL,
+    kind TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (number)
+);
+
+-- A posting moves `amount` into (positive) or out of (negative) either a customer account or
+-- one of the bank's own system accounts, e.g. 'funding' for money entering the ledger.
+CREATE TABLE IF NOT EXISTS Posting(
+    number BIGSERIAL,
+    journal_number BIGINT NOT NULL REFERENCES Journal(number),
+    account_id BIGINT REFERENCES Account(id),
+    system_account TEXT,
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    amount NUMERIC(28, 8) NOT NULL,
+
+    PRIMARY KEY (number),
+    CHECK ((account_id IS NULL) <> (system_account IS NULL)),
+    CHECK (amount <> 0)
+);
+
+CREATE OR REPLACE FUNCTION CheckJournalBalanced()
+RETURNS TRIGGER
+
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    IF (
+        SELECT SUM(amount) FROM Posting
+        WHERE journal_number = NEW.journal_number AND asset = NEW.asset
+    ) <> 0 THEN
+        RAISE EXCEPTION 'Journal % is not balanced', NEW.journal_number USING ERRCODE = 'LG001';
+    END IF;
+    RETURN NULL;
+END
+$$;
+
+-- Checked at commit time, once every posting of the journal has been written
+DROP TRIGGER IF EXISTS JournalBalanced ON Posting;
+
+CREATE CONSTRAINT TRIGGER JournalBalanced
+AFTER INSERT ON Posting
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW EXECUTE FUNCTION CheckJournalBalanced();
+
+-- Procedures are dropped before being created again, as their parameters change from version to
+-- version, under every signature they may have been created with
+CREATE OR REPLACE PROCEDURE DropProcedures(IN _name TEXT)
+
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    _procedure REGPROCEDURE;
+BEGIN
+    FOR _procedure IN
+        SELECT oid FROM pg_proc
+        WHERE proname = lower(_name)
+            AND prokind = 'p'
+            AND pronamespace = current_schema()::REGNAMESPACE
+    LOOP
+        EXECUTE format('DROP PROCEDURE %s', _procedure);
+    END LOOP;
+END
+$$;
+
+CALL DropProcedures('UpdateUser');
+
+CALL DropProcedures('PostEntry');
+
+CREATE PROCEDURE PostEntry(
+    IN _journal_number BIGINT,
+    IN _account_id BIGINT,
+    IN _system_account TEXT,
+    IN _asset TEXT,
+    IN _amount NUMERIC)
 
 LANGUAGE plpgsql 
 AS $$ 
 BEGIN 
-    INSERT INTO Account(
-        id,
-        balance
+    INSERT INTO Posting(
+        journal_number,
+        account_id,
+        system_account,
+        asset,
+        amount
     )
     VALUES
     (
-        _id,         
-        _balance
+        _journal_number,
+        _account_id,
+        _system_account,
+        _asset,
+        _amount
     );
+
+    -- Holding.balance is a cache of the postings, kept in step within the same transaction
+    IF _account_id IS NOT NULL THEN
+        INSERT INTO Holding(account_id, asset, balance)
+        VALUES (_account_id, _asset, _amount)
+        ON CONFLICT (account_id, asset)
+        DO UPDATE SET balance = Holding.balance + EXCLUDED.balance;
+    END IF;
 END 
 $$;
 
-DROP PROCEDURE IF EXISTS UpdateUser(
-    IN _id BIGINT,         
-    IN _amount BIGINT,
-    IN _flag BIGINT);
+CALL DropProcedures('CheckAmount');
 
-CREATE PROCEDURE UpdateUser(
-    IN _id BIGINT,         
-    IN _amount BIGINT,
-    IN _flag BIGINT)  
+CREATE PROCEDURE CheckAmount(
+    IN _asset TEXT,
+    IN _amount NUMERIC)
+
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    _decimals SMALLINT;
+BEGIN
+    SELECT decimals INTO _decimals FROM Asset WHERE symbol = _asset;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Asset % does not exist', _asset USING ERRCODE = 'AS001';
+    END IF;
+
+    -- An asset cannot be divided any further than its decimal places
+    IF _amount <> round(_amount, _decimals) THEN
+        RAISE EXCEPTION 'Amount % has more decimal places than % allows', _amount, _asset
+            USING ERRCODE = 'AS002';
+    END IF;
+END
+$$;
+
+CALL DropProcedures('InsertUser');
+
+CREATE PROCEDURE InsertUser(
+    IN _asset TEXT,
+    IN _balance NUMERIC,
+    IN _external_ref TEXT,
+    IN _display_name TEXT,
+    IN _owner_ref TEXT,
+    IN _metadata JSONB,
+    IN _tier TEXT,
+    INOUT _id BIGINT)  
 
 LANGUAGE plpgsql 
 AS $$ 
+DECLARE
+    _journal_number BIGINT;
 BEGIN 
-    IF _flag = 0 THEN
-        -- Update sender's balance
-        UPDATE Account
-        SET balance = balance - _amount
-        WHERE id = _id;
-    ELSE
-        -- Update receiver's balance
-        UPDATE Account
-        SET balance = balance + _amount
-        WHERE id = _id;
+    CALL CheckAmount(_asset, _balance);
+
+    -- Ids are random, so that they cannot be enumerated, and stay within 53 bits, so that
+    -- clients parsing JSON numbers as doubles read them back exactly
+    LOOP
+        _id := ('x' || substr(md5(gen_random_uuid()::TEXT), 1, 16))::BIT(64)::BIGINT
+            & 9007199254740991;
+        EXIT WHEN _id > 0 AND NOT EXISTS (SELECT 1 FROM Account WHERE id = _id);
+    END LOOP;
+
+    INSERT INTO Account(
+        id,
+        external_ref,
+        display_name,
+        owner_ref,
+        metadata,
+        tier
+    )
+    VALUES
+    (
+        _id,
+        _external_ref,
+        _display_name,
+        _owner_ref,
+        _metadata,
+        _tier
+    );
+
+    -- The account holds its opening asset even when opened with a zero balance
+    INSERT INTO Holding(account_id, asset) VALUES (_id, _asset);
+
+    -- The opening balance enters the ledger from the bank's funding account
+    IF _balance > 0 THEN
+        INSERT INTO Journal(kind) VALUES ('opening') RETURNING number INTO _journal_number;
+        CALL PostEntry(_journal_number, NULL, 'funding', _asset, -_balance);
+        CALL PostEntry(_journal_number, _id, NULL, _asset, _balance);
     END IF;
 END 
 $$;
 
+-- A schedule transfers `amount` from `from_id` to `to_id` at `next_run_at`, once or every time its
+-- cron `recurrence` comes round. Runs falling short of balance are retried, counted in `attempts`,
+-- and the transactions that runs create name the schedule in `schedule_number`.
+CREATE TABLE IF NOT EXISTS Schedule(
+    number BIGSERIAL,
+    from_id BIGINT NOT NULL REFERENCES Account(id),
+    to_id BIGINT NOT NULL REFERENCES Account(id),
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    amount NUMERIC(28, 8) NOT NULL,
+    recurrence TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    next_run_at TIMESTAMPTZ,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (number),
+    CHECK (amount > 0),
+    CHECK (from_id <> to_id),
+    CHECK (status IN ('active', 'paused', 'completed', 'failed', 'cancelled')),
+    -- only schedules that may still run have a next run
+    CHECK ((status IN ('active', 'paused')) = (next_run_at IS NOT NULL))
+);
+
+-- A reversal refunds part or all of the transfer it `reverses`, moving funds the other way
 CREATE TABLE IF NOT EXISTS Transaction(
     number BIGSERIAL,
-    from_id BIGINT,  
-    to_id BIGINT,  
-    amount BIGINT,  
+    from_id BIGINT NOT NULL REFERENCES Account(id),  
+    to_id BIGINT REFERENCES Account(id),  
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    amount NUMERIC(28, 8) NOT NULL,  
+    from_balance_after NUMERIC(28, 8) NOT NULL,
+    to_balance_after NUMERIC(28, 8),
+    status TEXT NOT NULL DEFAULT 'completed',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    journal_number BIGINT REFERENCES Journal(number),
+    kind TEXT NOT NULL DEFAULT 'transfer',
+    reverses BIGINT REFERENCES Transaction(number),
+    fee NUMERIC(28, 8) NOT NULL DEFAULT 0,
+    schedule_number BIGINT REFERENCES Schedule(number),
 
-    PRIMARY KEY (number)
+    PRIMARY KEY (number),
+    CHECK (amount > 0),
+    CHECK (fee >= 0),
+    CHECK (from_id <> to_id),
+    CHECK (kind IN ('transfer', 'reversal', 'split', 'interest')),
+    CHECK ((kind = 'reversal') = (reverses IS NOT NULL)),
+    -- splits credit their recipients through TransactionLeg instead
+    CHECK ((kind = 'split') = (to_id IS NULL)),
+    CHECK ((to_id IS NULL) = (to_balance_after IS NULL))
 );
 
-DROP PROCEDURE IF EXISTS InsertTx(
-    IN _from_id BIGINT,     
-    IN _to_id BIGINT,             
-    IN _amount BIGINT);
+-- As for Account, the balances after are filled in and made mandatory just below
+ALTER TABLE Transaction
+    ADD COLUMN IF NOT EXISTS asset TEXT NOT NULL DEFAULT 'TOCOS' REFERENCES Asset(symbol),
+    ADD COLUMN IF NOT EXISTS from_balance_after NUMERIC(28, 8),
+    ADD COLUMN IF NOT EXISTS to_balance_after NUMERIC(28, 8),
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'completed',
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS journal_number BIGINT REFERENCES Journal(number),
+    ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'transfer'
+        CHECK (kind IN ('transfer', 'reversal', 'split', 'interest')),
+    ADD COLUMN IF NOT EXISTS reverses BIGINT REFERENCES Transaction(number),
+    ADD COLUMN IF NOT EXISTS fee NUMERIC(28, 8) NOT NULL DEFAULT 0 CHECK (fee >= 0),
+    ADD COLUMN IF NOT EXISTS schedule_number BIGINT REFERENCES Schedule(number);
 
+-- Databases set up before the ledger kept a whole-number balance on Account, and nothing but the
+-- amounts of transactions. Their balances are carried over as opening journals from 'funding', and
+-- the balances after each earlier transaction are worked back from them. Constraints that the old
+-- rows were never held to are enforced on new rows only. Does nothing once `Account.balance` is gone.
+DO $$
+DECLARE
+    _account RECORD;
+    _journal_number BIGINT;
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema()
+            AND table_name = 'account'
+            AND column_name = 'balance'
+    ) THEN
+        RETURN;
+    END IF;
+
+    INSERT INTO Holding(account_id, asset)
+    SELECT id, 'TOCOS' FROM Account
+    ON CONFLICT DO NOTHING;
+
+    FOR _account IN
+        SELECT id, balance FROM Account WHERE balance <> 0 ORDER BY number
+    LOOP
+        INSERT INTO Journal(kind) VALUES ('opening') RETURNING number INTO _journal_number;
+        CALL PostEntry(_journal_number, NULL, 'funding', 'TOCOS', -_account.balance);
+        CALL PostEntry(_journal_number, _account.id, NULL, 'TOCOS', _account.balance);
+    END LOOP;
+
+    -- Checked right away rather than at commit, as pending checks keep Posting from being indexed
+    SET CONSTRAINTS JournalBalanced IMMEDIATE;
+    SET CONSTRAINTS JournalBalanced DEFERRED;
+
+    ALTER TABLE Transaction
+        ALTER COLUMN amount TYPE NUMERIC(28, 8),
+        ALTER COLUMN asset DROP DEFAULT;
+
+    -- The balance after a transaction is today's, less what every later one moved in and out
+    UPDATE Transaction t
+    SET from_balance_after = (SELECT COALESCE(balance, 0) FROM Account WHERE id = t.from_id)
+            - COALESCE((
+                SELECT SUM(CASE WHEN later.to_id = t.from_id THEN later.amount ELSE -later.amount END)
+                FROM Transaction later
+                WHERE later.number > t.number AND t.from_id IN (later.from_id, later.to_id)
+            ), 0),
+        to_balance_after = (SELECT COALESCE(balance, 0) FROM Account WHERE id = t.to_id)
+            - COALESCE((
+                SELECT SUM(CASE WHEN later.to_id = t.to_id THEN later.amount ELSE -later.amount END)
+                FROM Transaction later
+                WHERE later.number > t.number AND t.to_id IN (later.from_id, later.to_id)
+            ), 0);
+
+    ALTER TABLE Transaction
+        ALTER COLUMN from_id SET NOT NULL,
+        ALTER COLUMN amount SET NOT NULL,
+        ALTER COLUMN from_balance_after SET NOT NULL,
+        ADD FOREIGN KEY (from_id) REFERENCES Account(id) NOT VALID,
+        ADD FOREIGN KEY (to_id) REFERENCES Account(id) NOT VALID,
+        ADD CHECK (amount > 0) NOT VALID,
+        ADD CHECK (from_id <> to_id) NOT VALID,
+        ADD CHECK ((kind = 'reversal') = (reverses IS NOT NULL)) NOT VALID,
+        ADD CHECK ((kind = 'split') = (to_id IS NULL)) NOT VALID,
+        ADD CHECK ((to_id IS NULL) = (to_balance_after IS NULL)) NOT VALID;
+
+    ALTER TABLE Account
+        DROP COLUMN balance,
+        ADD CHECK (id >= 0) NOT VALID;
+END
+$$;
+
+-- A leg credits one recipient of a split with its share of the parent transaction's amount
+CREATE TABLE IF NOT EXISTS TransactionLeg(
+    transaction_number BIGINT NOT NULL REFERENCES Transaction(number),
+    position INT NOT NULL,
+    to_id BIGINT NOT NULL REFERENCES Account(id),
+    amount NUMERIC(28, 8) NOT NULL,
+    to_balance_after NUMERIC(28, 8) NOT NULL,
+
+    PRIMARY KEY (transaction_number, position),
+    UNIQUE (transaction_number, to_id),
+    CHECK (amount > 0)
+);
+
+CALL DropProcedures('InsertTx');
+
+-- `_fee` is what the sender paid on top of `_amount`, see `TransferFunds`.
 CREATE PROCEDURE InsertTx(
     IN _from_id BIGINT,     
     IN _to_id BIGINT,             
-    IN _amount BIGINT)  
+    IN _asset TEXT,
+    IN _amount NUMERIC,
+    IN _journal_number BIGINT,
+    IN _reverses BIGINT,
+    IN _fee NUMERIC,
+    INOUT _number BIGINT)  
 
 LANGUAGE plpgsql 
 AS $$ 
 BEGIN 
+    -- Balances are read after both parties have been updated, so they reflect this transaction
     INSERT INTO Transaction(
         from_id,
         to_id,
-        amount
+        asset,
+        amount,
+        from_balance_after,
+        to_balance_after,
+        journal_number,
+        kind,
+        reverses,
+        fee
     )
     VALUES
     (
         _from_id,     
         _to_id,    
-        _amount
+        _asset,
+        _amount,
+        (SELECT balance FROM Holding WHERE account_id = _from_id AND asset = _asset),
+        (SELECT balance FROM Holding WHERE account_id = _to_id AND asset = _asset),
+        _journal_number,
+        CASE WHEN _reverses IS NULL THEN 'transfer' ELSE 'reversal' END,
+        _reverses,
+        _fee
+    )
+    RETURNING number INTO _number;
+END 
+$$;
+
+-- Funds can neither leave nor reach frozen and closed accounts
+CALL DropProcedures('CheckAccountActive');
+
+CREATE PROCEDURE CheckAccountActive(
+    IN _id BIGINT,
+    IN _status TEXT)
+
+LANGUAGE plpgsql 
+AS $$ 
+BEGIN 
+    IF _status = 'frozen' THEN
+        RAISE EXCEPTION 'Account % is frozen', _id USING ERRCODE = 'TF005', DETAIL = _id;
+    ELSIF _status = 'closed' THEN
+        RAISE EXCEPTION 'Account % is closed', _id USING ERRCODE = 'TF006', DETAIL = _id;
+    END IF;
+END 
+$$;
+
+CALL DropProcedures('TransferFunds');
+
+-- Locks both parties of a transfer and checks that `_from_id` can send `_amount` of `_asset` to
+-- `_to_id`. `_min_balance` and `_overdraft_limit` are the default policy, for senders without overrides.
+CALL DropProcedures('CheckTransfer');
+
+CREATE PROCEDURE CheckTransfer(
+    IN _from_id BIGINT,     
+    IN _to_id BIGINT,             
+    IN _asset TEXT,
+    IN _amount NUMERIC,
+    IN _min_balance NUMERIC,
+    IN _overdraft_limit NUMERIC)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _status TEXT;
+    _from_balance NUMERIC;
+    _required NUMERIC;
+BEGIN 
+    -- Lock both accounts in ascending id order so that concurrent transfers cannot deadlock
+    PERFORM id FROM Account
+    WHERE id IN (_from_id, _to_id)
+    ORDER BY id asc
+    FOR UPDATE;
+
+    CALL CheckAmount(_asset, _amount);
+
+    SELECT status, COALESCE(min_balance, _min_balance), COALESCE(overdraft_limit, _overdraft_limit)
+    INTO _status, _min_balance, _overdraft_limit
+    FROM Account WHERE id = _from_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Sender % does not exist', _from_id USING ERRCODE = 'TF001';
+    END IF;
+    CALL CheckAccountActive(_from_id, _status);
+
+    -- Funds can only move in an asset the sender holds, recipients start holding it on receipt.
+    -- Funds reserved by holds are not available.
+    SELECT balance - held INTO _from_balance
+    FROM Holding WHERE account_id = _from_id AND asset = _asset;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Sender % does not hold %', _from_id, _asset USING ERRCODE = 'TF004';
+    END IF;
+
+    -- The balance may not fall below the minimum balance, less the overdraft the sender is allowed
+    _required := _amount + _min_balance - _overdraft_limit;
+    IF _from_balance < _required THEN
+        RAISE EXCEPTION 'Sender % does not have enough balance', _from_id
+        USING ERRCODE = 'TF003', DETAIL = _required;
+    END IF;
+
+    SELECT status INTO _status FROM Account WHERE id = _to_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Recipient % does not exist', _to_id USING ERRCODE = 'TF002';
+    END IF;
+    CALL CheckAccountActive(_to_id, _status);
+END 
+$$;
+
+-- `_reverses` is the transaction a reversal refunds, see `ReverseTransfer`. The sender pays `_fee`
+-- on top of `_amount`, credited to the account whose external_ref is `_fee_account_ref`.
+CREATE PROCEDURE TransferFunds(
+    IN _from_id BIGINT,     
+    IN _to_id BIGINT,             
+    IN _asset TEXT,
+    IN _amount NUMERIC,
+    IN _min_balance NUMERIC,
+    IN _overdraft_limit NUMERIC,
+    IN _reverses BIGINT,
+    IN _fee NUMERIC,
+    IN _fee_account_ref TEXT,
+    INOUT _number BIGINT)  
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _journal_number BIGINT;
+    _fee_account_id BIGINT;
+BEGIN 
+    IF _fee > 0 THEN
+        SELECT id INTO _fee_account_id FROM Account WHERE external_ref = _fee_account_ref;
+        IF NOT FOUND THEN
+            RAISE EXCEPTION 'Fee account % does not exist', _fee_account_ref USING ERRCODE = 'FE001';
+        END IF;
+    END IF;
+
+    -- The sender has to afford the fee along with the amount
+    CALL CheckTransfer(_from_id, _to_id, _asset, _amount + _fee, _min_balance, _overdraft_limit);
+
+    INSERT INTO Journal(kind)
+    VALUES (CASE WHEN _reverses IS NULL THEN 'transfer' ELSE 'reversal' END)
+    RETURNING number INTO _journal_number;
+    CALL PostEntry(_journal_number, _from_id, NULL, _asset, -_amount);
+    CALL PostEntry(_journal_number, _to_id, NULL, _asset, _amount);
+    IF _fee > 0 THEN
+        CALL PostEntry(_journal_number, _from_id, NULL, _asset, -_fee);
+        CALL PostEntry(_journal_number, _fee_account_id, NULL, _asset, _fee);
+    END IF;
+    CALL InsertTx(_from_id, _to_id, _asset, _amount, _journal_number, _reverses, _fee, _number);
+END 
+$$;
+
+-- Moves `_amount` from `_from_id` to every recipient of `_to_ids`, each receiving the amount at the
+-- same position of `_amounts`. The journal rejects shares that do not sum up to `_amount`. The
+-- sender pays `_fee` on top of `_amount`, as for `TransferFunds`.
+CALL DropProcedures('SplitFunds');
+
+CREATE PROCEDURE SplitFunds(
+    IN _from_id BIGINT,
+    IN _asset TEXT,
+    IN _amount NUMERIC,
+    IN _to_ids BIGINT[],
+    IN _amounts NUMERIC[],
+    IN _min_balance NUMERIC,
+    IN _overdraft_limit NUMERIC,
+    IN _fee NUMERIC,
+    IN _fee_account_ref TEXT,
+    INOUT _number BIGINT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _journal_number BIGINT;
+    _fee_account_id BIGINT;
+    _status TEXT;
+BEGIN 
+    IF _fee > 0 THEN
+        SELECT id INTO _fee_account_id FROM Account WHERE external_ref = _fee_account_ref;
+        IF NOT FOUND THEN
+            RAISE EXCEPTION 'Fee account % does not exist', _fee_account_ref USING ERRCODE = 'FE001';
+        END IF;
+    END IF;
+
+    -- Lock every party in ascending id order, as `CheckTransfer` does for two of them
+    PERFORM id FROM Account
+    WHERE id = _from_id OR id = ANY(_to_ids)
+    ORDER BY id asc
+    FOR UPDATE;
+
+    -- The sender is checked against the whole amount and the fee, along with the first recipient
+    CALL CheckTransfer(_from_id, _to_ids[1], _asset, _amount + _fee, _min_balance, _overdraft_limit);
+
+    INSERT INTO Journal(kind) VALUES ('split') RETURNING number INTO _journal_number;
+    CALL PostEntry(_journal_number, _from_id, NULL, _asset, -_amount);
+    FOR _position IN 1 .. array_length(_to_ids, 1) LOOP
+        CALL CheckAmount(_asset, _amounts[_position]);
+
+        SELECT status INTO _status FROM Account WHERE id = _to_ids[_position];
+        IF NOT FOUND THEN
+            RAISE EXCEPTION 'Recipient % does not exist', _to_ids[_position] USING ERRCODE = 'TF002';
+        END IF;
+        CALL CheckAccountActive(_to_ids[_position], _status);
+
+        CALL PostEntry(_journal_number, _to_ids[_position], NULL, _asset, _amounts[_position]);
+    END LOOP;
+    IF _fee > 0 THEN
+        CALL PostEntry(_journal_number, _from_id, NULL, _asset, -_fee);
+        CALL PostEntry(_journal_number, _fee_account_id, NULL, _asset, _fee);
+    END IF;
+
+    INSERT INTO Transaction(from_id, asset, amount, from_balance_after, journal_number, kind, fee)
+    VALUES
+    (
+        _from_id,
+        _asset,
+        _amount,
+        (SELECT balance FROM Holding WHERE account_id = _from_id AND asset = _asset),
+        _journal_number,
+        'split',
+        _fee
+    )
+    RETURNING number INTO _number;
+
+    INSERT INTO TransactionLeg(transaction_number, position, to_id, amount, to_balance_after)
+    SELECT _number, leg.position, leg.to_id, leg.amount, Holding.balance
+    FROM unnest(_to_ids, _amounts) WITH ORDINALITY AS leg(to_id, amount, position)
+    JOIN Holding ON Holding.account_id = leg.to_id AND Holding.asset = _asset;
+END 
+$$;
+
+-- Refunds `_amount` of the transfer `_number` to its sender, or whatever has not been refunded
+-- yet when `_amount` is NULL. The recipient pays the refund under its own balance policy.
+CALL DropProcedures('ReverseTransfer');
+
+CREATE PROCEDURE ReverseTransfer(
+    IN _number BIGINT,
+    IN _amount NUMERIC,
+    IN _min_balance NUMERIC,
+    IN _overdraft_limit NUMERIC,
+    INOUT _reversal_number BIGINT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _original RECORD;
+    _remaining NUMERIC;
+BEGIN 
+    -- Locking the original serializes concurrent reversals of it
+    SELECT from_id, to_id, asset, amount, kind INTO _original
+    FROM Transaction WHERE number = _number
+    FOR UPDATE;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Transaction % does not exist', _number USING ERRCODE = 'RV001';
+    END IF;
+
+    -- Reversals refund a single recipient, which neither reversals nor splits have
+    IF _original.kind <> 'transfer' THEN
+        RAISE EXCEPTION 'Transaction % is not a transfer', _number USING ERRCODE = 'RV002';
+    END IF;
+
+    SELECT _original.amount - COALESCE(SUM(amount), 0) INTO _remaining
+    FROM Transaction WHERE reverses = _number;
+
+    _amount := COALESCE(_amount, _remaining);
+    IF _remaining = 0 OR _amount > _remaining THEN
+        RAISE EXCEPTION 'Transaction % has % left to reverse', _number, _remaining
+        USING ERRCODE = 'RV003', DETAIL = _remaining;
+    END IF;
+
+    CALL TransferFunds(
+        _original.to_id,
+        _original.from_id,
+        _original.asset,
+        _amount,
+        _min_balance,
+        _overdraft_limit,
+        _number,
+        0,
+        NULL,
+        _reversal_number
     );
 END 
 $$;
 
+-- Creates a schedule once its asset and both parties are found active. Balances are only checked
+-- when the schedule runs.
+CALL DropProcedures('InsertSchedule');
+
+CREATE PROCEDURE InsertSchedule(
+    IN _from_id BIGINT,
+    IN _to_id BIGINT,
+    IN _asset TEXT,
+    IN _amount NUMERIC,
+    IN _recurrence TEXT,
+    IN _next_run_at TIMESTAMPTZ,
+    INOUT _number BIGINT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _status TEXT;
+BEGIN 
+    CALL CheckAmount(_asset, _amount);
+
+    SELECT status INTO _status FROM Account WHERE id = _from_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Sender % does not exist', _from_id USING ERRCODE = 'TF001';
+    END IF;
+    CALL CheckAccountActive(_from_id, _status);
+
+    SELECT status INTO _status FROM Account WHERE id = _to_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Recipient % does not exist', _to_id USING ERRCODE = 'TF002';
+    END IF;
+    CALL CheckAccountActive(_to_id, _status);
+
+    INSERT INTO Schedule(from_id, to_id, asset, amount, recurrence, next_run_at)
+    VALUES (_from_id, _to_id, _asset, _amount, _recurrence, _next_run_at)
+    RETURNING number INTO _number;
+END 
+$$;
+
+-- A hold reserves `amount` of the sender's balance for a transfer to `to_id`, counting towards
+-- `Holding.held` until it is captured, voided or expires. Capturing a hold records the
+-- transaction it resulted in.
+CREATE TABLE IF NOT EXISTS Hold(
+    number BIGSERIAL,
+    from_id BIGINT NOT NULL REFERENCES Account(id),
+    to_id BIGINT NOT NULL REFERENCES Account(id),
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    amount NUMERIC(28, 8) NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    captured_amount NUMERIC(28, 8),
+    transaction_number BIGINT REFERENCES Transaction(number),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    settled_at TIMESTAMPTZ,
+
+    PRIMARY KEY (number),
+    CHECK (amount > 0),
+    CHECK (from_id <> to_id),
+    CHECK (status IN ('pending', 'captured', 'voided', 'expired')),
+    CHECK (captured_amount > 0 AND captured_amount <= amount)
+);
+
+-- Reserves `_amount` for a transfer checked as `TransferFunds` does, for `_ttl_secs` seconds
+CALL DropProcedures('PlaceHold');
+
+CREATE PROCEDURE PlaceHold(
+    IN _from_id BIGINT,
+    IN _to_id BIGINT,
+    IN _asset TEXT,
+    IN _amount NUMERIC,
+    IN _min_balance NUMERIC,
+    IN _overdraft_limit NUMERIC,
+    IN _ttl_secs BIGINT,
+    INOUT _number BIGINT)
+
+LANGUAGE plpgsql 
+AS $$ 
+BEGIN 
+    CALL CheckTransfer(_from_id, _to_id, _asset, _amount, _min_balance, _overdraft_limit);
+
+    UPDATE Holding SET held = held + _amount WHERE account_id = _from_id AND asset = _asset;
+
+    INSERT INTO Hold(from_id, to_id, asset, amount, expires_at)
+    VALUES (_from_id, _to_id, _asset, _amount, now() + _ttl_secs * INTERVAL '1 second')
+    RETURNING number INTO _number;
+END 
+$$;
+
+-- Moves a pending hold to `_to_status`, releasing its reserved funds. Capturing transfers
+-- `_amount` of them, or all of them when `_amount` is NULL, and releases the rest. The sender pays
+-- `_fee` on top of the amount captured, as for `TransferFunds`. The fee was not reserved along with
+-- the hold, so the sender has to afford it under its balance policy when captured.
+CALL DropProcedures('SettleHold');
+
+CREATE PROCEDURE SettleHold(
+    IN _number BIGINT,
+    IN _to_status TEXT,
+    IN _amount NUMERIC,
+    IN _min_balance NUMERIC,
+    IN _overdraft_limit NUMERIC,
+    IN _fee NUMERIC,
+    IN _fee_account_ref TEXT,
+    INOUT _transaction_number BIGINT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _hold RECORD;
+    _status TEXT;
+    _journal_number BIGINT;
+    _fee_account_id BIGINT;
+BEGIN 
+    SELECT from_id, to_id INTO _hold FROM Hold WHERE number = _number;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Hold % does not exist', _number USING ERRCODE = 'HD001';
+    END IF;
+
+    -- Lock both accounts before the hold itself, in the same order as `CheckTransfer`
+    PERFORM id FROM Account
+    WHERE id IN (_hold.from_id, _hold.to_id)
+    ORDER BY id asc
+    FOR UPDATE;
+
+    SELECT * INTO _hold FROM Hold WHERE number = _number FOR UPDATE;
+
+    -- Holds past their expiry can only expire, even before they are released
+    IF _hold.status = 'pending' AND _to_status <> 'expired' AND _hold.expires_at <= now() THEN
+        RAISE EXCEPTION 'Hold % has expired', _number USING ERRCODE = 'HD002', DETAIL = 'expired';
+    END IF;
+    IF _hold.status <> 'pending' THEN
+        RAISE EXCEPTION 'Hold % is %', _number, _hold.status
+        USING ERRCODE = 'HD002', DETAIL = _hold.status;
+    END IF;
+
+    UPDATE Holding SET held = held - _hold.amount
+    WHERE account_id = _hold.from_id AND asset = _hold.asset;
+
+    IF _to_status = 'captured' THEN
+        _amount := COALESCE(_amount, _hold.amount);
+        IF _amount > _hold.amount THEN
+            RAISE EXCEPTION 'Hold % reserves less than %', _number, _amount
+            USING ERRCODE = 'HD003', DETAIL = _hold.amount;
+        END IF;
+        CALL CheckAmount(_hold.asset, _amount);
+
+        -- Reserved funds were checked against the sender's policy when placing the hold, whereas
+        -- frozen and closed accounts cannot move them
+        SELECT status INTO _status FROM Account WHERE id = _hold.from_id;
+        CALL CheckAccountActive(_hold.from_id, _status);
+        SELECT status INTO _status FROM Account WHERE id = _hold.to_id;
+        CALL CheckAccountActive(_hold.to_id, _status);
+
+        IF _fee > 0 THEN
+            SELECT id INTO _fee_account_id FROM Account WHERE external_ref = _fee_account_ref;
+            IF NOT FOUND THEN
+                RAISE EXCEPTION 'Fee account % does not exist', _fee_account_ref
+                USING ERRCODE = 'FE001';
+            END IF;
+
+            -- The funds on hold have been released above, so they count towards the balance
+            CALL CheckTransfer(
+                _hold.from_id, _hold.to_id, _hold.asset, _amount + _fee, _min_balance,
+                _overdraft_limit
+            );
+        END IF;
+
+        INSERT INTO Journal(kind) VALUES ('capture') RETURNING number INTO _journal_number;
+        CALL PostEntry(_journal_number, _hold.from_id, NULL, _hold.asset, -_amount);
+        CALL PostEntry(_journal_number, _hold.to_id, NULL, _hold.asset, _amount);
+        IF _fee > 0 THEN
+            CALL PostEntry(_journal_number, _hold.from_id, NULL, _hold.asset, -_fee);
+            CALL PostEntry(_journal_number, _fee_account_id, NULL, _hold.asset, _fee);
+        END IF;
+        CALL InsertTx(
+            _hold.from_id, _hold.to_id, _hold.asset, _amount, _journal_number, NULL, _fee,
+            _transaction_number
+        );
+    END IF;
+
+    UPDATE Hold SET
+        status = _to_status,
+        captured_amount = CASE WHEN _to_status = 'captured' THEN _amount END,
+        transaction_number = _transaction_number,
+        settled_at = now()
+    WHERE number = _number;
+END 
+$$;
+
+-- Accounts move from 'active' to 'frozen' and back, and from either to 'closed' for good. Accounts
+-- are closed once they hold nothing, which sweeping their positive balances to `_sweep_to` achieves.
+CALL DropProcedures('ChangeAccountStatus');
+
+CREATE PROCEDURE ChangeAccountStatus(
+    IN _id BIGINT,
+    IN _to_status TEXT,
+    IN _actor TEXT,
+    IN _reason TEXT,
+    IN _sweep_to BIGINT,
+    INOUT _number BIGINT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _from_status TEXT;
+    _status TEXT;
+    _holding RECORD;
+    _journal_number BIGINT;
+    _tx_number BIGINT;
+BEGIN 
+    -- Lock accounts in ascending id order, as `TransferFunds` does
+    PERFORM id FROM Account
+    WHERE id IN (_id, _sweep_to)
+    ORDER BY id asc
+    FOR UPDATE;
+
+    SELECT status INTO _from_status FROM Account WHERE id = _id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Account % does not exist', _id USING ERRCODE = 'AC001';
+    END IF;
+
+    IF NOT (
+        (_from_status = 'active' AND _to_status = 'frozen') OR
+        (_from_status = 'frozen' AND _to_status = 'active') OR
+        (_from_status <> 'closed' AND _to_status = 'closed')
+    ) THEN
+        RAISE EXCEPTION 'Account % cannot become % when %', _id, _to_status, _from_status
+        USING ERRCODE = 'AC002', DETAIL = _from_status;
+    END IF;
+
+    IF _to_status = 'closed' AND _sweep_to IS NOT NULL THEN
+        SELECT status INTO _status FROM Account WHERE id = _sweep_to;
+        IF NOT FOUND THEN
+            RAISE EXCEPTION 'Recipient % does not exist', _sweep_to USING ERRCODE = 'TF002';
+        END IF;
+        CALL CheckAccountActive(_sweep_to, _status);
+
+        FOR _holding IN
+            SELECT asset, balance FROM Holding
+            WHERE account_id = _id AND balance > 0
+            ORDER BY asset
+        LOOP
+            IF _journal_number IS NULL THEN
+                INSERT INTO Journal(kind) VALUES ('sweep') RETURNING number INTO _journal_number;
+            END IF;
+            CALL PostEntry(_journal_number, _id, NULL, _holding.asset, -_holding.balance);
+            CALL PostEntry(_journal_number, _sweep_to, NULL, _holding.asset, _holding.balance);
+            CALL InsertTx(
+                _id, _sweep_to, _holding.asset, _holding.balance, _journal_number, NULL, 0,
+                _tx_number
+            );
+        END LOOP;
+    END IF;
+
+    -- Overdrawn balances cannot be swept, they need to be settled first, as do pending holds
+    IF _to_status = 'closed' AND EXISTS (
+        SELECT 1 FROM Holding WHERE account_id = _id AND (balance <> 0 OR held <> 0)
+    ) THEN
+        RAISE EXCEPTION 'Account % still holds funds', _id USING ERRCODE = 'AC003';
+    END IF;
+
+    UPDATE Account SET status = _to_status WHERE id = _id;
+
+    INSERT INTO AccountStatusChange(account_id, from_status, to_status, actor, reason, swept_to)
+    VALUES (_id, _from_status, _to_status, _actor, _reason, _sweep_to)
+    RETURNING number INTO _number;
+END 
+$$;
+
+-- Interest an account earned over one `day`, under the product it had at the end of it. Days are UTC
+-- dates. Accounts switching products accrue the days owed under the former first, see
+-- `crate::db::Database::set_account_interest`.
+CREATE TABLE IF NOT EXISTS InterestAccrual(
+    account_id BIGINT NOT NULL REFERENCES Account(id),
+    day DATE NOT NULL,
+    product TEXT NOT NULL REFERENCES InterestProduct(code),
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    basis NUMERIC(28, 8) NOT NULL,
+    amount NUMERIC(28, 8) NOT NULL,
+
+    PRIMARY KEY (account_id, day),
+    CHECK (basis >= 0),
+    CHECK (amount >= 0)
+);
+
+-- Interest accrued over the month starting on `period`, paid to the account as `amount` by the
+-- transaction `transaction_number`, unless it rounds down to nothing
+CREATE TABLE IF NOT EXISTS InterestPayment(
+    account_id BIGINT NOT NULL REFERENCES Account(id),
+    period DATE NOT NULL,
+    asset TEXT NOT NULL REFERENCES Asset(symbol),
+    accrued NUMERIC(28, 8) NOT NULL,
+    amount NUMERIC(28, 8) NOT NULL,
+    transaction_number BIGINT REFERENCES Transaction(number),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (account_id, period, asset),
+    CHECK (extract(day FROM period) = 1),
+    CHECK ((amount > 0) = (transaction_number IS NOT NULL))
+);
+
+-- Accrues the interest every account earning it made on `_day`, which has to be over, or only the
+-- account `_account_id` if given. Balances are read from the journal as of the end of the day,
+-- leaving out interest payments, which count from the month after the one they pay for instead.
+-- Days accrued already are left as they are, so that running a day again changes nothing.
+CALL DropProcedures('AccrueInterest');
+
+CREATE PROCEDURE AccrueInterest(
+    IN _day DATE,
+    IN _account_id BIGINT,
+    INOUT _accrued INT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _period DATE := make_date(extract(year FROM _day)::INT, extract(month FROM _day)::INT, 1);
+    _end_of_day TIMESTAMPTZ := (_day + 1)::TIMESTAMP AT TIME ZONE 'UTC';
+    _days_in_year INT := make_date(extract(year FROM _day)::INT + 1, 1, 1)
+        - make_date(extract(year FROM _day)::INT, 1, 1);
+BEGIN 
+    INSERT INTO InterestAccrual(account_id, day, product, asset, basis, amount)
+    SELECT
+        account_id,
+        _day,
+        code,
+        asset,
+        basis,
+        round(
+            basis * annual_rate / 100 / CASE accrual_basis
+                WHEN 'actual_365' THEN 365
+                WHEN 'actual_360' THEN 360
+                ELSE _days_in_year
+            END,
+            8
+        )
+    FROM (
+        SELECT
+            Account.id AS account_id,
+            InterestProduct.*,
+            GREATEST(0,
+                (
+                    SELECT COALESCE(SUM(Posting.amount), 0)
+                    FROM Posting JOIN Journal ON Journal.number = Posting.journal_number
+                    WHERE Posting.account_id = Account.id AND Posting.asset = InterestProduct.asset
+                    AND Journal.kind <> 'interest' AND Journal.created_at < _end_of_day
+                ) + (
+                    SELECT COALESCE(SUM(amount), 0) FROM InterestPayment
+                    WHERE account_id = Account.id AND asset = InterestProduct.asset
+                    AND period < _period
+                ) + (
+                    SELECT COALESCE(SUM(amount), 0) FROM InterestAccrual
+                    WHERE account_id = Account.id AND asset = InterestProduct.asset
+                    AND day >= _period AND day < _day
+                    AND InterestProduct.compounding = 'daily'
+                )
+            ) AS basis
+        FROM Account JOIN InterestProduct ON InterestProduct.code = Account.interest_product
+        WHERE Account.status <> 'closed' AND Account.interest_since <= _day
+        AND (_account_id IS NULL OR Account.id = _account_id)
+    ) AS EndOfDay
+    ON CONFLICT DO NOTHING;
+
+    GET DIAGNOSTICS _accrued = ROW_COUNT;
+END 
+$$;
+
+-- Pays the interest accrued over every month up to `_through` that has not been paid yet, from the
+-- account whose external_ref is `_account_ref`. Payments are rounded down to the decimals of their
+-- asset, and leave out closed accounts.
+CALL DropProcedures('PayInterest');
+
+CREATE PROCEDURE PayInterest(
+    IN _through DATE,
+    IN _account_ref TEXT,
+    INOUT _paid INT)
+
+LANGUAGE plpgsql 
+AS $$ 
+DECLARE
+    _interest_account_id BIGINT;
+    _due RECORD;
+    _amount NUMERIC;
+    _journal_number BIGINT;
+    _tx_number BIGINT;
+BEGIN 
+    -- Concurrent runs pay one after the other, so that the later one finds the months paid
+    PERFORM pg_advisory_xact_lock(hashtext('PayInterest'));
+
+    _paid := 0;
+    FOR _due IN
+        SELECT
+            InterestAccrual.account_id,
+            date_trunc('month', InterestAccrual.day)::DATE AS period,
+            InterestAccrual.asset,
+            SUM(InterestAccrual.amount) AS accrued,
+            Asset.decimals
+        FROM InterestAccrual
+        JOIN Account ON Account.id = InterestAccrual.account_id
+        JOIN Asset ON Asset.symbol = InterestAccrual.asset
+        WHERE Account.status <> 'closed'
+        -- only months that are over
+        AND InterestAccrual.day < date_trunc('month', _through + 1)::DATE
+        AND NOT EXISTS (
+            SELECT 1 FROM InterestPayment
+            WHERE InterestPayment.account_id = InterestAccrual.account_id
+            AND InterestPayment.period = date_trunc('month', InterestAccrual.day)::DATE
+            AND InterestPayment.asset = InterestAccrual.asset
+        )
+        GROUP BY 1, 2, 3, 5
+        ORDER BY 2, 1, 3
+    LOOP
+        _amount := trunc(_due.accrued, _due.decimals);
+        _tx_number := NULL;
+
+        IF _amount > 0 THEN
+            IF _interest_account_id IS NULL THEN
+                SELECT id INTO _interest_account_id FROM Account WHERE external_ref = _account_ref;
+                IF NOT FOUND THEN
+                    RAISE EXCEPTION 'Interest account % does not exist', _account_ref
+                    USING ERRCODE = 'IN001';
+                END IF;
+            END IF;
+
+            -- Lock both accounts in ascending id order, as `CheckTransfer` does
+            PERFORM id FROM Account
+            WHERE id IN (_interest_account_id, _due.account_id)
+            ORDER BY id asc
+            FOR UPDATE;
+
+            INSERT INTO Journal(kind) VALUES ('interest') RETURNING number INTO _journal_number;
+            CALL PostEntry(_journal_number, _interest_account_id, NULL, _due.asset, -_amount);
+            CALL PostEntry(_journal_number, _due.account_id, NULL, _due.asset, _amount);
+
+            INSERT INTO Transaction(
+                from_id, to_id, asset, amount, from_balance_after, to_balance_after,
+                journal_number, kind
+            )
+            VALUES
+            (
+                _interest_account_id,
+                _due.account_id,
+                _due.asset,
+                _amount,
+                (SELECT balance FROM Holding
+                 WHERE account_id = _interest_account_id AND asset = _due.asset),
+                (SELECT balance FROM Holding
+                 WHERE account_id = _due.account_id AND asset = _due.asset),
+                _journal_number,
+                'interest'
+            )
+            RETURNING number INTO _tx_number;
+        END IF;
+
+        INSERT INTO InterestPayment(account_id, period, asset, accrued, amount, transaction_number)
+        VALUES (_due.account_id, _due.period, _due.asset, _due.accrued, _amount, _tx_number);
+        _paid := _paid + 1;
+    END LOOP;
+END 
+$$;
+
+-- API keys are stored as the SHA-256 hash of the key, along with its first characters to tell them apart
+CREATE TABLE IF NOT EXISTS ApiKey(
+    number BIGSERIAL,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMPTZ,
+    -- only admin keys reach the admin endpoints and register assets
+    admin BOOLEAN NOT NULL DEFAULT false,
+
+    PRIMARY KEY (number),
+    CHECK (length(name) BETWEEN 1 AND 64)
+);
+
+-- every API key picks its idempotency keys apart from the others
+CREATE TABLE IF NOT EXISTS IdempotencyKey(
+    api_key BIGINT NOT NULL REFERENCES ApiKey(number),
+    key TEXT,
+    request_hash TEXT NOT NULL,
+    response TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (api_key, key)
+);
+
 -------- Indexes ------------------
 
 CREATE INDEX IF NOT EXISTS \"id_index\" ON Account (\"id\");
+CREATE INDEX IF NOT EXISTS \"balance_index\" ON Holding (\"asset\", \"balance\", \"account_id\");
+CREATE INDEX IF NOT EXISTS \"from_id_index\" ON Transaction (\"from_id\", \"number\");
+CREATE INDEX IF NOT EXISTS \"to_id_index\" ON Transaction (\"to_id\", \"number\");
+CREATE INDEX IF NOT EXISTS \"reverses_index\" ON Transaction (\"reverses\");
+CREATE INDEX IF NOT EXISTS \"leg_to_id_index\" ON TransactionLeg (\"to_id\", \"transaction_number\");
+CREATE INDEX IF NOT EXISTS \"posting_account_index\" ON Posting (\"account_id\", \"asset\");
+CREATE INDEX IF NOT EXISTS \"posting_journal_index\" ON Posting (\"journal_number\");
+CREATE INDEX IF NOT EXISTS \"status_change_account_index\" ON AccountStatusChange (\"account_id\", \"number\");
+CREATE INDEX IF NOT EXISTS \"pending_hold_index\" ON Hold (\"expires_at\") WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS \"due_schedule_index\" ON Schedule (\"next_run_at\") WHERE status = 'active';
+CREATE INDEX IF NOT EXISTS \"schedule_from_id_index\" ON Schedule (\"from_id\", \"number\");
+CREATE INDEX IF NOT EXISTS \"schedule_tx_index\" ON Transaction (\"schedule_number\", \"number\");
+CREATE INDEX IF NOT EXISTS \"interest_account_index\" ON Account (\"interest_product\") WHERE interest_product IS NOT NULL;
 ";
 
 pub const DROP_ALL_TABLES: &str = "
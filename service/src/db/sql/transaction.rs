@@ -2,14 +2,121 @@
 
 pub const SELECT_LATEST_TX: &str = "
 SELECT * FROM Transaction
+WHERE ($1::BIGINT IS NULL OR from_id = $1)
+AND ($2::BIGINT IS NULL OR to_id = $2 OR EXISTS (
+    SELECT 1 FROM TransactionLeg
+    WHERE TransactionLeg.transaction_number = Transaction.number AND TransactionLeg.to_id = $2
+))
+AND ($3::NUMERIC IS NULL OR amount >= $3)
+AND ($4::NUMERIC IS NULL OR amount <= $4)
+AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+AND ($8::BIGINT IS NULL OR schedule_number = $8)
 ORDER by number desc
-LIMIT $1;
+LIMIT $7;
+";
+
+pub const SELECT_TX_BY_ACCOUNT: &str = "
+SELECT * FROM Transaction
+WHERE (from_id = $1 OR to_id = $1 OR number IN (
+    SELECT transaction_number FROM TransactionLeg WHERE TransactionLeg.to_id = $1
+))
+AND number < COALESCE($2, 9223372036854775807)
+ORDER by number desc
+LIMIT $3;
+";
+
+pub const SELECT_TX_BY_NUMBER: &str = "
+SELECT * FROM Transaction
+WHERE number = $1;
 ";
 
 pub const CREATE_NEW_TX: &str = "
-BEGIN;
-CALL UpdateUser($1, $3, 0);
-CALL UpdateUser($2, $3, 1);
-CALL InsertTx($1, $2, $3);
-COMMIT;
+CALL TransferFunds(
+    $1,  -- from_id
+    $2,  -- to_id
+    $3,  -- asset
+    $4,  -- amount
+    $5,  -- default min_balance
+    $6,  -- default overdraft_limit
+    NULL, -- transaction reversed, none for transfers
+    $7,  -- fee paid by the sender
+    $8,  -- external_ref of the account credited with the fee
+    NULL -- number of the created transaction (out)
+);
 ";
+
+// Returns one row either way, leaving `tier` or `decimals` NULL if the account or asset does not exist.
+pub const SELECT_FEE_BASIS: &str = "
+SELECT
+    (SELECT tier FROM Account WHERE id = $1) AS tier,
+    (SELECT decimals FROM Asset WHERE symbol = $2) AS decimals;
+";
+
+pub const SELECT_LEGS_BY_TX: &str = "
+SELECT * FROM TransactionLeg
+WHERE transaction_number = ANY($1)
+ORDER BY transaction_number, position;
+";
+
+pub const CREATE_NEW_SPLIT: &str = "
+CALL SplitFunds(
+    $1,  -- from_id
+    $2,  -- asset
+    $3,  -- amount
+    $4,  -- recipients
+    $5,  -- amount each recipient receives
+    $6,  -- default min_balance
+    $7,  -- default overdraft_limit
+    $8,  -- fee paid by the sender
+    $9,  -- external_ref of the account credited with the fee
+    NULL -- number of the created transaction (out)
+);
+";
+
+pub const LOCK_ACCOUNTS: &str = "
+SELECT id FROM Account
+WHERE id = ANY($1)
+ORDER BY id asc
+FOR UPDATE;
+";
+
+pub const CREATE_REVERSAL: &str = "
+CALL ReverseTransfer(
+    $1,  -- number of the reversed transaction
+    $2,  -- amount, the remainder if NULL
+    $3,  -- default min_balance
+    $4,  -- default overdraft_limit
+    NULL -- number of the created reversal (out)
+);
+";
+
+/// SQLSTATE raised by `TransferFunds` when the sender does not exist.
+pub const SENDER_DOES_NOT_EXIST_STATE: &str = "TF001";
+
+/// SQLSTATE raised by `TransferFunds` when the recipient does not exist.
+pub const RECIPIENT_DOES_NOT_EXIST_STATE: &str = "TF002";
+
+/// SQLSTATE raised by `TransferFunds` when the sender cannot afford the transfer.
+pub const NOT_ENOUGH_BALANCE_STATE: &str = "TF003";
+
+/// SQLSTATE raised by `TransferFunds` when the fee account is not set up.
+pub const FEE_ACCOUNT_DOES_NOT_EXIST_STATE: &str = "FE001";
+
+/// SQLSTATE raised by `TransferFunds` when the sender does not hold the asset being transferred.
+pub const ASSET_MISMATCH_STATE: &str = "TF004";
+
+/// SQLSTATE raised by `CheckAccountActive` when funds would leave or reach a frozen account.
+pub const ACCOUNT_FROZEN_STATE: &str = "TF005";
+
+/// SQLSTATE raised by `CheckAccountActive` when funds would leave or reach a closed account.
+pub const ACCOUNT_CLOSED_STATE: &str = "TF006";
+
+/// SQLSTATE raised by `ReverseTransfer` when the transaction to reverse does not exist.
+pub const TRANSACTION_DOES_NOT_EXIST_STATE: &str = "RV001";
+
+/// SQLSTATE raised by `ReverseTransfer` when the transaction to reverse is a reversal itself.
+pub const NOT_REVERSIBLE_STATE: &str = "RV002";
+
+/// SQLSTATE raised by `ReverseTransfer` when more than what remains of a transaction would be reversed.
+pub const REVERSAL_EXCEEDS_REMAINING_STATE: &str = "RV003";
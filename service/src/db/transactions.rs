@@ -1,106 +1,881 @@
 //! Methods processing HTTP requests related to querying transactions.
 
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde_derive::{Deserialize, Serialize};
+use tokio_postgres::{GenericClient, Row, Transaction as DbTransaction};
 
-use crate::db::{bigint_to_u64, sql, u64_to_bigint, Database};
-use crate::error_codes::Error as ServiceAPIError;
+use crate::db::{
+    bigint_to_id, check_amount, check_transfer, cursor_id, decode_cursor, default_asset,
+    encode_cursor, error_detail, id_to_bigint, sql, transfer_error, validation_result, Amount,
+    Database, IdempotencyHeader, IdempotencyKey, Page,
+};
+use crate::error_codes::{Error as ServiceAPIError, FieldError};
 
 impl Database {
-    pub async fn get_tx(&self, limit: usize) -> Result<Vec<Transaction>, ServiceAPIError> {
-        let client = self
-            .pool
-            .get()
+    pub async fn get_tx(
+        &self,
+        filter: &TransactionFilter,
+        limit: usize,
+    ) -> Result<Vec<TransactionRecord>, ServiceAPIError> {
+        filter.validate()?;
+
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let tx_query_result = client
+            .query(
+                sql::SELECT_LATEST_TX,
+                &[
+                    &filter.from_id.map(id_to_bigint).transpose()?,
+                    &filter.to_id.map(id_to_bigint).transpose()?,
+                    &filter.min_amount,
+                    &filter.max_amount,
+                    &filter.created_after,
+                    &filter.created_before,
+                    &(limit as i64),
+                    &filter.schedule_id.map(id_to_bigint).transpose()?,
+                ],
+            )
             .await
-            .map_err(|_| ServiceAPIError::ResourceBusy)?;
+            .map_err(|e| ServiceAPIError::database("querying transactions", e))?;
+
+        let mut records = tx_query_result
+            .iter()
+            .map(TransactionRecord::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+        load_legs(&*client, &mut records).await?;
+
+        Ok(records)
+    }
+
+    pub async fn get_tx_by_id(&self, id: u64) -> Result<TransactionRecord, ServiceAPIError> {
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
 
         let tx_query_result = client
-            .query(sql::SELECT_LATEST_TX, &[&(limit as i64)])
+            .query_opt(sql::SELECT_TX_BY_NUMBER, &[&id_to_bigint(id)?])
             .await
-            .map_err(|_| ServiceAPIError::DatabaseQueryError)?;
+            .map_err(|e| ServiceAPIError::database("querying transaction", e))?
+            .ok_or(ServiceAPIError::TransactionDoesNotExist)?;
+
+        let mut records = vec![TransactionRecord::from_row(&tx_query_result)?];
+        load_legs(&*client, &mut records).await?;
+
+        Ok(records.remove(0))
+    }
+
+    /// Returns transactions sent or received by `account_id`, newest first, starting
+    /// after the cursor `after` if given.
+    pub async fn get_account_history(
+        &self,
+        account_id: u64,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Page<HistoryEntry>, ServiceAPIError> {
+        let after = after
+            .map(decode_cursor::<1>)
+            .transpose()?
+            .map(|[number]| cursor_id(number))
+            .transpose()?;
+        let account = id_to_bigint(account_id)?;
 
-        if tx_query_result.is_empty() {
-            return Err(ServiceAPIError::DatabaseQueryError);
+        let client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+
+        let account_query_result = client
+            .query(sql::SELECT_ACCOUNT_INFO_BY_ID, &[&account])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying account", e))?;
+        if account_query_result.is_empty() {
+            return Err(ServiceAPIError::SenderDoesNotExist);
         }
 
-        let mut txs = Vec::with_capacity(tx_query_result.len());
-        for row in tx_query_result {
-            txs.push(Transaction {
-                from_id: bigint_to_u64(row.get::<_, i64>("from_id")),
-                to_id: bigint_to_u64(row.get::<_, i64>("to_id")),
-                amount: bigint_to_u64(row.get::<_, i64>("amount")),
-            })
+        // one extra row is fetched to find out whether there is a next page
+        let tx_query_result = client
+            .query(
+                sql::SELECT_TX_BY_ACCOUNT,
+                &[&account, &after, &(limit as i64 + 1)],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying account history", e))?;
+
+        let mut records = tx_query_result
+            .iter()
+            .map(TransactionRecord::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+        load_legs(&*client, &mut records).await?;
+
+        let mut items = records
+            .into_iter()
+            .map(|tx| HistoryEntry::new(account_id, tx))
+            .collect::<Vec<_>>();
+
+        let next_cursor = if items.len() > limit {
+            items.truncate(limit);
+            items
+                .last()
+                .map(|entry| encode_cursor(&[Decimal::from(entry.id)]))
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
+    }
+
+    pub async fn post_tx(
+        &self,
+        tx: Transaction,
+        idempotency_key: Option<IdempotencyHeader>,
+    ) -> Result<TransactionRecord, ServiceAPIError> {
+        tx.validate()?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning transfer", e))?;
+
+        // a retried request returns the response of the original one instead of transferring again
+        let idempotency_key = idempotency_key
+            .map(|key| IdempotencyKey::new(key, POST_TX_ROUTE, &tx))
+            .transpose()?;
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.replay_response(&db_transaction, key).await? {
+                return Ok(response);
+            }
+        }
+
+        let response = self.transfer(&db_transaction, &tx).await?;
+
+        if let Some(key) = &idempotency_key {
+            self.record_response(&db_transaction, key, &response)
+                .await?;
         }
 
-        Ok(txs)
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing transfer", e))?;
+
+        Ok(response)
     }
 
-    pub async fn post_tx(&self, tx: Transaction) -> Result<String, ServiceAPIError> {
-        let client = self
-            .pool
-            .get()
+    /// Submits every transaction of `batch` in one DB transaction, in order, so that later
+    /// transactions see the balances left by earlier ones. Either all of them go through or none
+    /// does, in which case the error names the index of the transaction that failed.
+    pub async fn post_tx_batch(
+        &self,
+        batch: TransactionBatch,
+        idempotency_key: Option<IdempotencyHeader>,
+    ) -> Result<TransactionBatchRecord, ServiceAPIError> {
+        batch.validate(self.max_batch_size)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
             .await
-            .map_err(|_| ServiceAPIError::ResourceBusy)?;
+            .map_err(|e| ServiceAPIError::database("beginning batch transfer", e))?;
 
-        // check existence and balance of 1st user
-        let from_balance = self
-            .check_existence_of_user(tx.from_id)
+        let idempotency_key = idempotency_key
+            .map(|key| IdempotencyKey::new(key, POST_TX_BATCH_ROUTE, &batch))
+            .transpose()?;
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.replay_response(&db_transaction, key).await? {
+                return Ok(response);
+            }
+        }
+
+        // Locking every account of the batch up front, in ascending id order, keeps the batch from
+        // deadlocking with concurrent transfers locking them one pair at a time.
+        let mut ids = batch
+            .transactions
+            .iter()
+            .flat_map(Transaction::parties)
+            .map(id_to_bigint)
+            .collect::<Result<Vec<_>, _>>()?;
+        ids.sort_unstable();
+        ids.dedup();
+        db_transaction
+            .execute(sql::LOCK_ACCOUNTS, &[&ids])
             .await
-            .map_err(|_| ServiceAPIError::SenderDoesNotExist)?;
+            .map_err(|e| ServiceAPIError::database("locking batch accounts", e))?;
 
-        // we do not allow the balance to fall down below a certain threshold
-        if from_balance < tx.amount + THRESHOLD_BALANCE {
-            return Err(ServiceAPIError::NotEnoughBalance);
+        let mut transactions = Vec::with_capacity(batch.transactions.len());
+        for (index, tx) in batch.transactions.iter().enumerate() {
+            let record = self
+                .transfer(&db_transaction, tx)
+                .await
+                .map_err(|e| ServiceAPIError::batch_entry(index, e))?;
+            transactions.push(record);
         }
+        let response = TransactionBatchRecord { transactions };
 
-        // check existence and balance of 2nd user
-        if self.check_existence_of_user(tx.to_id).await.is_err() {
-            return Err(ServiceAPIError::RecipientDoesNotExist);
+        if let Some(key) = &idempotency_key {
+            self.record_response(&db_transaction, key, &response)
+                .await?;
         }
 
-        let combined_sql = sql::CREATE_NEW_TX
-            .replace("$1", &u64_to_bigint(tx.from_id).to_string())
-            .replace("$2", &u64_to_bigint(tx.to_id).to_string())
-            .replace("$3", &u64_to_bigint(tx.amount).to_string());
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing batch transfer", e))?;
+
+        Ok(response)
+    }
+
+    /// Refunds part or all of the transaction `id` to its sender, and returns the reversal.
+    pub async fn reverse_tx(
+        &self,
+        id: u64,
+        reversal: Reversal,
+        idempotency_key: Option<IdempotencyHeader>,
+    ) -> Result<TransactionRecord, ServiceAPIError> {
+        reversal.validate()?;
+        let number = id_to_bigint(id)?;
+
+        let mut client = self.pool.get().await.map_err(ServiceAPIError::pool)?;
+        let db_transaction = client
+            .transaction()
+            .await
+            .map_err(|e| ServiceAPIError::database("beginning reversal", e))?;
 
-        if client.batch_execute(&combined_sql).await.is_err() {
-            return Err(ServiceAPIError::DatabaseQueryError);
+        let idempotency_key = idempotency_key
+            .map(|key| IdempotencyKey::new(key, REVERSE_TX_ROUTE, &(id, &reversal)))
+            .transpose()?;
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.replay_response(&db_transaction, key).await? {
+                return Ok(response);
+            }
         }
 
-        Ok(String::from("Balance transfer completed."))
-    }
+        let original = db_transaction
+            .query_opt(sql::SELECT_TX_BY_NUMBER, &[&number])
+            .await
+            .map_err(|e| ServiceAPIError::database("querying reversed transaction", e))?
+            .ok_or(ServiceAPIError::TransactionDoesNotExist)?;
+        let asset: String = original.get("asset");
 
-    pub async fn check_existence_of_user(&self, user_id: u64) -> Result<u64, ServiceAPIError> {
-        let client = self
-            .pool
-            .get()
+        // `ReverseTransfer` moves the funds back with `TransferFunds`, under the recipient's policy
+        let reversal_result = db_transaction
+            .query_one(
+                sql::CREATE_REVERSAL,
+                &[
+                    &number,
+                    &reversal.amount,
+                    &self.balance_policy.min_balance,
+                    &self.balance_policy.overdraft_limit,
+                ],
+            )
             .await
-            .map_err(|_| ServiceAPIError::ResourceBusy)?;
+            .map_err(|e| match e.code().map(|state| state.code()) {
+                Some(sql::TRANSACTION_DOES_NOT_EXIST_STATE) => {
+                    ServiceAPIError::TransactionDoesNotExist
+                }
+                Some(sql::NOT_REVERSIBLE_STATE) => ServiceAPIError::TransactionNotReversible,
+                Some(sql::REVERSAL_EXCEEDS_REMAINING_STATE) => {
+                    ServiceAPIError::ReversalExceedsRemaining(
+                        error_detail::<Decimal>(&e).map(|remaining| remaining.normalize()),
+                    )
+                }
+                _ => transfer_error(e, &asset),
+            })?;
 
-        // check existence of user and their balance
-        let tx_query_result = client
-            .query(sql::SELECT_ACCOUNT_INFO_BY_ID, &[&u64_to_bigint(user_id)])
+        let tx_query_result = db_transaction
+            .query_one(
+                sql::SELECT_TX_BY_NUMBER,
+                &[&reversal_result.get::<_, i64>("_reversal_number")],
+            )
             .await
-            .map_err(|_| ServiceAPIError::DatabaseQueryError)?;
+            .map_err(|e| ServiceAPIError::database("querying created reversal", e))?;
+        let response = TransactionRecord::from_row(&tx_query_result)?;
 
-        if tx_query_result.is_empty() {
-            return Err(ServiceAPIError::DatabaseQueryError);
+        if let Some(key) = &idempotency_key {
+            self.record_response(&db_transaction, key, &response)
+                .await?;
         }
 
-        Ok(bigint_to_u64(
-            tx_query_result[0]
-                .get::<_, i64>("balance")
-                .try_into()
-                .unwrap(),
-        ))
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| ServiceAPIError::database("committing reversal", e))?;
+
+        Ok(response)
+    }
+
+    /// Moves the funds of `tx` within `db_transaction`, and returns the created transaction.
+    pub(crate) async fn transfer(
+        &self,
+        db_transaction: &DbTransaction<'_>,
+        tx: &Transaction,
+    ) -> Result<TransactionRecord, ServiceAPIError> {
+        // `TransferFunds` and `SplitFunds` lock every party, validate them against the sender's
+        // policy and move the funds atomically. The default policy applies unless the sender
+        // overrides it. The fee is charged on top of the amount and credited to the fee account.
+        let transfer_result = match tx.to_id {
+            Some(to_id) => db_transaction
+                .query_one(
+                    sql::CREATE_NEW_TX,
+                    &[
+                        &id_to_bigint(tx.from_id)?,
+                        &id_to_bigint(to_id)?,
+                        &tx.asset,
+                        &tx.amount,
+                        &self.balance_policy.min_balance,
+                        &self.balance_policy.overdraft_limit,
+                        &self.fee_for(db_transaction, tx).await?,
+                        &self.fee_policy.account_ref,
+                    ],
+                )
+                .await
+                .map_err(|e| match e.code().map(|state| state.code()) {
+                    Some(sql::FEE_ACCOUNT_DOES_NOT_EXIST_STATE) => {
+                        ServiceAPIError::FeeAccountMissing
+                    }
+                    _ => transfer_error(e, &tx.asset),
+                })?,
+            None => {
+                let decimals = db_transaction
+                    .query_opt(sql::SELECT_ASSET_DECIMALS, &[&tx.asset])
+                    .await
+                    .map_err(|e| ServiceAPIError::database("querying asset decimals", e))?
+                    .ok_or(ServiceAPIError::AssetDoesNotExist)?
+                    .get::<_, i16>("decimals");
+                let (to_ids, amounts): (Vec<_>, Vec<_>) =
+                    tx.split(decimals as u32)?.into_iter().unzip();
+
+                db_transaction
+                    .query_one(
+                        sql::CREATE_NEW_SPLIT,
+                        &[
+                            &id_to_bigint(tx.from_id)?,
+                            &tx.asset,
+                            &tx.amount,
+                            &to_ids
+                                .into_iter()
+                                .map(id_to_bigint)
+                                .collect::<Result<Vec<_>, _>>()?,
+                            &amounts,
+                            &self.balance_policy.min_balance,
+                            &self.balance_policy.overdraft_limit,
+                            &self.fee_for(db_transaction, tx).await?,
+                            &self.fee_policy.account_ref,
+                        ],
+                    )
+                    .await
+                    .map_err(|e| match e.code().map(|state| state.code()) {
+                        Some(sql::FEE_ACCOUNT_DOES_NOT_EXIST_STATE) => {
+                            ServiceAPIError::FeeAccountMissing
+                        }
+                        _ => transfer_error(e, &tx.asset),
+                    })?
+            }
+        };
+
+        let tx_query_result = db_transaction
+            .query_one(
+                sql::SELECT_TX_BY_NUMBER,
+                &[&transfer_result.get::<_, i64>("_number")],
+            )
+            .await
+            .map_err(|e| ServiceAPIError::database("querying created transaction", e))?;
+
+        let mut records = vec![TransactionRecord::from_row(&tx_query_result)?];
+        load_legs(db_transaction, &mut records).await?;
+
+        Ok(records.remove(0))
     }
 }
 
+/// Fills in the legs of the splits among `records`, which are read from DB without them.
+async fn load_legs<C: GenericClient>(
+    client: &C,
+    records: &mut [TransactionRecord],
+) -> Result<(), ServiceAPIError> {
+    let numbers = records
+        .iter()
+        .filter(|record| record.kind == TransactionKind::Split)
+        .map(|record| id_to_bigint(record.id))
+        .collect::<Result<Vec<_>, _>>()?;
+    if numbers.is_empty() {
+        return Ok(());
+    }
+
+    let legs_query_result = client
+        .query(sql::SELECT_LEGS_BY_TX, &[&numbers])
+        .await
+        .map_err(|e| ServiceAPIError::database("querying transaction legs", e))?;
+
+    for row in legs_query_result {
+        let number = bigint_to_id(row.get::<_, i64>("transaction_number"))?;
+        if let Some(record) = records.iter_mut().find(|record| record.id == number) {
+            record.legs.push(LegRecord {
+                to_id: bigint_to_id(row.get::<_, i64>("to_id"))?,
+                amount: row.get::<_, Amount>("amount"),
+                to_balance_after: row.get::<_, Amount>("to_balance_after"),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// [Transaction] moves `amount` of `asset` from one account either to `to_id`, or split across
+/// the recipients of `legs`. Requests that leave out `asset` refer to [crate::db::DEFAULT_ASSET].
 #[derive(Deserialize, Serialize)]
 pub struct Transaction {
     pub from_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_id: Option<u64>,
+    #[serde(default = "default_asset")]
+    pub asset: String,
+    pub amount: Amount,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legs: Vec<Leg>,
+}
+
+impl Transaction {
+    pub(crate) fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        match (self.to_id, self.legs.is_empty()) {
+            (Some(to_id), true) => check_transfer(&mut errors, self.from_id, to_id, self.amount),
+            (None, false) => self.check_legs(&mut errors),
+            (Some(_), false) => errors.push(FieldError::new(
+                "to_id",
+                "must be left out when splitting across legs",
+            )),
+            (None, true) => errors.push(FieldError::new("to_id", "is required")),
+        }
+
+        validation_result(errors)
+    }
+
+    fn check_legs(&self, errors: &mut Vec<FieldError>) {
+        // every leg has to be a valid transfer on its own
+        check_amount(errors, "amount", self.amount);
+        for leg in &self.legs {
+            let amount = leg.amount.unwrap_or(self.amount);
+            check_transfer(errors, self.from_id, leg.to_id, amount);
+        }
+        if !errors.is_empty() {
+            return;
+        }
+
+        if self.legs.len() < MIN_LEGS {
+            errors.push(FieldError::new(
+                "legs",
+                format!("must hold at least {} legs", MIN_LEGS),
+            ));
+        }
+        if self.legs.len() > MAX_LEGS {
+            errors.push(FieldError::new(
+                "legs",
+                format!("must hold at most {} legs", MAX_LEGS),
+            ));
+        }
+
+        let mut to_ids = self.legs.iter().map(|leg| leg.to_id).collect::<Vec<_>>();
+        to_ids.sort_unstable();
+        to_ids.dedup();
+        if to_ids.len() != self.legs.len() {
+            errors.push(FieldError::new("legs", "must credit every recipient once"));
+        }
+
+        if self
+            .legs
+            .iter()
+            .any(|leg| leg.amount.is_some() == leg.weight.is_some())
+        {
+            errors.push(FieldError::new(
+                "legs",
+                "must each give either an amount or a weight",
+            ));
+        }
+        if self.legs.iter().any(|leg| leg.weight == Some(0)) {
+            errors.push(FieldError::new("legs", "weights must be greater than zero"));
+        }
+
+        let fixed = self
+            .legs
+            .iter()
+            .filter_map(|leg| leg.amount)
+            .map(Amount::value)
+            .sum::<Decimal>();
+        let weighted = self.legs.iter().any(|leg| leg.weight.is_some());
+        if weighted && fixed >= self.amount.value() {
+            errors.push(FieldError::new(
+                "legs",
+                "fixed amounts must leave part of amount to weighted legs",
+            ));
+        } else if !weighted && fixed != self.amount.value() {
+            errors.push(FieldError::new(
+                "legs",
+                "fixed amounts must sum up to amount",
+            ));
+        }
+    }
+
+    /// Returns what each recipient of a split receives. Fixed legs receive their amount, and the
+    /// rest is shared among weighted legs in proportion to their weight, rounded down to
+    /// `decimals` places. The first weighted leg receives what rounding leaves over.
+    fn split(&self, decimals: u32) -> Result<Vec<(u64, Decimal)>, ServiceAPIError> {
+        let fixed = self
+            .legs
+            .iter()
+            .filter_map(|leg| leg.amount)
+            .map(Amount::value)
+            .sum::<Decimal>();
+        let remainder = self.amount.value() - fixed;
+        let total_weight = self
+            .legs
+            .iter()
+            .filter_map(|leg| leg.weight)
+            .map(Decimal::from)
+            .sum::<Decimal>();
+
+        let mut shares = self
+            .legs
+            .iter()
+            .map(|leg| {
+                let share = match (leg.amount, leg.weight) {
+                    (Some(amount), _) => amount.value(),
+                    (None, weight) => (remainder * Decimal::from(weight.unwrap_or_default())
+                        / total_weight)
+                        .round_dp_with_strategy(decimals, RoundingStrategy::ToZero),
+                };
+                (leg.to_id, share)
+            })
+            .collect::<Vec<_>>();
+
+        let residue = self.amount.value() - shares.iter().map(|(_, share)| share).sum::<Decimal>();
+        if let Some(position) = self.legs.iter().position(|leg| leg.weight.is_some()) {
+            shares[position].1 += residue;
+        }
+
+        if shares.iter().any(|(_, share)| share.is_zero()) {
+            return Err(ServiceAPIError::ValidationFailed(vec![FieldError::new(
+                "legs",
+                "every leg must receive a positive amount",
+            )]));
+        }
+
+        Ok(shares)
+    }
+
+    /// Returns the ids of every account `self` moves funds between.
+    fn parties(&self) -> Vec<u64> {
+        let mut parties = vec![self.from_id];
+        parties.extend(self.to_id);
+        parties.extend(self.legs.iter().map(|leg| leg.to_id));
+        parties
+    }
+}
+
+/// [Leg] credits `to_id` with a share of a split [Transaction], given either as a fixed `amount`
+/// or as a `weight` relative to the other weighted legs.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Leg {
     pub to_id: u64,
-    pub amount: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Amount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+}
+
+/// [TransactionBatch] submits `transactions` all at once, see [Database::post_tx_batch].
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionBatch {
+    pub transactions: Vec<Transaction>,
+}
+
+impl TransactionBatch {
+    fn validate(&self, max_batch_size: usize) -> Result<(), ServiceAPIError> {
+        if self.transactions.is_empty() || self.transactions.len() > max_batch_size {
+            return Err(ServiceAPIError::ValidationFailed(vec![FieldError::new(
+                "transactions",
+                format!("must hold between 1 and {} transactions", max_batch_size),
+            )]));
+        }
+
+        for (index, tx) in self.transactions.iter().enumerate() {
+            tx.validate()
+                .map_err(|e| ServiceAPIError::batch_entry(index, e))?;
+        }
+
+        Ok(())
+    }
 }
 
-/// `THRESHOLD_BALANCE` denotes minimum balance of an account
-pub const THRESHOLD_BALANCE: u64 = 5;
+/// [TransactionBatchRecord] lists the transactions created for a [TransactionBatch], in order.
+#[derive(Deserialize, Serialize)]
+pub struct TransactionBatchRecord {
+    pub transactions: Vec<TransactionRecord>,
+}
+
+/// [Reversal] refunds `amount` of a transaction to its sender. Leaving out `amount` refunds
+/// whatever has not been refunded yet.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Reversal {
+    pub amount: Option<Amount>,
+}
+
+impl Reversal {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        let mut errors = Vec::new();
+
+        match self.amount.map(Amount::check) {
+            Some(Ok(amount)) if amount.is_zero() => {
+                errors.push(FieldError::new("amount", "must be greater than zero"))
+            }
+            Some(Err(e)) => errors.push(FieldError::new("amount", e)),
+            _ => {}
+        }
+
+        validation_result(errors)
+    }
+}
+
+/// [TransactionRecord] is a transaction as stored on DB, as opposed to a submitted [Transaction].
+/// Reversals name the transaction they refund in `reverses`, whereas splits credit their `legs`
+/// instead of a single `to_id`. The sender pays `fee` on top of `amount`. Transactions created by
+/// running a schedule name it in `schedule_id`.
+#[derive(Deserialize, Serialize)]
+pub struct TransactionRecord {
+    pub id: u64,
+    pub kind: TransactionKind,
+    pub reverses: Option<u64>,
+    pub from_id: u64,
+    pub to_id: Option<u64>,
+    pub asset: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub from_balance_after: Amount,
+    pub to_balance_after: Option<Amount>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legs: Vec<LegRecord>,
+    pub status: TransactionStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_id: Option<u64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TransactionRecord {
+    fn from_row(row: &Row) -> Result<TransactionRecord, ServiceAPIError> {
+        Ok(TransactionRecord {
+            id: bigint_to_id(row.get::<_, i64>("number"))?,
+            kind: TransactionKind::from_db(row.get::<_, &str>("kind"))?,
+            reverses: row
+                .get::<_, Option<i64>>("reverses")
+                .map(bigint_to_id)
+                .transpose()?,
+            from_id: bigint_to_id(row.get::<_, i64>("from_id"))?,
+            to_id: row
+                .get::<_, Option<i64>>("to_id")
+                .map(bigint_to_id)
+                .transpose()?,
+            asset: row.get("asset"),
+            amount: row.get::<_, Amount>("amount"),
+            fee: row.get::<_, Amount>("fee"),
+            from_balance_after: row.get::<_, Amount>("from_balance_after"),
+            to_balance_after: row.get::<_, Option<Amount>>("to_balance_after"),
+            // filled in by [load_legs]
+            legs: Vec::new(),
+            status: TransactionStatus::from_db(row.get::<_, &str>("status"))?,
+            schedule_id: row
+                .get::<_, Option<i64>>("schedule_number")
+                .map(bigint_to_id)
+                .transpose()?,
+            created_at: row.get::<_, DateTime<Utc>>("created_at"),
+        })
+    }
+}
+
+/// [LegRecord] is what one recipient of a split received, along with its balance right after.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LegRecord {
+    pub to_id: u64,
+    pub amount: Amount,
+    pub to_balance_after: Amount,
+}
+
+/// [TransactionFilter] narrows down the transactions returned by [Database::get_tx].
+/// `created_after` is inclusive and `created_before` exclusive.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TransactionFilter {
+    pub from_id: Option<u64>,
+    pub to_id: Option<u64>,
+    pub min_amount: Option<Amount>,
+    pub max_amount: Option<Amount>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub schedule_id: Option<u64>,
+}
+
+impl TransactionFilter {
+    fn validate(&self) -> Result<(), ServiceAPIError> {
+        // ids beyond BIGINT cannot be on record
+        if [self.from_id, self.to_id, self.schedule_id]
+            .into_iter()
+            .flatten()
+            .any(|id| id_to_bigint(id).is_err())
+        {
+            return Err(ServiceAPIError::InvalidFilter);
+        }
+
+        if [self.min_amount, self.max_amount]
+            .into_iter()
+            .flatten()
+            .any(|amount| amount.check().is_err())
+        {
+            return Err(ServiceAPIError::InvalidFilter);
+        }
+
+        if let (Some(min_amount), Some(max_amount)) = (self.min_amount, self.max_amount) {
+            if min_amount > max_amount {
+                return Err(ServiceAPIError::InvalidFilter);
+            }
+        }
+
+        if let (Some(created_after), Some(created_before)) =
+            (self.created_after, self.created_before)
+        {
+            if created_after > created_before {
+                return Err(ServiceAPIError::InvalidFilter);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// [HistoryEntry] is a transaction as seen from one of its parties. Senders of a split have no
+/// single `counterparty` and see the whole `amount`, whereas its recipients see their own share.
+/// Either side sees every leg of it. Only the sender sees the `fee` it paid on top of `amount`.
+#[derive(Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub kind: TransactionKind,
+    pub reverses: Option<u64>,
+    pub direction: Direction,
+    pub counterparty: Option<u64>,
+    pub asset: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub balance_after: Amount,
+    pub status: TransactionStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legs: Vec<LegRecord>,
+}
+
+impl HistoryEntry {
+    fn new(account_id: u64, tx: TransactionRecord) -> HistoryEntry {
+        let leg = tx.legs.iter().find(|leg| leg.to_id == account_id);
+        let (direction, counterparty, amount, balance_after) = match leg {
+            _ if tx.from_id == account_id => {
+                (Direction::Sent, tx.to_id, tx.amount, tx.from_balance_after)
+            }
+            Some(leg) => (
+                Direction::Received,
+                Some(tx.from_id),
+                leg.amount,
+                leg.to_balance_after,
+            ),
+            None => (
+                Direction::Received,
+                Some(tx.from_id),
+                tx.amount,
+                tx.to_balance_after.unwrap_or_default(),
+            ),
+        };
+
+        let fee = match direction {
+            Direction::Sent => tx.fee,
+            Direction::Received => Amount::default(),
+        };
+
+        HistoryEntry {
+            id: tx.id,
+            kind: tx.kind,
+            reverses: tx.reverses,
+            direction,
+            counterparty,
+            asset: tx.asset,
+            amount,
+            fee,
+            balance_after,
+            status: tx.status,
+            created_at: tx.created_at,
+            legs: tx.legs,
+        }
+    }
+}
+
+/// [Direction] denotes whether funds left or entered an account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// [TransactionKind] denotes whether a transaction is a transfer, refunds one, is split across
+/// several recipients, or pays interest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Transfer,
+    Reversal,
+    Split,
+    Interest,
+}
+
+impl TransactionKind {
+    fn from_db(kind: &str) -> Result<TransactionKind, ServiceAPIError> {
+        match kind {
+            "transfer" => Ok(TransactionKind::Transfer),
+            "reversal" => Ok(TransactionKind::Reversal),
+            "split" => Ok(TransactionKind::Split),
+            "interest" => Ok(TransactionKind::Interest),
+            _ => {
+                log::error!("unknown transaction kind on record: {}", kind);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// [TransactionStatus] denotes the stage a transaction has reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// Funds have been moved from sender to recipient.
+    Completed,
+}
+
+impl TransactionStatus {
+    fn from_db(status: &str) -> Result<TransactionStatus, ServiceAPIError> {
+        match status {
+            "completed" => Ok(TransactionStatus::Completed),
+            _ => {
+                log::error!("unknown transaction status on record: {}", status);
+                Err(ServiceAPIError::SerializationFailure)
+            }
+        }
+    }
+}
+
+/// `MIN_LEGS` denotes the fewest recipients a transaction can be split across, as a single
+/// recipient is a plain transfer.
+const MIN_LEGS: usize = 2;
+
+/// `MAX_LEGS` denotes the most recipients a transaction can be split across.
+const MAX_LEGS: usize = 25;
+
+/// `POST_TX_ROUTE` scopes idempotency keys used for submitting transactions.
+const POST_TX_ROUTE: &str = "POST /transactions";
+/// `POST_TX_BATCH_ROUTE` scopes idempotency keys used for submitting batches of transactions.
+const POST_TX_BATCH_ROUTE: &str = "POST /transactions/batch";
+/// `REVERSE_TX_ROUTE` scopes idempotency keys used for reversing transactions.
+const REVERSE_TX_ROUTE: &str = "POST /transactions/{id}/reverse";
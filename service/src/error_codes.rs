@@ -1,51 +1,641 @@
 //! Methods defining error handling for the endpoints.
+//!
+//! Every error is returned to the client as a JSON body of the form
+//! `{ "code": "...", "numeric_code": N, "message": "...", "details": {...}, "request_id": "..." }`.
+//! `code` and `numeric_code` are stable and meant for programmatic handling, whereas `message`
+//! is meant for humans and may be reworded at any time.
 
+use rust_decimal::Decimal;
+use serde_derive::Serialize;
 use std::convert::Infallible;
 use warp::{self, http, hyper::StatusCode};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     ResourceBusy,
     WindowLimitExceeded,
-    DatabaseQueryError,
+    DatabaseQueryError(DatabaseError),
+    DatabaseUnavailable(DatabaseError),
+    SerializationConflict(DatabaseError),
+    UniqueViolation(DatabaseError),
+    ConstraintViolation(DatabaseError),
     SerializationFailure,
-    NotEnoughBalance,
+    // `required` is the balance the sender would have needed under its policy, if DB reported it.
+    NotEnoughBalance {
+        asset: String,
+        required: Option<Decimal>,
+    },
     SenderDoesNotExist,
     RecipientDoesNotExist,
     AccountExists,
+    IdempotencyKeyConflict,
+    TransactionDoesNotExist,
+    InvalidCursor,
+    InvalidFilter,
+    AssetDoesNotExist,
+    AssetMismatch,
+    AssetExists,
+    InvalidAmount,
+    AmountOutOfRange,
+    IdOutOfRange,
+    ValidationFailed(Vec<FieldError>),
+    // the id of the account that is not active, if DB reported it.
+    AccountFrozen(Option<u64>),
+    AccountClosed(Option<u64>),
+    InvalidStatusTransition {
+        from: Option<String>,
+        to: String,
+    },
+    AccountNotEmpty,
+    TransactionNotReversible,
+    // the amount of the transaction that has not been reversed yet, if DB reported it.
+    ReversalExceedsRemaining(Option<Decimal>),
+    HoldDoesNotExist,
+    // the status the hold has been settled with already, if DB reported it.
+    HoldNotPending(Option<String>),
+    // the amount on hold, if DB reported it.
+    CaptureExceedsHold(Option<Decimal>),
+    FeeAccountMissing,
+    ScheduleDoesNotExist,
+    InterestProductExists,
+    InterestProductDoesNotExist,
+    InterestAccountMissing,
+    Unauthorized,
+    Forbidden,
+    // a transaction of a batch failed with `source`, failing the whole batch.
+    BatchEntryFailed {
+        index: usize,
+        source: Box<Error>,
+    },
 }
 
 impl warp::reject::Reject for Error {}
 
-// handle_rejection receives a `Rejection` and returns a custom error code to the client.
+/// [FieldError] names a field of a request payload and why its value was rejected.
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, reason: impl ToString) -> FieldError {
+        FieldError {
+            field,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// [DatabaseError] is a failed DB call, along with what the service was doing when it failed.
+#[derive(Debug)]
+pub struct DatabaseError {
+    pub context: &'static str,
+    pub source: tokio_postgres::Error,
+}
+
+impl Error {
+    /// Classifies a failed DB call by its SQLSTATE and logs its cause along with `context`.
+    pub fn database(context: &'static str, source: tokio_postgres::Error) -> Error {
+        let sqlstate = source.code().map(|state| state.code().to_string());
+        let error = DatabaseError { context, source };
+
+        let error = match sqlstate.as_deref() {
+            // a result, such as a balance, overflowed `NUMERIC(28, 8)`
+            Some("22003") => {
+                log::info!("{} failed: {}", error.context, error.source);
+                return Error::AmountOutOfRange;
+            }
+            Some("23505") => Error::UniqueViolation(error),
+            Some("23514") => Error::ConstraintViolation(error),
+            Some("40001") | Some("40P01") => Error::SerializationConflict(error),
+            // connection exceptions, and the server shutting down or starting up
+            Some(state) if state.starts_with("08") || state.starts_with("57P") => {
+                Error::DatabaseUnavailable(error)
+            }
+            None if error.source.is_closed() => Error::DatabaseUnavailable(error),
+            _ => Error::DatabaseQueryError(error),
+        };
+
+        match &error {
+            Error::UniqueViolation(e) | Error::ConstraintViolation(e) => {
+                log::info!("{} failed: {}", e.context, e.source)
+            }
+            Error::SerializationConflict(e) => log::warn!("{} failed: {}", e.context, e.source),
+            Error::DatabaseUnavailable(e) | Error::DatabaseQueryError(e) => {
+                log::error!("{} failed: {}", e.context, e.source)
+            }
+            _ => {}
+        }
+
+        error
+    }
+
+    /// Classifies a failure to check a connection out of the pool and logs its cause.
+    pub fn pool(source: mobc::Error<tokio_postgres::Error>) -> Error {
+        match source {
+            // whatever the server said, a connection that cannot be opened means DB is unavailable
+            mobc::Error::Inner(source) => {
+                log::error!("connecting to database failed: {}", source);
+                Error::DatabaseUnavailable(DatabaseError {
+                    context: "connecting to database",
+                    source,
+                })
+            }
+            e => {
+                log::error!("checking out a database connection failed: {:?}", e);
+                Error::ResourceBusy
+            }
+        }
+    }
+
+    /// Attributes `source` to the transaction at `index` of a batch.
+    pub fn batch_entry(index: usize, source: Error) -> Error {
+        Error::BatchEntryFailed {
+            index,
+            source: Box::new(source),
+        }
+    }
+
+    fn description(&self) -> ErrorDescription {
+        match self {
+            Error::ResourceBusy => RESOURCE_BUSY,
+            Error::WindowLimitExceeded => WINDOW_LIMIT_EXCEEDED,
+            Error::DatabaseQueryError(_) => DB_QUERY_ERROR,
+            Error::DatabaseUnavailable(_) => DATABASE_UNAVAILABLE,
+            Error::SerializationConflict(_) => SERIALIZATION_CONFLICT,
+            Error::UniqueViolation(_) => UNIQUE_VIOLATION,
+            Error::ConstraintViolation(_) => CONSTRAINT_VIOLATION,
+            Error::SerializationFailure => SERIALIZATION_FAILURE,
+            Error::NotEnoughBalance { .. } => NOT_ENOUGH_BALANCE,
+            Error::SenderDoesNotExist => SENDER_DOES_NOT_EXIST,
+            Error::RecipientDoesNotExist => RECEIVER_DOES_NOT_EXIST,
+            Error::AccountExists => ACCOUNT_EXISTS,
+            Error::IdempotencyKeyConflict => IDEMPOTENCY_KEY_CONFLICT,
+            Error::TransactionDoesNotExist => TRANSACTION_DOES_NOT_EXIST,
+            Error::InvalidCursor => INVALID_CURSOR,
+            Error::InvalidFilter => INVALID_FILTER,
+            Error::AssetDoesNotExist => ASSET_DOES_NOT_EXIST,
+            Error::AssetMismatch => ASSET_MISMATCH,
+            Error::AssetExists => ASSET_EXISTS,
+            Error::InvalidAmount => INVALID_AMOUNT,
+            Error::AmountOutOfRange => AMOUNT_OUT_OF_RANGE,
+            Error::IdOutOfRange => ID_OUT_OF_RANGE,
+            Error::ValidationFailed(_) => VALIDATION_FAILED,
+            Error::AccountFrozen(_) => ACCOUNT_FROZEN,
+            Error::AccountClosed(_) => ACCOUNT_CLOSED,
+            Error::InvalidStatusTransition { .. } => INVALID_STATUS_TRANSITION,
+            Error::AccountNotEmpty => ACCOUNT_NOT_EMPTY,
+            Error::TransactionNotReversible => TRANSACTION_NOT_REVERSIBLE,
+            Error::ReversalExceedsRemaining(_) => REVERSAL_EXCEEDS_REMAINING,
+            Error::HoldDoesNotExist => HOLD_DOES_NOT_EXIST,
+            Error::HoldNotPending(_) => HOLD_NOT_PENDING,
+            Error::CaptureExceedsHold(_) => CAPTURE_EXCEEDS_HOLD,
+            Error::FeeAccountMissing => FEE_ACCOUNT_MISSING,
+            Error::ScheduleDoesNotExist => SCHEDULE_DOES_NOT_EXIST,
+            Error::InterestProductExists => INTEREST_PRODUCT_EXISTS,
+            Error::InterestProductDoesNotExist => INTEREST_PRODUCT_DOES_NOT_EXIST,
+            Error::InterestAccountMissing => INTEREST_ACCOUNT_MISSING,
+            Error::Unauthorized => UNAUTHORIZED,
+            Error::Forbidden => FORBIDDEN,
+            // batches fail the way their failing transaction does
+            Error::BatchEntryFailed { source, .. } => source.description(),
+        }
+    }
+
+    fn details(&self) -> serde_json::Value {
+        match self {
+            Error::ResourceBusy
+            | Error::DatabaseUnavailable(_)
+            | Error::SerializationConflict(_) => {
+                serde_json::json!({ "retryable": true })
+            }
+            Error::ValidationFailed(fields) => serde_json::json!({ "fields": fields }),
+            Error::NotEnoughBalance {
+                asset,
+                required: Some(required),
+            } => serde_json::json!({ "asset": asset, "required_balance": required.to_string() }),
+            Error::AccountFrozen(Some(id)) | Error::AccountClosed(Some(id)) => {
+                serde_json::json!({ "account_id": id })
+            }
+            Error::InvalidStatusTransition { from, to } => {
+                serde_json::json!({ "status": from, "requested_status": to })
+            }
+            Error::ReversalExceedsRemaining(Some(remaining)) => {
+                serde_json::json!({ "remaining_amount": remaining.to_string() })
+            }
+            Error::HoldNotPending(Some(status)) => serde_json::json!({ "status": status }),
+            Error::CaptureExceedsHold(Some(held)) => {
+                serde_json::json!({ "held_amount": held.to_string() })
+            }
+            Error::BatchEntryFailed { index, source } => {
+                let mut details = source.details();
+                details["index"] = serde_json::json!(index);
+                details
+            }
+            _ => serde_json::json!({}),
+        }
+    }
+
+    /// Returns the stable code the error is reported to clients with.
+    pub fn code(&self) -> &'static str {
+        self.description().code
+    }
+
+    /// Returns whether the service, rather than the request, is at fault.
+    pub fn is_server_error(&self) -> bool {
+        self.description().status.is_server_error()
+    }
+
+    // Messages of most errors are fixed, whereas some name the values the request fell short of.
+    fn message(&self) -> String {
+        match self {
+            Error::NotEnoughBalance {
+                asset,
+                required: Some(required),
+            } => format!(
+                "Sender does not have enough balance to submit this transaction. Balance needs to be at least {} {}.",
+                required, asset
+            ),
+            Error::BatchEntryFailed { index, source } => {
+                format!("Transaction {} of the batch failed: {}", index, source.message())
+            }
+            _ => self.description().message.to_string(),
+        }
+    }
+}
+
+// handle_rejection receives a `Rejection` and returns a structured error body to the client.
 pub(crate) async fn handle_rejection(
     err: warp::reject::Rejection,
 ) -> Result<impl warp::Reply, Infallible> {
-    let (code, message): (StatusCode, &str) = match err.find() {
-        Some(Error::WindowLimitExceeded) => (StatusCode::BAD_REQUEST, WINDOW_LIMIT_EXCEEDED),
-        Some(Error::SerializationFailure) => (StatusCode::BAD_REQUEST, SERIALIZATION_FAILURE),
-        Some(Error::DatabaseQueryError) => (StatusCode::BAD_REQUEST, DB_QUERY_ERROR),
-        Some(Error::ResourceBusy) => (StatusCode::INTERNAL_SERVER_ERROR, RESOURCE_BUSY),
-        Some(Error::NotEnoughBalance) => (StatusCode::BAD_REQUEST, NOT_ENOUGH_BALANCE),
-        Some(Error::SenderDoesNotExist) => (StatusCode::BAD_REQUEST, SENDER_DOES_NOT_EXIST),
-        Some(Error::RecipientDoesNotExist) => (StatusCode::BAD_REQUEST, RECEIVER_DOES_NOT_EXIST),
-        Some(Error::AccountExists) => (StatusCode::BAD_REQUEST, ACCOUNT_EXISTS),
-        None => (StatusCode::INTERNAL_SERVER_ERROR, DB_QUERY_ERROR),
+    let (description, details) = if let Some(e) = err.find::<Error>() {
+        (e.description(), e.details())
+    } else if err.is_not_found() {
+        (ROUTE_NOT_FOUND, serde_json::json!({}))
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (INVALID_BODY, serde_json::json!({ "reason": e.to_string() }))
+    } else if let Some(e) = err.find::<warp::reject::InvalidQuery>() {
+        (
+            INVALID_QUERY,
+            serde_json::json!({ "reason": e.to_string() }),
+        )
+    } else if let Some(e) = err.find::<warp::reject::MissingHeader>() {
+        (INVALID_HEADER, serde_json::json!({ "header": e.name() }))
+    } else if let Some(e) = err.find::<warp::reject::InvalidHeader>() {
+        (INVALID_HEADER, serde_json::json!({ "header": e.name() }))
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        (PAYLOAD_TOO_LARGE, serde_json::json!({}))
+    } else if err.find::<warp::reject::LengthRequired>().is_some() {
+        (LENGTH_REQUIRED, serde_json::json!({}))
+    } else if err.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        (UNSUPPORTED_MEDIA_TYPE, serde_json::json!({}))
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (METHOD_NOT_ALLOWED, serde_json::json!({}))
+    } else {
+        (INTERNAL_ERROR, serde_json::json!({}))
+    };
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    if description.status.is_server_error() {
+        log::error!("[{}] {}: {:?}", request_id, description.code, err);
+    } else {
+        log::info!("[{}] {}", request_id, description.code);
+    }
+
+    let body = ErrorResponse {
+        code: description.code,
+        numeric_code: description.numeric_code,
+        message: err
+            .find::<Error>()
+            .map_or_else(|| description.message.to_string(), Error::message),
+        details,
+        request_id: request_id.clone(),
     };
 
-    Ok(http::Response::builder()
-        .status(code)
-        .body(message.to_string()))
-}
-
-const WINDOW_LIMIT_EXCEEDED: &str =
-    "Current window limit has exceeded. Please adhere to a max limit of 25.";
-const SERIALIZATION_FAILURE: &str = "Serde serialization failed. Please check Tx data stucture.";
-const DB_QUERY_ERROR: &str = "Database query error occured. Please check query attributes.";
-const RESOURCE_BUSY: &str = "Database resources busy. Please contact system administrator.";
-const SENDER_DOES_NOT_EXIST: &str = "Sender id does not exist on record. Please provide correct ID";
-const RECEIVER_DOES_NOT_EXIST: &str =
-    "Receiver id does not exist on record. Please provide correct ID";
-const NOT_ENOUGH_BALANCE: &str =
-    "Sender does not have enough balance to submit this transacion.Minimum balance needs to be 5.";
-const ACCOUNT_EXISTS: &str = "Account exists on DB";
+    let mut response = http::Response::builder()
+        .status(description.status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(REQUEST_ID_HEADER, request_id);
+    // tells clients which scheme to authenticate with
+    if description.status == StatusCode::UNAUTHORIZED {
+        response = response.header(http::header::WWW_AUTHENTICATE, "Bearer");
+    }
+
+    Ok(response.body(serde_json::to_string(&body).unwrap_or_default()))
+}
+
+/// [ErrorResponse] is the JSON body returned for every failed request.
+#[derive(Serialize)]
+struct ErrorResponse {
+    code: &'static str,
+    numeric_code: u16,
+    message: String,
+    details: serde_json::Value,
+    request_id: String,
+}
+
+/// [ErrorDescription] pairs an HTTP status with the stable codes and message of an error.
+#[derive(Clone, Copy)]
+struct ErrorDescription {
+    status: StatusCode,
+    code: &'static str,
+    numeric_code: u16,
+    message: &'static str,
+}
+
+// `REQUEST_ID_HEADER` echoes the request id of an error body, so it can be found in the logs.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const WINDOW_LIMIT_EXCEEDED: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "WINDOW_LIMIT_EXCEEDED",
+    numeric_code: 1001,
+    message: "Current window limit has exceeded. Please adhere to a max limit of 25.",
+};
+const SERIALIZATION_FAILURE: ErrorDescription = ErrorDescription {
+    status: StatusCode::INTERNAL_SERVER_ERROR,
+    code: "SERIALIZATION_FAILURE",
+    numeric_code: 1002,
+    message: "Serde serialization failed. Please check Tx data stucture.",
+};
+const DB_QUERY_ERROR: ErrorDescription = ErrorDescription {
+    status: StatusCode::INTERNAL_SERVER_ERROR,
+    code: "DATABASE_QUERY_ERROR",
+    numeric_code: 1003,
+    message: "Database query error occured. Please check query attributes.",
+};
+const RESOURCE_BUSY: ErrorDescription = ErrorDescription {
+    status: StatusCode::SERVICE_UNAVAILABLE,
+    code: "RESOURCE_BUSY",
+    numeric_code: 1004,
+    message: "Database resources busy. Please contact system administrator.",
+};
+const SENDER_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "SENDER_DOES_NOT_EXIST",
+    numeric_code: 1005,
+    message: "Sender id does not exist on record. Please provide correct ID",
+};
+const RECEIVER_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "RECIPIENT_DOES_NOT_EXIST",
+    numeric_code: 1006,
+    message: "Receiver id does not exist on record. Please provide correct ID",
+};
+const NOT_ENOUGH_BALANCE: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "NOT_ENOUGH_BALANCE",
+    numeric_code: 1007,
+    message: "Sender does not have enough balance to submit this transaction.",
+};
+const ACCOUNT_EXISTS: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "ACCOUNT_EXISTS",
+    numeric_code: 1008,
+    message: "Account exists on DB",
+};
+const IDEMPOTENCY_KEY_CONFLICT: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "IDEMPOTENCY_KEY_CONFLICT",
+    numeric_code: 1009,
+    message:
+        "Idempotency key has already been used with a different request. Please use a new key.",
+};
+const TRANSACTION_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "TRANSACTION_DOES_NOT_EXIST",
+    numeric_code: 1010,
+    message: "Transaction id does not exist on record. Please provide correct ID",
+};
+const INVALID_CURSOR: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "INVALID_CURSOR",
+    numeric_code: 1011,
+    message: "Cursor is malformed. Please pass the next_cursor returned by the previous page.",
+};
+const INVALID_FILTER: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "INVALID_FILTER",
+    numeric_code: 1012,
+    message: "Filter is invalid. Please check that ids and amounts are in range and that ranges are not inverted.",
+};
+const DATABASE_UNAVAILABLE: ErrorDescription = ErrorDescription {
+    status: StatusCode::SERVICE_UNAVAILABLE,
+    code: "DATABASE_UNAVAILABLE",
+    numeric_code: 1013,
+    message: "Database is unreachable. Please retry later.",
+};
+const SERIALIZATION_CONFLICT: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "SERIALIZATION_CONFLICT",
+    numeric_code: 1014,
+    message: "Request conflicted with a concurrent request. Please retry.",
+};
+const UNIQUE_VIOLATION: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "UNIQUE_VIOLATION",
+    numeric_code: 1015,
+    message: "Request conflicts with an existing record.",
+};
+const CONSTRAINT_VIOLATION: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "CONSTRAINT_VIOLATION",
+    numeric_code: 1016,
+    message: "Request violates a data integrity constraint. Please check the values submitted.",
+};
+const ASSET_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "ASSET_DOES_NOT_EXIST",
+    numeric_code: 1017,
+    message: "Asset does not exist on record. Please provide a registered asset symbol.",
+};
+const ASSET_MISMATCH: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "ASSET_MISMATCH",
+    numeric_code: 1018,
+    message: "Sender does not hold the asset of this transaction.",
+};
+const ASSET_EXISTS: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "ASSET_EXISTS",
+    numeric_code: 1019,
+    message: "Asset exists on DB",
+};
+const INVALID_AMOUNT: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "INVALID_AMOUNT",
+    numeric_code: 1020,
+    message: "Amount has more decimal places than its asset allows.",
+};
+const AMOUNT_OUT_OF_RANGE: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "AMOUNT_OUT_OF_RANGE",
+    numeric_code: 1021,
+    message: "Request would take an amount beyond the largest amount supported.",
+};
+const ID_OUT_OF_RANGE: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "ID_OUT_OF_RANGE",
+    numeric_code: 1022,
+    message: "Id is out of range. Ids cannot exceed 9223372036854775807.",
+};
+const VALIDATION_FAILED: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "VALIDATION_FAILED",
+    numeric_code: 1023,
+    message: "Request payload is invalid. Please check `details.fields` for the rejected fields.",
+};
+const ACCOUNT_FROZEN: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "ACCOUNT_FROZEN",
+    numeric_code: 1024,
+    message: "Account is frozen. Funds can neither leave nor reach it until it is unfrozen.",
+};
+const ACCOUNT_CLOSED: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "ACCOUNT_CLOSED",
+    numeric_code: 1025,
+    message: "Account is closed. Funds can neither leave nor reach it.",
+};
+const INVALID_STATUS_TRANSITION: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "INVALID_STATUS_TRANSITION",
+    numeric_code: 1026,
+    message: "Account cannot move to the requested status from its current one.",
+};
+const ACCOUNT_NOT_EMPTY: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "ACCOUNT_NOT_EMPTY",
+    numeric_code: 1027,
+    message: "Account still holds funds. Please sweep them to another account, or settle its overdraft, before closing it.",
+};
+const TRANSACTION_NOT_REVERSIBLE: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "TRANSACTION_NOT_REVERSIBLE",
+    numeric_code: 1028,
+    message: "Only transfers to a single recipient can be reversed. Please submit a new transaction instead.",
+};
+const REVERSAL_EXCEEDS_REMAINING: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "REVERSAL_EXCEEDS_REMAINING",
+    numeric_code: 1029,
+    message: "Reversal exceeds the amount of the transaction that has not been reversed yet.",
+};
+const HOLD_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "HOLD_DOES_NOT_EXIST",
+    numeric_code: 1030,
+    message: "Hold does not exist. Please check the hold id.",
+};
+const HOLD_NOT_PENDING: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "HOLD_NOT_PENDING",
+    numeric_code: 1031,
+    message: "Hold has been captured, voided or has expired already.",
+};
+const CAPTURE_EXCEEDS_HOLD: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNPROCESSABLE_ENTITY,
+    code: "CAPTURE_EXCEEDS_HOLD",
+    numeric_code: 1032,
+    message: "Capture exceeds the amount on hold.",
+};
+const FEE_ACCOUNT_MISSING: ErrorDescription = ErrorDescription {
+    status: StatusCode::SERVICE_UNAVAILABLE,
+    code: "FEE_ACCOUNT_MISSING",
+    numeric_code: 1033,
+    message: "Fee account does not exist. Please contact system administrator.",
+};
+const SCHEDULE_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "SCHEDULE_DOES_NOT_EXIST",
+    numeric_code: 1034,
+    message: "Schedule does not exist. Please check the schedule id.",
+};
+const INTEREST_PRODUCT_EXISTS: ErrorDescription = ErrorDescription {
+    status: StatusCode::CONFLICT,
+    code: "INTEREST_PRODUCT_EXISTS",
+    numeric_code: 1035,
+    message: "Interest product is defined already.",
+};
+const INTEREST_PRODUCT_DOES_NOT_EXIST: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "INTEREST_PRODUCT_DOES_NOT_EXIST",
+    numeric_code: 1036,
+    message: "Interest product does not exist. Please check the product code.",
+};
+const INTEREST_ACCOUNT_MISSING: ErrorDescription = ErrorDescription {
+    status: StatusCode::SERVICE_UNAVAILABLE,
+    code: "INTEREST_ACCOUNT_MISSING",
+    numeric_code: 1037,
+    message: "Interest account does not exist. Please contact system administrator.",
+};
+const UNAUTHORIZED: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNAUTHORIZED,
+    code: "UNAUTHORIZED",
+    numeric_code: 1038,
+    message: "API key is missing, unknown or revoked. Please send a valid one as `Authorization: Bearer <key>`.",
+};
+const FORBIDDEN: ErrorDescription = ErrorDescription {
+    status: StatusCode::FORBIDDEN,
+    code: "FORBIDDEN",
+    numeric_code: 1039,
+    message: "API key may not call this endpoint. Please send an admin key.",
+};
+
+// Errors raised by warp itself before a request reaches its handler.
+const ROUTE_NOT_FOUND: ErrorDescription = ErrorDescription {
+    status: StatusCode::NOT_FOUND,
+    code: "ROUTE_NOT_FOUND",
+    numeric_code: 1101,
+    message: "No endpoint exists at this path.",
+};
+const METHOD_NOT_ALLOWED: ErrorDescription = ErrorDescription {
+    status: StatusCode::METHOD_NOT_ALLOWED,
+    code: "METHOD_NOT_ALLOWED",
+    numeric_code: 1102,
+    message: "HTTP method is not supported at this path.",
+};
+const INVALID_BODY: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "INVALID_BODY",
+    numeric_code: 1103,
+    message: "Request body is not valid JSON for this endpoint. Please check the data structure.",
+};
+const INVALID_QUERY: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "INVALID_QUERY",
+    numeric_code: 1104,
+    message: "Query string is invalid for this endpoint. Please check parameter names and types.",
+};
+const INVALID_HEADER: ErrorDescription = ErrorDescription {
+    status: StatusCode::BAD_REQUEST,
+    code: "INVALID_HEADER",
+    numeric_code: 1105,
+    message: "A required header is missing or invalid.",
+};
+const PAYLOAD_TOO_LARGE: ErrorDescription = ErrorDescription {
+    status: StatusCode::PAYLOAD_TOO_LARGE,
+    code: "PAYLOAD_TOO_LARGE",
+    numeric_code: 1106,
+    message: "Request body exceeds the size limit of this endpoint.",
+};
+const LENGTH_REQUIRED: ErrorDescription = ErrorDescription {
+    status: StatusCode::LENGTH_REQUIRED,
+    code: "LENGTH_REQUIRED",
+    numeric_code: 1107,
+    message: "Request body must be sent with a Content-Length header.",
+};
+const UNSUPPORTED_MEDIA_TYPE: ErrorDescription = ErrorDescription {
+    status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+    code: "UNSUPPORTED_MEDIA_TYPE",
+    numeric_code: 1108,
+    message: "Request body must be sent as application/json.",
+};
+const INTERNAL_ERROR: ErrorDescription = ErrorDescription {
+    status: StatusCode::INTERNAL_SERVER_ERROR,
+    code: "INTERNAL_ERROR",
+    numeric_code: 1199,
+    message: "An unexpected error occured. Please contact system administrator.",
+};
@@ -10,7 +10,7 @@ mod error_codes;
 /// `routes` defines the set of HTTP endpoints for serving information related to accounts and transactions.
 mod routes;
 
-use anyhow::{Error, Result};
+use anyhow::{anyhow, Error, Result};
 use clap::Parser;
 use humantime::Timestamp;
 use std::{sync::Arc, time::SystemTime};
@@ -60,44 +60,144 @@ async fn main() -> Result<(), Error> {
         ))?)
         .apply()?;
 
+    //////////////////////////////////////////
+    // 3. Run Administrative Commands, If Any
+    //////////////////////////////////////////
+
+    // commands may run while the service is serving requests, so they leave the schema alone
+    if let Some(command) = cli_args.command {
+        if cli_args.start_anew {
+            return Err(anyhow!("--start-anew cannot be combined with a command"));
+        }
+
+        let db = db::Database::connect(db_config.as_str(), &service_config)
+            .await
+            .expect("Irrecoverable error: Failed to connect to database.");
+        return run_command(&db, command).await;
+    }
+
     ///////////////////////
-    // 3. Open Service DB
+    // 4. Open Service DB
     ///////////////////////
 
-    let db = db::Database::open(cli_args.start_anew, db_config.as_str())
+    let db = db::Database::open(cli_args.start_anew, db_config.as_str(), &service_config)
         .await
         .expect("Irrecoverable error: Failed to open database.");
 
     let db = Arc::new(db);
     let db_instance_accounts = Arc::clone(&db);
     let db_instance_transactions = Arc::clone(&db);
+    let db_instance_assets = Arc::clone(&db);
+    let db_instance_policies = Arc::clone(&db);
+    let db_instance_statuses = Arc::clone(&db);
+    let db_instance_interest = Arc::clone(&db);
+    let db_instance_holds = Arc::clone(&db);
+    let db_instance_schedules = Arc::clone(&db);
+    let db_instance_expiry = Arc::clone(&db);
+    let db_instance_scheduler = Arc::clone(&db);
+    let db_instance_accrual = Arc::clone(&db);
 
     ///////////////////////////////////
-    // 4. Serve Users and Tx Endpoints
+    // 5. Release Expired Holds Timely
     ///////////////////////////////////
 
+    let mut expiry_interval = tokio::time::interval(std::time::Duration::from_secs(
+        service_config.hold_expiry_interval_secs,
+    ));
+    tokio::spawn(async move {
+        loop {
+            expiry_interval.tick().await;
+            match db_instance_expiry.expire_holds().await {
+                Ok(0) => {}
+                Ok(released) => log::info!("released {} expired holds", released),
+                // failures are logged on classifying them, and the next pass retries
+                Err(_) => {}
+            }
+        }
+    });
+
+    ////////////////////////////////
+    // 6. Run Due Schedules Timely
+    ////////////////////////////////
+
+    let mut schedule_interval = tokio::time::interval(std::time::Duration::from_secs(
+        service_config.schedule_interval_secs,
+    ));
+    tokio::spawn(async move {
+        loop {
+            schedule_interval.tick().await;
+            match db_instance_scheduler.run_due_schedules().await {
+                Ok(0) => {}
+                Ok(runs) => log::info!("ran {} due schedules", runs),
+                // failures are logged on classifying them, and the next pass retries
+                Err(_) => {}
+            }
+        }
+    });
+
+    ////////////////////////////////////////
+    // 7. Accrue and Pay Interest Every Day
+    ////////////////////////////////////////
+
+    let mut interest_interval = tokio::time::interval(std::time::Duration::from_secs(
+        service_config.interest_interval_secs,
+    ));
+    tokio::spawn(async move {
+        loop {
+            interest_interval.tick().await;
+            match db_instance_accrual.accrue_interest(None).await {
+                Ok(run) if run.accruals == 0 && run.payments == 0 => {}
+                Ok(run) => log::info!(
+                    "accrued interest {} times and paid it {} times through {}",
+                    run.accruals,
+                    run.payments,
+                    run.through
+                ),
+                // failures are logged on classifying them, and the next pass resumes the run
+                Err(_) => {}
+            }
+        }
+    });
+
+    ////////////////////////////////////////////////////////////////
+    // 8. Serve Users, Tx, Hold, Schedule, Asset and Admin Endpoints
+    ////////////////////////////////////////////////////////////////
+
+    // browsers may only call the service cross-origin from the origins configured, if any
+    let cors =
+        warp::cors().allow_origins(service_config.allowed_origins.iter().map(String::as_str));
+
+    // every endpoint but the index requires an API key, and admin endpoints an admin one
     let warp_serve = warp::serve(
         routes::index_route()
             .or(routes::transactions(Arc::clone(&db_instance_transactions)))
             .or(routes::accounts(Arc::clone(&db_instance_accounts)))
+            .or(routes::holds(Arc::clone(&db_instance_holds)))
+            .or(routes::schedules(Arc::clone(&db_instance_schedules)))
+            .or(routes::assets(Arc::clone(&db_instance_assets)))
+            .or(routes::policies(Arc::clone(&db_instance_policies)))
+            .or(routes::account_statuses(Arc::clone(&db_instance_statuses)))
+            .or(routes::interest(Arc::clone(&db_instance_interest)))
             .recover(error_codes::handle_rejection)
             .with(
-                warp::cors()
-                    .allow_any_origin()
-                    .allow_headers(vec![
-                        "content-type",
-                        "User-Agent",
-                        "Sec-Fetch-Mode",
-                        "Referer",
-                        "Origin",
-                        "Access-Control-Request-Method",
-                        "Access-Control-Request-Headers",
-                    ])
-                    .allow_methods(&[
-                        warp::http::Method::GET,
-                        warp::http::Method::POST,
-                        warp::http::Method::OPTIONS,
-                    ]),
+                cors.allow_headers(vec![
+                    "content-type",
+                    "Authorization",
+                    "Idempotency-Key",
+                    "User-Agent",
+                    "Sec-Fetch-Mode",
+                    "Referer",
+                    "Origin",
+                    "Access-Control-Request-Method",
+                    "Access-Control-Request-Headers",
+                ])
+                .expose_headers(vec!["x-request-id"])
+                .allow_methods(&[
+                    warp::http::Method::GET,
+                    warp::http::Method::POST,
+                    warp::http::Method::PUT,
+                    warp::http::Method::OPTIONS,
+                ]),
             ),
     );
 
@@ -114,3 +214,34 @@ async fn main() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Runs an administrative `command` against DB, printing its outcome as JSON.
+async fn run_command(db: &db::Database, command: config::Command) -> Result<(), Error> {
+    let output = match command {
+        config::Command::ApiKeys(config::ApiKeyCommand::Create { name, admin }) => {
+            let api_key = db
+                .create_api_key(&name, admin)
+                .await
+                .map_err(command_error)?;
+            serde_json::to_string_pretty(&api_key)?
+        }
+        config::Command::ApiKeys(config::ApiKeyCommand::List) => {
+            let api_keys = db.list_api_keys().await.map_err(command_error)?;
+            serde_json::to_string_pretty(&api_keys)?
+        }
+        config::Command::ApiKeys(config::ApiKeyCommand::Revoke { id }) => {
+            match db.revoke_api_key(id).await.map_err(command_error)? {
+                Some(api_key) => serde_json::to_string_pretty(&api_key)?,
+                None => return Err(anyhow!("API key {} does not exist", id)),
+            }
+        }
+    };
+
+    println!("{}", output);
+
+    Ok(())
+}
+
+fn command_error(e: error_codes::Error) -> Error {
+    anyhow!("{}: {:?}", e.code(), e)
+}
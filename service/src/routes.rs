@@ -4,7 +4,12 @@ use serde_derive::{Deserialize, Serialize};
 use std::{convert::Infallible, sync::Arc};
 use warp::{self, http, Filter};
 
-use crate::db::{self, Transaction, User};
+use crate::db::{
+    self, AccountFilter, AccountStatus, Asset, Capture, IdempotencyHeader, InterestEnrollment,
+    InterestProduct, InterestRunRequest, NewAccount, NewHold, NewSchedule, PolicyOverrides,
+    Reversal, ScheduleFilter, ScheduleStatus, StatusChangeRequest, Transaction, TransactionBatch,
+    TransactionFilter,
+};
 use crate::error_codes::Error as ServiceAPIError;
 
 /// Index Route (GET /).
@@ -18,6 +23,65 @@ pub(crate) fn index_route(
     warp::path!().and(warp::get()).and_then(index_page_handler)
 }
 
+/// Rejects requests that do not send a valid API key as `Authorization: Bearer <key>`. Routes
+/// check it once their path and method match, so that stray requests are told they are.
+pub(crate) fn authenticated(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    api_key(db, false).map(|_| ()).untuple_one()
+}
+
+/// Rejects requests that do not send a valid admin API key, the way [authenticated] does.
+pub(crate) fn admin(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    api_key(db, true).map(|_| ()).untuple_one()
+}
+
+/// Extracts the `Idempotency-Key` header of requests, if any, scoped to the API key they send,
+/// so that clients picking the same key do not collide. Rejects requests the way
+/// [authenticated] does.
+pub(crate) fn idempotency_key(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (Option<IdempotencyHeader>,), Error = warp::Rejection> + Clone {
+    api_key(db, false)
+        .and(warp::header::optional::<String>(IDEMPOTENCY_KEY_HEADER))
+        .map(|api_key: db::api_keys::ApiKey, key: Option<String>| {
+            key.map(|key| IdempotencyHeader {
+                api_key_id: api_key.id,
+                key,
+            })
+        })
+}
+
+fn api_key(
+    db: Arc<db::Database>,
+    admin: bool,
+) -> impl Filter<Extract = (db::api_keys::ApiKey,), Error = warp::Rejection> + Clone {
+    async fn authenticate(
+        authorization: Option<String>,
+        admin: bool,
+        db: Arc<db::Database>,
+    ) -> Result<db::api_keys::ApiKey, warp::Rejection> {
+        let key = authorization
+            .as_deref()
+            .and_then(|authorization| authorization.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(BEARER_SCHEME))
+            .map(|(_, key)| key.trim())
+            .ok_or_else(|| warp::reject::custom(ServiceAPIError::Unauthorized))?;
+
+        let api_key = db.authenticate(key).await.map_err(warp::reject::custom)?;
+        if admin && !api_key.admin {
+            return Err(warp::reject::custom(ServiceAPIError::Forbidden));
+        }
+
+        Ok(api_key)
+    }
+
+    warp::header::optional::<String>(http::header::AUTHORIZATION.as_str())
+        .and_then(move |authorization| authenticate(authorization, admin, Arc::clone(&db)))
+}
+
 //////////////////////////////////
 // Handlers for Service Endpoints
 //////////////////////////////////
@@ -28,27 +92,68 @@ pub(crate) fn transactions(
     // POST /transactions
     pub async fn post_tx(
         tx: Transaction,
+        idempotency_key: Option<IdempotencyHeader>,
         db: Arc<db::Database>,
     ) -> Result<impl warp::Reply, warp::Rejection> {
-        let query_response = db.post_tx(tx).await.map_err(|e| warp::reject::custom(e))?;
+        let query_response = db
+            .post_tx(tx, idempotency_key)
+            .await
+            .map_err(warp::reject::custom)?;
 
         Ok(warp::reply::json(&query_response))
     }
 
+    // POST /transactions/batch
+    pub async fn post_tx_batch(
+        batch: TransactionBatch,
+        idempotency_key: Option<IdempotencyHeader>,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let batch = db
+            .post_tx_batch(batch, idempotency_key)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&batch))
+    }
+
+    // POST /transactions/quote
+    pub async fn quote_tx(
+        tx: Transaction,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let quote = db.quote_tx(tx).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&quote))
+    }
+
+    // POST /transactions/id/reverse
+    pub async fn reverse_tx(
+        id: u64,
+        reversal: Reversal,
+        idempotency_key: Option<IdempotencyHeader>,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let reversal = db
+            .reverse_tx(id, reversal, idempotency_key)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&reversal))
+    }
+
     // GET /transactions
     pub async fn get_transactions(
         limit: Limit,
+        filter: TransactionFilter,
         db: Arc<db::Database>,
     ) -> Result<impl warp::Reply, warp::Rejection> {
-        let window = limit.limit.unwrap_or(MAX_WINDOW_SIZE);
-        if window == 0 || window > MAX_WINDOW_SIZE {
-            return Err(warp::reject::custom(ServiceAPIError::WindowLimitExceeded));
-        }
+        let window = limit.window()?;
 
         let tx_response = db
-            .get_tx(window as usize)
+            .get_tx(&filter, window)
             .await
-            .map_err(|e| warp::reject::custom(e))?;
+            .map_err(warp::reject::custom)?;
 
         let query_resposne = serde_json::to_string(&tx_response)
             .map_err(|_| warp::reject::custom(ServiceAPIError::SerializationFailure))?;
@@ -58,24 +163,110 @@ pub(crate) fn transactions(
             .body(query_resposne))
     }
 
+    // GET /transactions/id
+    pub async fn get_transaction(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let tx = db.get_tx_by_id(id).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&tx))
+    }
+
+    // GET /users/id/transactions
+    pub async fn get_account_history(
+        id: u64,
+        limit: Limit,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let window = limit.window()?;
+
+        let history = db
+            .get_account_history(id, limit.after.as_deref(), window)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&history))
+    }
+
+    let get_account_history_route = |db: Arc<db::Database>| {
+        warp::path!("users" / u64 / "transactions")
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::query::<Limit>())
+            .and(warp::path::end())
+            .and_then(move |id, limit| get_account_history(id, limit, Arc::clone(&db)))
+    };
+
+    let get_tx_by_id_route = |db: Arc<db::Database>| {
+        warp::path!("transactions" / u64)
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_transaction(id, Arc::clone(&db)))
+    };
+
     let get_tx_route = |db: Arc<db::Database>| {
         warp::get()
             .and(warp::path("transactions"))
-            .and(warp::query::<Limit>())
             .and(warp::path::end())
-            .and_then(move |limit| get_transactions(limit, Arc::clone(&db)))
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::query::<Limit>())
+            .and(warp::query::<TransactionFilter>())
+            .and_then(move |limit, filter| get_transactions(limit, filter, Arc::clone(&db)))
     };
 
     let post_tx_route = |db: Arc<db::Database>| {
         warp::path!("transactions")
             .and(warp::post())
+            .and(idempotency_key(Arc::clone(&db)))
             .and(warp::body::content_length_limit(10 * 1024 * 1024))
             .and(warp::body::json())
             .and(warp::path::end())
-            .and_then(move |tx| post_tx(tx, Arc::clone(&db)))
+            .and_then(move |idempotency_key, tx| post_tx(tx, idempotency_key, Arc::clone(&db)))
     };
 
-    get_tx_route(db.clone()).or(post_tx_route(db.clone()))
+    let post_tx_batch_route = |db: Arc<db::Database>| {
+        warp::path!("transactions" / "batch")
+            .and(warp::post())
+            .and(idempotency_key(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |idempotency_key, batch| {
+                post_tx_batch(batch, idempotency_key, Arc::clone(&db))
+            })
+    };
+
+    let quote_tx_route = |db: Arc<db::Database>| {
+        warp::path!("transactions" / "quote")
+            .and(warp::post())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |tx| quote_tx(tx, Arc::clone(&db)))
+    };
+
+    let reverse_tx_route = |db: Arc<db::Database>| {
+        warp::path!("transactions" / u64 / "reverse")
+            .and(warp::post())
+            .and(idempotency_key(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |id, idempotency_key, reversal| {
+                reverse_tx(id, reversal, idempotency_key, Arc::clone(&db))
+            })
+    };
+
+    get_tx_by_id_route(db.clone())
+        .or(get_tx_route(db.clone()))
+        .or(post_tx_route(db.clone()))
+        .or(post_tx_batch_route(db.clone()))
+        .or(quote_tx_route(db.clone()))
+        .or(reverse_tx_route(db.clone()))
+        .or(get_account_history_route(db))
 }
 
 pub(crate) fn accounts(
@@ -83,63 +274,613 @@ pub(crate) fn accounts(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     // POST /users
     pub async fn create_account(
-        user: User,
+        account: NewAccount,
+        idempotency_key: Option<IdempotencyHeader>,
         db: Arc<db::Database>,
     ) -> Result<impl warp::Reply, warp::Rejection> {
         let query_response = db
-            .create_account(user)
+            .create_account(account, idempotency_key)
             .await
-            .map_err(|e| warp::reject::custom(e))?;
+            .map_err(warp::reject::custom)?;
 
         Ok(warp::reply::json(&query_response))
     }
 
+    // GET /users
+    pub async fn list_accounts(
+        limit: Limit,
+        filter: AccountFilter,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let window = limit.window()?;
+
+        let users = db
+            .list_accounts(&filter, limit.after.as_deref(), window)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&users))
+    }
+
     // GET /users/id
     pub async fn get_account(
-        id: Option<u64>,
+        id: u64,
         db: Arc<db::Database>,
     ) -> Result<impl warp::Reply, warp::Rejection> {
         let user = db
             .get_account_info(id)
             .await
-            .map_err(|e| warp::reject::custom(e))?;
+            .map_err(warp::reject::custom)?;
 
         Ok(warp::reply::json(&user))
     }
 
+    // GET /users/id/balance
+    pub async fn get_ledger_balance(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let balance = db
+            .recompute_balance(id)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&balance))
+    }
+
     let get_account_route = |db: Arc<db::Database>| {
         warp::path!("users" / u64)
             .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_account(id, Arc::clone(&db)))
+    };
+
+    let get_ledger_balance_route = |db: Arc<db::Database>| {
+        warp::path!("users" / u64 / "balance")
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
             .and(warp::path::end())
-            .and_then(move |id| get_account(Some(id), Arc::clone(&db)))
+            .and_then(move |id| get_ledger_balance(id, Arc::clone(&db)))
     };
 
     let get_accounts_route = |db: Arc<db::Database>| {
         warp::path!("users")
             .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::query::<Limit>())
+            .and(warp::query::<AccountFilter>())
             .and(warp::path::end())
-            .and_then(move || get_account(None, Arc::clone(&db)))
+            .and_then(move |limit, filter| list_accounts(limit, filter, Arc::clone(&db)))
     };
 
     let post_account_route = |db: Arc<db::Database>| {
         warp::path!("users")
             .and(warp::post())
+            .and(idempotency_key(Arc::clone(&db)))
             .and(warp::body::content_length_limit(10 * 1024 * 1024))
             .and(warp::body::json())
             .and(warp::path::end())
-            .and_then(move |user| create_account(user, Arc::clone(&db)))
+            .and_then(move |idempotency_key, account| {
+                create_account(account, idempotency_key, Arc::clone(&db))
+            })
     };
 
     get_account_route(db.clone())
+        .or(get_ledger_balance_route(db.clone()))
         .or(post_account_route(db.clone()))
         .or(get_accounts_route(db))
 }
 
+pub(crate) fn assets(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // POST /assets
+    pub async fn create_asset(
+        asset: Asset,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let asset = db.create_asset(asset).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&asset))
+    }
+
+    // GET /assets
+    pub async fn list_assets(db: Arc<db::Database>) -> Result<impl warp::Reply, warp::Rejection> {
+        let assets = db.list_assets().await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&assets))
+    }
+
+    let get_assets_route = |db: Arc<db::Database>| {
+        warp::path!("assets")
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move || list_assets(Arc::clone(&db)))
+    };
+
+    let post_asset_route = |db: Arc<db::Database>| {
+        warp::path!("assets")
+            .and(warp::post())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |asset| create_asset(asset, Arc::clone(&db)))
+    };
+
+    get_assets_route(db.clone()).or(post_asset_route(db))
+}
+
+pub(crate) fn policies(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // GET /admin/users/id/policy
+    pub async fn get_policy(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let policy = db.get_policy(id).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&policy))
+    }
+
+    // PUT /admin/users/id/policy
+    pub async fn set_policy(
+        id: u64,
+        overrides: PolicyOverrides,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let policy = db
+            .set_policy(id, overrides)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&policy))
+    }
+
+    let get_policy_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "users" / u64 / "policy")
+            .and(warp::get())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_policy(id, Arc::clone(&db)))
+    };
+
+    let put_policy_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "users" / u64 / "policy")
+            .and(warp::put())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |id, overrides| set_policy(id, overrides, Arc::clone(&db)))
+    };
+
+    get_policy_route(db.clone()).or(put_policy_route(db))
+}
+
+pub(crate) fn interest(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // POST /admin/interest-products
+    pub async fn create_interest_product(
+        product: InterestProduct,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let product = db
+            .create_interest_product(product)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&product))
+    }
+
+    // GET /admin/interest-products
+    pub async fn list_interest_products(
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let products = db
+            .list_interest_products()
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&products))
+    }
+
+    // GET /admin/users/id/interest
+    pub async fn get_account_interest(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let interest = db
+            .get_account_interest(id)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&interest))
+    }
+
+    // PUT /admin/users/id/interest
+    pub async fn set_account_interest(
+        id: u64,
+        enrollment: InterestEnrollment,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let interest = db
+            .set_account_interest(id, enrollment)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&interest))
+    }
+
+    // POST /admin/interest/accrue
+    pub async fn accrue_interest(
+        request: InterestRunRequest,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let run = db
+            .accrue_interest(request.through)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&run))
+    }
+
+    let get_products_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "interest-products")
+            .and(warp::get())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move || list_interest_products(Arc::clone(&db)))
+    };
+
+    let post_product_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "interest-products")
+            .and(warp::post())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |product| create_interest_product(product, Arc::clone(&db)))
+    };
+
+    let get_account_interest_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "users" / u64 / "interest")
+            .and(warp::get())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_account_interest(id, Arc::clone(&db)))
+    };
+
+    let put_account_interest_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "users" / u64 / "interest")
+            .and(warp::put())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |id, enrollment| set_account_interest(id, enrollment, Arc::clone(&db)))
+    };
+
+    let post_accrual_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "interest" / "accrue")
+            .and(warp::post())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |request| accrue_interest(request, Arc::clone(&db)))
+    };
+
+    get_products_route(db.clone())
+        .or(post_product_route(db.clone()))
+        .or(get_account_interest_route(db.clone()))
+        .or(put_account_interest_route(db.clone()))
+        .or(post_accrual_route(db))
+}
+
+pub(crate) fn account_statuses(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // POST /admin/users/id/freeze, /admin/users/id/unfreeze and /admin/users/id/close
+    pub async fn change_account_status(
+        id: u64,
+        status: AccountStatus,
+        change: StatusChangeRequest,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let change = db
+            .change_account_status(id, status, change)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&change))
+    }
+
+    // GET /admin/users/id/status
+    pub async fn get_account_status(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let status = db
+            .get_account_status(id)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&status))
+    }
+
+    let get_status_route = |db: Arc<db::Database>| {
+        warp::path!("admin" / "users" / u64 / "status")
+            .and(warp::get())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_account_status(id, Arc::clone(&db)))
+    };
+
+    let post_status_route = |db: Arc<db::Database>, action: &'static str, status| {
+        warp::path("admin")
+            .and(warp::path("users"))
+            .and(warp::path::param::<u64>())
+            .and(warp::path(action))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(admin(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and_then(move |id, change| change_account_status(id, status, change, Arc::clone(&db)))
+    };
+
+    get_status_route(db.clone())
+        .or(post_status_route(
+            db.clone(),
+            "freeze",
+            AccountStatus::Frozen,
+        ))
+        .or(post_status_route(
+            db.clone(),
+            "unfreeze",
+            AccountStatus::Active,
+        ))
+        .or(post_status_route(db, "close", AccountStatus::Closed))
+}
+
+pub(crate) fn holds(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // POST /holds
+    pub async fn place_hold(
+        hold: NewHold,
+        idempotency_key: Option<IdempotencyHeader>,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let hold = db
+            .place_hold(hold, idempotency_key)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&hold))
+    }
+
+    // GET /holds/id
+    pub async fn get_hold(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let hold = db.get_hold(id).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&hold))
+    }
+
+    // POST /holds/id/capture
+    pub async fn capture_hold(
+        id: u64,
+        capture: Capture,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let hold = db
+            .capture_hold(id, capture)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&hold))
+    }
+
+    // POST /holds/id/void
+    pub async fn void_hold(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let hold = db.void_hold(id).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&hold))
+    }
+
+    let post_hold_route = |db: Arc<db::Database>| {
+        warp::path!("holds")
+            .and(warp::post())
+            .and(idempotency_key(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |idempotency_key, hold| {
+                place_hold(hold, idempotency_key, Arc::clone(&db))
+            })
+    };
+
+    let get_hold_route = |db: Arc<db::Database>| {
+        warp::path!("holds" / u64)
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_hold(id, Arc::clone(&db)))
+    };
+
+    let capture_hold_route = |db: Arc<db::Database>| {
+        warp::path!("holds" / u64 / "capture")
+            .and(warp::post())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |id, capture| capture_hold(id, capture, Arc::clone(&db)))
+    };
+
+    let void_hold_route = |db: Arc<db::Database>| {
+        warp::path!("holds" / u64 / "void")
+            .and(warp::post())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| void_hold(id, Arc::clone(&db)))
+    };
+
+    post_hold_route(db.clone())
+        .or(get_hold_route(db.clone()))
+        .or(capture_hold_route(db.clone()))
+        .or(void_hold_route(db))
+}
+
+pub(crate) fn schedules(
+    db: Arc<db::Database>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    // POST /schedules
+    pub async fn create_schedule(
+        schedule: NewSchedule,
+        idempotency_key: Option<IdempotencyHeader>,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let schedule = db
+            .create_schedule(schedule, idempotency_key)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&schedule))
+    }
+
+    // GET /schedules
+    pub async fn list_schedules(
+        limit: Limit,
+        filter: ScheduleFilter,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let window = limit.window()?;
+
+        let schedules = db
+            .list_schedules(&filter, limit.after.as_deref(), window)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&schedules))
+    }
+
+    // GET /schedules/id
+    pub async fn get_schedule(
+        id: u64,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let schedule = db.get_schedule(id).await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&schedule))
+    }
+
+    // POST /schedules/id/pause, /schedules/id/resume and /schedules/id/cancel
+    pub async fn change_schedule_status(
+        id: u64,
+        status: ScheduleStatus,
+        db: Arc<db::Database>,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
+        let schedule = db
+            .change_schedule_status(id, status)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&schedule))
+    }
+
+    let post_schedule_route = |db: Arc<db::Database>| {
+        warp::path!("schedules")
+            .and(warp::post())
+            .and(idempotency_key(Arc::clone(&db)))
+            .and(warp::body::content_length_limit(10 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::path::end())
+            .and_then(move |idempotency_key, schedule| {
+                create_schedule(schedule, idempotency_key, Arc::clone(&db))
+            })
+    };
+
+    let list_schedules_route = |db: Arc<db::Database>| {
+        warp::path!("schedules")
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::query::<Limit>())
+            .and(warp::query::<ScheduleFilter>())
+            .and(warp::path::end())
+            .and_then(move |limit, filter| list_schedules(limit, filter, Arc::clone(&db)))
+    };
+
+    let get_schedule_route = |db: Arc<db::Database>| {
+        warp::path!("schedules" / u64)
+            .and(warp::get())
+            .and(authenticated(Arc::clone(&db)))
+            .and(warp::path::end())
+            .and_then(move |id| get_schedule(id, Arc::clone(&db)))
+    };
+
+    let post_status_route = |db: Arc<db::Database>, action: &'static str, status| {
+        warp::path("schedules")
+            .and(warp::path::param::<u64>())
+            .and(warp::path(action))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(authenticated(Arc::clone(&db)))
+            .and_then(move |id| change_schedule_status(id, status, Arc::clone(&db)))
+    };
+
+    post_schedule_route(db.clone())
+        .or(list_schedules_route(db.clone()))
+        .or(get_schedule_route(db.clone()))
+        .or(post_status_route(
+            db.clone(),
+            "pause",
+            ScheduleStatus::Paused,
+        ))
+        .or(post_status_route(
+            db.clone(),
+            "resume",
+            ScheduleStatus::Active,
+        ))
+        .or(post_status_route(db, "cancel", ScheduleStatus::Cancelled))
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Limit {
     pub limit: Option<u64>,
+    // cursor returned as `next_cursor` by endpoints that reply with a [db::Page].
+    pub after: Option<String>,
+}
+
+impl Limit {
+    fn window(&self) -> Result<usize, warp::Rejection> {
+        let window = self.limit.unwrap_or(MAX_WINDOW_SIZE);
+        if window == 0 || window > MAX_WINDOW_SIZE {
+            return Err(warp::reject::custom(ServiceAPIError::WindowLimitExceeded));
+        }
+
+        Ok(window as usize)
+    }
 }
 
+// IDEMPOTENCY_KEY_HEADER names the header carrying a client-generated key that makes
+// retries of a POST request safe.
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+// BEARER_SCHEME names the authentication scheme API keys are sent with.
+const BEARER_SCHEME: &str = "Bearer";
+
 // MAX_WINDOW_SIZE denotes the maximum allowable number of entities that can be fetched
 // from DB in one endpoint call.
 const MAX_WINDOW_SIZE: u64 = 25;
@@ -10,12 +10,57 @@ pub(crate) mod service {
     pub(crate) const LOGS_PATH: &str = "/logs/service_api";
     pub(crate) const CONFIGURATION_PATH: &str = "/service.toml";
     pub(crate) const BINARY_PATH: &str = "/submission";
-    pub(crate) const SENDER_DOES_NOT_EXIST: &str =
-        "Sender id does not exist on record. Please provide correct ID";
-    pub(crate) const RECEIVER_DOES_NOT_EXIST: &str =
-        "Receiver id does not exist on record. Please provide correct ID";
-    pub(crate) const NOT_ENOUGH_BALANCE: &str = "Sender does not have enough balance to submit this transacion.Minimum balance needs to be 5.";
-    pub(crate) const ACCOUNT_EXISTS: &str = "Account exists on DB";
+    pub(crate) const THRESHOLD_BALANCE: u64 = 5;
+    pub(crate) const DEFAULT_ASSET: &str = "TOCOS";
+    // kept small so that tests can exceed it cheaply
+    pub(crate) const MAX_BATCH_SIZE: usize = 5;
+    // the only asset transfers are charged fees on, credited to the account of `FEE_ACCOUNT_REF`
+    pub(crate) const FEE_ASSET: &str = "FEES";
+    pub(crate) const FEE_ACCOUNT_REF: &str = "fees";
+    // the account paying interest, which tests create only once they expect interest to be paid
+    pub(crate) const INTEREST_ACCOUNT_REF: &str = "interest";
+    // retries of scheduled transfers short of balance, a second apart, before they fail
+    pub(crate) const SCHEDULE_MAX_RETRIES: u32 = 2;
+    // the only origin browsers may call the service from, as the front end does
+    pub(crate) const ALLOWED_ORIGIN: &str = "http://localhost:3000";
+}
+
+// Stable error codes returned by the Service.
+pub(crate) mod error_codes {
+    pub(crate) const SENDER_DOES_NOT_EXIST: &str = "SENDER_DOES_NOT_EXIST";
+    pub(crate) const RECEIVER_DOES_NOT_EXIST: &str = "RECIPIENT_DOES_NOT_EXIST";
+    pub(crate) const NOT_ENOUGH_BALANCE: &str = "NOT_ENOUGH_BALANCE";
+    pub(crate) const ACCOUNT_EXISTS: &str = "ACCOUNT_EXISTS";
+    pub(crate) const IDEMPOTENCY_KEY_CONFLICT: &str = "IDEMPOTENCY_KEY_CONFLICT";
+    pub(crate) const TRANSACTION_DOES_NOT_EXIST: &str = "TRANSACTION_DOES_NOT_EXIST";
+    pub(crate) const INVALID_FILTER: &str = "INVALID_FILTER";
+    pub(crate) const INVALID_BODY: &str = "INVALID_BODY";
+    pub(crate) const METHOD_NOT_ALLOWED: &str = "METHOD_NOT_ALLOWED";
+    pub(crate) const ROUTE_NOT_FOUND: &str = "ROUTE_NOT_FOUND";
+    pub(crate) const DATABASE_UNAVAILABLE: &str = "DATABASE_UNAVAILABLE";
+    pub(crate) const ASSET_DOES_NOT_EXIST: &str = "ASSET_DOES_NOT_EXIST";
+    pub(crate) const ASSET_MISMATCH: &str = "ASSET_MISMATCH";
+    pub(crate) const ASSET_EXISTS: &str = "ASSET_EXISTS";
+    pub(crate) const INVALID_AMOUNT: &str = "INVALID_AMOUNT";
+    pub(crate) const AMOUNT_OUT_OF_RANGE: &str = "AMOUNT_OUT_OF_RANGE";
+    pub(crate) const ID_OUT_OF_RANGE: &str = "ID_OUT_OF_RANGE";
+    pub(crate) const VALIDATION_FAILED: &str = "VALIDATION_FAILED";
+    pub(crate) const ACCOUNT_FROZEN: &str = "ACCOUNT_FROZEN";
+    pub(crate) const ACCOUNT_CLOSED: &str = "ACCOUNT_CLOSED";
+    pub(crate) const INVALID_STATUS_TRANSITION: &str = "INVALID_STATUS_TRANSITION";
+    pub(crate) const ACCOUNT_NOT_EMPTY: &str = "ACCOUNT_NOT_EMPTY";
+    pub(crate) const TRANSACTION_NOT_REVERSIBLE: &str = "TRANSACTION_NOT_REVERSIBLE";
+    pub(crate) const REVERSAL_EXCEEDS_REMAINING: &str = "REVERSAL_EXCEEDS_REMAINING";
+    pub(crate) const HOLD_DOES_NOT_EXIST: &str = "HOLD_DOES_NOT_EXIST";
+    pub(crate) const HOLD_NOT_PENDING: &str = "HOLD_NOT_PENDING";
+    pub(crate) const CAPTURE_EXCEEDS_HOLD: &str = "CAPTURE_EXCEEDS_HOLD";
+    pub(crate) const FEE_ACCOUNT_MISSING: &str = "FEE_ACCOUNT_MISSING";
+    pub(crate) const SCHEDULE_DOES_NOT_EXIST: &str = "SCHEDULE_DOES_NOT_EXIST";
+    pub(crate) const INTEREST_PRODUCT_EXISTS: &str = "INTEREST_PRODUCT_EXISTS";
+    pub(crate) const INTEREST_PRODUCT_DOES_NOT_EXIST: &str = "INTEREST_PRODUCT_DOES_NOT_EXIST";
+    pub(crate) const INTEREST_ACCOUNT_MISSING: &str = "INTEREST_ACCOUNT_MISSING";
+    pub(crate) const UNAUTHORIZED: &str = "UNAUTHORIZED";
+    pub(crate) const FORBIDDEN: &str = "FORBIDDEN";
 }
 
 // Postgresql.
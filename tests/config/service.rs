@@ -1,12 +1,14 @@
 use anyhow::{anyhow, Result};
 use portpicker::pick_unused_port;
-use reqwest;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::process::{Command, Stdio};
 use tokio_postgres::NoTls;
 
 use crate::config::constants::common;
+use crate::config::constants::service::{
+    ALLOWED_ORIGIN, FEE_ACCOUNT_REF, FEE_ASSET, INTEREST_ACCOUNT_REF, MAX_BATCH_SIZE,
+    SCHEDULE_MAX_RETRIES,
+};
 use crate::utilities::*;
 use crate::{config, utilities};
 
@@ -14,6 +16,10 @@ pub(crate) struct Service {
     pub test_name: String,
     process: std::process::Child,
     port_number: u16,
+    service_binary: String,
+    config_path: String,
+    // key the Service was issued on start, sent along with every request.
+    api_key: String,
 }
 
 // Config denotes the configuration settings for the integration tests related to the Service API.
@@ -25,6 +31,34 @@ struct Config {
     pub db_name: String,
     pub logs_dir: String,
     pub port_number: u16,
+    pub allowed_origins: Vec<String>,
+    pub hold_expiry_interval_secs: u64,
+    pub max_batch_size: usize,
+    pub schedule_interval_secs: u64,
+    pub schedule_retry_backoff_secs: u64,
+    pub schedule_max_retries: u32,
+    pub interest_account_ref: String,
+    pub fee_account_ref: String,
+    pub fee_schedules: Vec<FeeSchedule>,
+}
+
+// FeeSchedule mirrors the fee schedules of the Service configuration.
+#[derive(Deserialize, Serialize)]
+struct FeeSchedule {
+    pub asset: String,
+    pub tier: Option<String>,
+    pub flat: String,
+    pub percentage: String,
+    pub min_fee: Option<String>,
+    pub max_fee: Option<String>,
+    pub bands: Vec<FeeBand>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct FeeBand {
+    pub from_amount: String,
+    pub flat: String,
+    pub percentage: String,
 }
 
 impl Config {
@@ -43,12 +77,60 @@ impl Config {
             db_user_pw: db_passwd.to_string(),
             db_name: test_name.to_string(),
             logs_dir: format!("{}/{}{}", config_dir_per_test, test_name, logs_path),
+            allowed_origins: vec![ALLOWED_ORIGIN.to_string()],
+            // releases expired holds promptly, so that tests need not wait long for it
+            hold_expiry_interval_secs: 1,
+            max_batch_size: MAX_BATCH_SIZE,
+            // runs due schedules and retries them promptly, for the same reason
+            schedule_interval_secs: 1,
+            schedule_retry_backoff_secs: 1,
+            schedule_max_retries: SCHEDULE_MAX_RETRIES,
+            interest_account_ref: INTEREST_ACCOUNT_REF.to_string(),
+            fee_account_ref: FEE_ACCOUNT_REF.to_string(),
+            // fees are charged on an asset of their own, so that other tests are not charged any
+            fee_schedules: vec![
+                FeeSchedule {
+                    asset: FEE_ASSET.to_string(),
+                    tier: None,
+                    flat: "1".to_string(),
+                    percentage: "2".to_string(),
+                    min_fee: None,
+                    max_fee: Some("50".to_string()),
+                    bands: Vec::new(),
+                },
+                FeeSchedule {
+                    asset: FEE_ASSET.to_string(),
+                    tier: Some("premium".to_string()),
+                    flat: "0".to_string(),
+                    percentage: "0".to_string(),
+                    min_fee: Some("0.25".to_string()),
+                    max_fee: None,
+                    bands: vec![
+                        FeeBand {
+                            from_amount: "0".to_string(),
+                            flat: "0".to_string(),
+                            percentage: "0.5".to_string(),
+                        },
+                        FeeBand {
+                            from_amount: "1000".to_string(),
+                            flat: "2".to_string(),
+                            percentage: "0".to_string(),
+                        },
+                    ],
+                },
+            ],
         }
     }
 }
 
 impl Service {
     pub(crate) async fn start(test_name: &str) -> Service {
+        Self::start_on(test_name, "").await
+    }
+
+    // Start the Service on a test database that `statements` were run against beforehand, e.g. to
+    // set up the schema of an earlier release.
+    pub(crate) async fn start_on(test_name: &str, statements: &str) -> Service {
         let service_binary = utilities::get_test_binary_path(
             config::common_constants::OUTPUT_DIR,
             config::service_constants::BINARY_PATH,
@@ -67,6 +149,12 @@ impl Service {
             .execute(&format!("{} {}", "CREATE DATABASE", test_name), &[])
             .await
             .unwrap();
+        Self::connect_to(test_name)
+            .await
+            .unwrap()
+            .batch_execute(statements)
+            .await
+            .unwrap();
 
         let mut configuration = Config::new_with_unconfigured_ports(
             config_dir_per_test.as_str(),
@@ -81,33 +169,39 @@ impl Service {
             let port_number: u16 = pick_unused_port().expect("No ports free");
             configuration.port_number = port_number;
 
-            std::fs::write(
-                format!(
-                    "{}{}",
-                    &config_dir_per_test,
-                    config::service_constants::CONFIGURATION_PATH
-                ),
-                toml::to_string(&configuration).unwrap(),
-            )
-            .expect("Unable to generate config json for testing.");
+            let config_path = format!(
+                "{}{}",
+                &config_dir_per_test,
+                config::service_constants::CONFIGURATION_PATH
+            );
+            std::fs::write(&config_path, toml::to_string(&configuration).unwrap())
+                .expect("Unable to generate config json for testing.");
 
             let process = Command::new(&service_binary)
-                .arg(&format!(
-                    "--config-path={}{}",
-                    config_dir_per_test,
-                    config::service_constants::CONFIGURATION_PATH
-                ))
+                .arg(format!("--config-path={}", config_path))
                 .stdout(Stdio::null())
                 .spawn();
 
             std::thread::sleep(std::time::Duration::from_millis(2000));
 
-            if process.is_ok() {
-                return Service {
+            if let Ok(process) = process {
+                let mut service = Service {
                     test_name: test_name.to_string(),
-                    process: process.unwrap(),
+                    process,
                     port_number,
+                    service_binary: service_binary.clone(),
+                    config_path,
+                    api_key: String::new(),
                 };
+
+                // every endpoint but the index requires an API key, issued from the command line,
+                // and tests reach the admin endpoints too
+                let api_key = service
+                    .run_command(&["api-keys", "create", "--name", test_name, "--admin"])
+                    .unwrap();
+                service.api_key = api_key["key"].as_str().unwrap().to_string();
+
+                return service;
             }
         }
     }
@@ -124,13 +218,102 @@ impl Service {
         }
     }
 
+    // Simulates a database outage by refusing new connections to the test database and
+    // terminating the ones the service holds.
+    pub(crate) async fn cut_off_database(&self) -> anyhow::Result<()> {
+        let client = Self::connect().await?;
+
+        client
+            .execute(
+                &format!("ALTER DATABASE {} ALLOW_CONNECTIONS false", self.test_name),
+                &[],
+            )
+            .await?;
+        client
+            .execute(
+                "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE datname = $1",
+                &[&self.test_name],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // Execute a statement directly against the test database, bypassing the Service.
+    pub(crate) async fn execute_sql(&self, statement: &str) -> anyhow::Result<u64> {
+        let client = Self::connect_to(&self.test_name).await?;
+
+        Ok(client.execute(statement, &[]).await?)
+    }
+
+    // Start the Service binary again against the test database, returning what it printed to
+    // stderr if it exits within the time it takes to start, or `None` if it keeps running.
+    pub(crate) fn start_again(&self) -> Result<Option<String>> {
+        let mut process = Command::new(&self.service_binary)
+            .arg(format!("--config-path={}", self.config_path))
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        std::thread::sleep(std::time::Duration::from_millis(2000));
+
+        if process.try_wait()?.is_none() {
+            process.kill()?;
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8(process.wait_with_output()?.stderr)?))
+    }
+
+    // Run a command of the Service binary against the test database, returning the JSON it prints.
+    pub(crate) fn run_command(&self, args: &[&str]) -> Result<serde_json::Value> {
+        let output = Command::new(&self.service_binary)
+            .arg(format!("--config-path={}", self.config_path))
+            .args(args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    // Client sending the API key of the Service along with every request.
+    fn client(&self) -> reqwest::Client {
+        self.client_with_key(Some(&self.api_key))
+    }
+
+    fn client_with_key(&self, api_key: Option<&str>) -> reqwest::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(api_key) = api_key {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", api_key).parse().unwrap(),
+            );
+        }
+
+        reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    }
+
     async fn connect() -> anyhow::Result<tokio_postgres::Client> {
+        Self::connect_to("").await
+    }
+
+    async fn connect_to(db_name: &str) -> anyhow::Result<tokio_postgres::Client> {
         let (client, conn) = tokio_postgres::connect(
             &format!(
-                "postgresql://{}:{}@{}",
+                "postgresql://{}:{}@{}/{}",
                 config::postgresql_constants::USER_NAME,
                 config::postgresql_constants::PASSWORD,
-                config::postgresql_constants::HOST_NAME
+                config::postgresql_constants::HOST_NAME,
+                db_name
             ),
             NoTls,
         )
@@ -151,65 +334,347 @@ impl Service {
         from_id: u64,
         to_id: u64,
         amount: u64,
-    ) -> Result<String> {
-        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
-        let mut map = HashMap::new();
-        map.insert("from_id", from_id);
-        map.insert("to_id", to_id);
-        map.insert("amount", amount);
+    ) -> Result<TransactionRecord> {
+        self.submit_transaction_with_idempotency_key(from_id, to_id, amount, None)
+            .await
+    }
+
+    // Submit a transaction moving a specific asset.
+    pub(crate) async fn submit_asset_transaction(
+        &self,
+        from_id: u64,
+        to_id: u64,
+        asset: &str,
+        amount: u64,
+    ) -> Result<TransactionRecord> {
+        self.send_transaction(
+            Transaction {
+                from_id,
+                to_id,
+                asset: Some(asset.to_string()),
+                amount,
+            },
+            None,
+        )
+        .await
+    }
+
+    // Submit a transaction, optionally tagging it with an Idempotency-Key header.
+    pub(crate) async fn submit_transaction_with_idempotency_key(
+        &self,
+        from_id: u64,
+        to_id: u64,
+        amount: u64,
+        idempotency_key: Option<&str>,
+    ) -> Result<TransactionRecord> {
+        let tx = Transaction {
+            from_id,
+            to_id,
+            asset: None,
+            amount,
+        };
+
+        self.send_transaction(tx, idempotency_key).await
+    }
 
+    async fn send_transaction(
+        &self,
+        tx: Transaction,
+        idempotency_key: Option<&str>,
+    ) -> Result<TransactionRecord> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
         let request_path = format!("{}/transactions", &api_url);
 
-        let response = reqwest::Client::new()
+        let mut request = self.client().post(request_path).json(&tx);
+        if let Some(idempotency_key) = idempotency_key {
+            request = request.header("Idempotency-Key", idempotency_key);
+        }
+
+        let response = request.send().await.map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Submit a batch of (from_id, to_id, amount) transfers of the default asset, all or nothing.
+    pub(crate) async fn submit_batch(&self, batch: &[(u64, u64, u64)]) -> Result<TransactionBatch> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/transactions/batch", &api_url);
+        let transactions = batch
+            .iter()
+            .map(|&(from_id, to_id, amount)| Transaction {
+                from_id,
+                to_id,
+                asset: None,
+                amount,
+            })
+            .collect::<Vec<_>>();
+
+        let response = self
+            .client()
+            .post(request_path)
+            .json(&serde_json::json!({ "transactions": transactions }))
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Reverse a transaction, refunding `amount` of it or whatever remains to be refunded.
+    pub(crate) async fn reverse_transaction(
+        &self,
+        id: u64,
+        amount: Option<u64>,
+    ) -> Result<TransactionRecord> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/transactions/{}/reverse", &api_url, id);
+
+        let response = self
+            .client()
             .post(request_path)
-            .json(&map)
+            .json(&serde_json::json!({ "amount": amount }))
             .send()
             .await
             .map_err(|e| anyhow!(e))?;
 
         match response.status() {
             reqwest::StatusCode::OK => {
-                return Ok("Success".to_string());
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
             }
-            _ => {
-                let body = response.text().await.unwrap();
-                return Err(anyhow!(body));
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Place a hold on `amount` of the default asset, expiring after `ttl_secs` or the configured default.
+    pub(crate) async fn place_hold(
+        &self,
+        from_id: u64,
+        to_id: u64,
+        amount: u64,
+        ttl_secs: Option<u64>,
+    ) -> Result<Hold> {
+        let body = serde_json::json!({
+            "from_id": from_id,
+            "to_id": to_id,
+            "amount": amount,
+            "ttl_secs": ttl_secs,
+        });
+
+        self.post_hold("/holds", body).await
+    }
+
+    // Capture `amount` of a hold, or all of it.
+    pub(crate) async fn capture_hold(&self, id: u64, amount: Option<u64>) -> Result<Hold> {
+        let path = format!("/holds/{}/capture", id);
+
+        self.post_hold(&path, serde_json::json!({ "amount": amount }))
+            .await
+    }
+
+    // Void a hold, releasing its funds.
+    pub(crate) async fn void_hold(&self, id: u64) -> Result<Hold> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/holds/{}/void", &api_url, id);
+
+        let response = self
+            .client()
+            .post(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
             }
+            _ => Err(error_code(response).await),
         }
     }
 
-    pub(crate) async fn create_account(&self, id: u64, balance: u64) -> Result<String> {
+    // Query a hold by id.
+    pub(crate) async fn query_hold(&self, id: u64) -> Result<Hold> {
         let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
-        let mut map = HashMap::new();
-        map.insert("id", id);
-        map.insert("balance", balance);
+        let request_path = format!("{}/holds/{}", &api_url, id);
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    async fn post_hold(&self, path: &str, body: serde_json::Value) -> Result<Hold> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}{}", &api_url, path);
+
+        let response = self
+            .client()
+            .post(request_path)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Schedule a transfer, as described by `body`.
+    pub(crate) async fn create_schedule(&self, body: serde_json::Value) -> Result<Schedule> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/schedules", &api_url);
+
+        let response = self
+            .client()
+            .post(request_path)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
 
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Query a schedule by id.
+    pub(crate) async fn query_schedule(&self, id: u64) -> Result<Schedule> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/schedules/{}", &api_url, id);
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Create an account opened with a balance of the default asset, returning the id allocated to it.
+    pub(crate) async fn create_account(&self, balance: u64) -> Result<u64> {
+        let user = self
+            .create_account_with(serde_json::json!({ "balance": balance }))
+            .await?;
+
+        Ok(user.id)
+    }
+
+    // Create an account opened with a balance of a specific asset, returning the id allocated to it.
+    pub(crate) async fn create_account_with_asset(&self, asset: &str, balance: u64) -> Result<u64> {
+        let user = self
+            .create_account_with(serde_json::json!({ "asset": asset, "balance": balance }))
+            .await?;
+
+        Ok(user.id)
+    }
+
+    // Create an account from the given request body, returning its opening holding.
+    pub(crate) async fn create_account_with(&self, body: serde_json::Value) -> Result<User> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
         let request_path = format!("{}/users", &api_url);
 
-        let response = reqwest::Client::new()
+        let response = self
+            .client()
             .post(request_path)
-            .json(&map)
+            .json(&body)
             .send()
             .await
             .map_err(|e| anyhow!(e))?;
 
         match response.status() {
             reqwest::StatusCode::OK => {
-                return Ok("Success".to_string());
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
             }
-            _ => {
-                let body = response.text().await.unwrap();
-                return Err(anyhow!(body));
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Register an asset.
+    pub(crate) async fn create_asset(&self, symbol: &str, decimals: u8) -> Result<Asset> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/assets", &api_url);
+        let asset = Asset {
+            symbol: symbol.to_string(),
+            decimals,
+            name: symbol.to_lowercase(),
+            issuer: None,
+        };
+
+        let response = self
+            .client()
+            .post(request_path)
+            .json(&asset)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
             }
+            _ => Err(error_code(response).await),
         }
     }
 
-    // Query user by id.
-    pub(crate) async fn query_user(&self, id: u64) -> Result<User> {
+    // Query the latest transactions, passing `query` through as the query string.
+    pub(crate) async fn query_transactions(&self, query: &str) -> Result<Vec<TransactionRecord>> {
         let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
-        let request_path = format!("{}/users/{}", &api_url, &id.to_string());
+        let request_path = format!("{}/transactions?{}", &api_url, query);
 
-        let response = reqwest::Client::new()
+        let response = self
+            .client()
             .get(request_path)
             .send()
             .await
@@ -219,23 +684,243 @@ impl Service {
             reqwest::StatusCode::OK => {
                 let data = response.text().await.unwrap();
 
-                let result: Vec<User> = serde_json::from_str(&data).unwrap();
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Query transaction by id.
+    pub(crate) async fn query_transaction(&self, id: u64) -> Result<TransactionRecord> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/transactions/{}", &api_url, &id.to_string());
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
 
-                return Ok(result[0].clone());
+                Ok(serde_json::from_str(&data).unwrap())
             }
-            _ => {
-                let body = response.text().await.unwrap();
-                return Err(anyhow!(body));
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Query one page of transactions sent or received by an account.
+    pub(crate) async fn query_account_history(
+        &self,
+        id: u64,
+        limit: u64,
+        after: Option<&str>,
+    ) -> Result<Page<HistoryEntry>> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let mut request_path = format!("{}/users/{}/transactions?limit={}", &api_url, id, limit);
+        if let Some(after) = after {
+            request_path = format!("{}&after={}", request_path, urlencoding::encode(after));
+        }
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
             }
+            _ => Err(error_code(response).await),
         }
     }
+
+    // Query one page of users, passing `query` through as the query string.
+    pub(crate) async fn query_users(&self, query: &str) -> Result<Page<User>> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/users?{}", &api_url, query);
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Send a raw request, returning the status and JSON body of the response.
+    pub(crate) async fn send_raw(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<(reqwest::StatusCode, serde_json::Value)> {
+        self.send_raw_with_key(Some(&self.api_key), method, path, body)
+            .await
+    }
+
+    // Send a raw request authenticated with `api_key`, if any.
+    pub(crate) async fn send_raw_with_key(
+        &self,
+        api_key: Option<&str>,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<(reqwest::StatusCode, serde_json::Value)> {
+        self.send_request(api_key, method, path, body, None).await
+    }
+
+    // Send a raw request tagged with an Idempotency-Key header.
+    pub(crate) async fn send_raw_with_idempotency_key(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&str>,
+        idempotency_key: &str,
+    ) -> Result<(reqwest::StatusCode, serde_json::Value)> {
+        self.send_request(
+            Some(&self.api_key),
+            method,
+            path,
+            body,
+            Some(idempotency_key),
+        )
+        .await
+    }
+
+    // Send the preflight request a browser at `origin` sends before a POST, returning its status
+    // along with the origin the Service allows, if any.
+    pub(crate) async fn send_preflight(
+        &self,
+        path: &str,
+        origin: &str,
+    ) -> Result<(reqwest::StatusCode, Option<String>)> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let response = reqwest::Client::new()
+            .request(reqwest::Method::OPTIONS, format!("{}{}", &api_url, path))
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header(
+                "Access-Control-Request-Headers",
+                "authorization, content-type",
+            )
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        let allowed_origin = response
+            .headers()
+            .get("access-control-allow-origin")
+            .map(|origin| origin.to_str().unwrap().to_string());
+
+        Ok((response.status(), allowed_origin))
+    }
+
+    // Send a raw request authenticated with `api_key` and tagged with `idempotency_key`, if any.
+    pub(crate) async fn send_request(
+        &self,
+        api_key: Option<&str>,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&str>,
+        idempotency_key: Option<&str>,
+    ) -> Result<(reqwest::StatusCode, serde_json::Value)> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let mut request = self
+            .client_with_key(api_key)
+            .request(method, format!("{}{}", &api_url, path));
+        if let Some(idempotency_key) = idempotency_key {
+            request = request.header("Idempotency-Key", idempotency_key);
+        }
+        if let Some(body) = body {
+            request = request
+                .header("content-type", "application/json")
+                .body(body.to_string());
+        }
+
+        let response = request.send().await.map_err(|e| anyhow!(e))?;
+        let status = response.status();
+        let body = response.json().await.map_err(|e| anyhow!(e))?;
+
+        Ok((status, body))
+    }
+
+    // Query the balance of a user recomputed from the journal.
+    pub(crate) async fn query_ledger_balance(&self, id: u64) -> Result<Vec<LedgerBalance>> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/users/{}/balance", &api_url, id);
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+
+    // Query user by id, returning its holding of the default asset.
+    pub(crate) async fn query_user(&self, id: u64) -> Result<User> {
+        Ok(self.query_holdings(id).await?[0].clone())
+    }
+
+    // Query all holdings of a user.
+    pub(crate) async fn query_holdings(&self, id: u64) -> Result<Vec<User>> {
+        let api_url = format!("{}:{}", common::HOST_URL, self.port_number);
+        let request_path = format!("{}/users/{}", &api_url, &id.to_string());
+
+        let response = self
+            .client()
+            .get(request_path)
+            .send()
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let data = response.text().await.unwrap();
+
+                Ok(serde_json::from_str(&data).unwrap())
+            }
+            _ => Err(error_code(response).await),
+        }
+    }
+}
+
+// Turn an error response into its stable error code, so tests do not depend on message wording.
+async fn error_code(response: reqwest::Response) -> anyhow::Error {
+    let body: ErrorResponse = response.json().await.unwrap();
+    anyhow!(body.code)
 }
 
 impl Drop for Service {
     fn drop(&mut self) {
-        match self.process.kill() {
-            Err(e) => println!("Could not kill child process: {}", e),
-            Ok(_) => {}
+        if let Err(e) = self.process.kill() {
+            println!("Could not kill child process: {}", e);
         }
 
         let test_name = self.test_name.clone();
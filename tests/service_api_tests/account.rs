@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-use crate::config::constants::service;
+use crate::config::constants::error_codes;
 use crate::config::service::Service;
 
 // Simulate account create success.
@@ -10,11 +10,11 @@ async fn test_create_account_success() -> Result<()> {
     let service = Service::start("test_create_account_success").await;
 
     // create user 1
-    let response = service.create_account(1, 10000).await;
+    let response = service.create_account(10000).await;
     assert!(response.is_ok());
 
     // query balance from 1 and check if balance is deducted
-    let response = service.query_user(1).await;
+    let response = service.query_user(response.unwrap()).await;
     assert!(response.is_ok());
     let user1 = response.unwrap();
     assert_eq!(user1.balance, 10000);
@@ -22,6 +22,69 @@ async fn test_create_account_success() -> Result<()> {
     Ok(())
 }
 
+// Simulate creating an account along with its metadata, letting the service assign its id.
+#[tokio::test]
+async fn test_create_account_with_metadata() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_create_account_with_metadata").await;
+
+    let user1 = service
+        .create_account_with(serde_json::json!({
+            "external_ref": "crm-1",
+            "display_name": "Alice",
+            "owner_ref": "org-7",
+            "metadata": {"tier": "gold"},
+            "balance": 100
+        }))
+        .await?;
+    assert!(user1.id > 0);
+    assert!(!user1.created_at.is_empty());
+
+    let user2 = service.create_account(100).await?;
+    assert_ne!(user1.id, user2);
+
+    // metadata is returned along with the account
+    let queried = service.query_user(user1.id).await?;
+    assert_eq!(queried.external_ref.as_deref(), Some("crm-1"));
+    assert_eq!(queried.display_name.as_deref(), Some("Alice"));
+    assert_eq!(queried.owner_ref.as_deref(), Some("org-7"));
+    assert_eq!(queried.metadata["tier"], "gold");
+    assert_eq!(queried.created_at, user1.created_at);
+
+    let queried = service.query_user(user2).await?;
+    assert!(queried.external_ref.is_none());
+    assert!(queried.metadata.as_object().unwrap().is_empty());
+
+    // accounts are found by the reference of the client
+    let page = service.query_users("external_ref=crm-1").await?;
+    let ids: Vec<u64> = page.items.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![user1.id]);
+
+    // ids are no longer chosen by clients
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/users",
+            Some(r#"{"id": 1, "balance": 10}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 400);
+    assert_eq!(body["code"], error_codes::INVALID_BODY);
+
+    for body in [
+        r#"{"metadata": [1, 2], "balance": 10}"#,
+        r#"{"display_name": " ", "balance": 10}"#,
+    ] {
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, "/users", Some(body))
+            .await?;
+        assert_eq!(status.as_u16(), 422);
+        assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+    }
+
+    Ok(())
+}
+
 // Simulate non existent account query failure.
 #[tokio::test]
 async fn test_non_existent_query_failure() -> Result<()> {
@@ -33,7 +96,10 @@ async fn test_non_existent_query_failure() -> Result<()> {
     assert!(response.is_err());
 
     let error_response = response.err().as_ref().unwrap().to_string();
-    assert_eq!(error_response, service::SENDER_DOES_NOT_EXIST.to_string());
+    assert_eq!(
+        error_response,
+        error_codes::SENDER_DOES_NOT_EXIST.to_string()
+    );
 
     Ok(())
 }
@@ -45,21 +111,415 @@ async fn test_duplicate_account_creation_failure() -> Result<()> {
     let service = Service::start("test_duplicate_account_creation_failure").await;
 
     // create user 1
-    let response = service.create_account(1, 10000).await;
+    let body = serde_json::json!({ "external_ref": "user-1", "balance": 10000 });
+    let response = service.create_account_with(body.clone()).await;
     assert!(response.is_ok());
 
     // query balance from 1 and check if balance is deducted
-    let response = service.query_user(1).await;
+    let response = service.query_user(response.unwrap().id).await;
     assert!(response.is_ok());
     let user1 = response.unwrap();
     assert_eq!(user1.balance, 10000);
 
     // create user 1 again
-    let response = service.create_account(1, 10000).await;
+    let response = service.create_account_with(body).await;
     assert!(response.is_err());
 
     let error_response = response.err().as_ref().unwrap().to_string();
-    assert_eq!(error_response, service::ACCOUNT_EXISTS.to_string());
+    assert_eq!(error_response, error_codes::ACCOUNT_EXISTS.to_string());
+
+    Ok(())
+}
+
+// Simulate retried account creations carrying the same idempotency key, within and past its window.
+#[tokio::test]
+async fn test_create_account_idempotent_replay() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_create_account_idempotent_replay").await;
+
+    // create user 1 twice with the same key, which replays the first response
+    let body = r#"{"external_ref": "user-1", "balance": 100}"#;
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let (status, user) = service
+            .send_raw_with_idempotency_key(
+                reqwest::Method::POST,
+                "/users",
+                Some(body),
+                "create-key",
+            )
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        ids.push(user["id"].clone());
+    }
+    assert_eq!(ids[0], ids[1]);
+    assert_eq!(service.query_users("").await?.items.len(), 1);
+
+    // reusing the key for a different body, or for a valid request to another route, is rejected
+    let recipient = service.create_account(100).await?;
+    let other_body = r#"{"external_ref": "user-2", "balance": 100}"#;
+    let tx = format!(
+        r#"{{"from_id": {}, "to_id": {}, "amount": 1}}"#,
+        ids[0], recipient
+    );
+    for (path, body) in [("/users", other_body), ("/transactions", tx.as_str())] {
+        let (status, error) = service
+            .send_raw_with_idempotency_key(reqwest::Method::POST, path, Some(body), "create-key")
+            .await?;
+        assert_eq!(status.as_u16(), 409);
+        assert_eq!(error["code"], error_codes::IDEMPOTENCY_KEY_CONFLICT);
+    }
+
+    // once the key falls out of its window (a day by default), it can be used for a new request
+    service
+        .execute_sql("UPDATE IdempotencyKey SET created_at = now() - interval '2 days'")
+        .await?;
+    let (status, user) = service
+        .send_raw_with_idempotency_key(
+            reqwest::Method::POST,
+            "/users",
+            Some(other_body),
+            "create-key",
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_ne!(user["id"], ids[0]);
+    assert_eq!(user["external_ref"], "user-2");
+
+    // and is replayed for it from then on
+    let (_, replayed) = service
+        .send_raw_with_idempotency_key(
+            reqwest::Method::POST,
+            "/users",
+            Some(other_body),
+            "create-key",
+        )
+        .await?;
+    assert_eq!(replayed["id"], user["id"]);
+    assert_eq!(service.query_users("").await?.items.len(), 3);
+
+    Ok(())
+}
+
+// Simulate paging through filtered and sorted users.
+#[tokio::test]
+async fn test_query_users_pagination_and_filters() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_query_users_pagination_and_filters").await;
+
+    // no users yet gives an empty page rather than an error
+    let page = service.query_users("").await.unwrap();
+    assert!(page.items.is_empty());
+    assert!(page.next_cursor.is_none());
+
+    // create users 1 to 5, where 2 and 4 share the same balance
+    let mut users = Vec::new();
+    for balance in [500, 300, 100, 300, 900] {
+        users.push(service.create_account(balance).await?);
+    }
+
+    // default order is by creation, newest first
+    let page = service.query_users("limit=2").await.unwrap();
+    let ids: Vec<u64> = page.items.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![users[4], users[3]]);
+
+    // page through users with balance between 200 and 600, smallest balance first, where users
+    // sharing a balance are ordered by id
+    let query = "limit=2&order=balance_asc&min_balance=200&max_balance=600";
+    let page = service.query_users(query).await.unwrap();
+    let ids: Vec<u64> = page.items.iter().map(|user| user.id).collect();
+    let (lower, higher) = (users[1].min(users[3]), users[1].max(users[3]));
+    assert_eq!(ids, vec![lower, higher]);
+
+    let query = format!("{}&after={}", query, page.next_cursor.unwrap());
+    let page = service.query_users(&query).await.unwrap();
+    let ids: Vec<u64> = page.items.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![users[0]]);
+    assert!(page.next_cursor.is_none());
+
+    Ok(())
+}
+
+// Simulate reconciling cached balances against the journal after transfers and after drift.
+#[tokio::test]
+async fn test_recompute_balance_from_journal() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_recompute_balance_from_journal").await;
+
+    // create users 1 and 2 and move funds between them
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(50).await?;
+    assert!(service.submit_transaction(user1, user2, 300).await.is_ok());
+    assert!(service.submit_transaction(user2, user1, 100).await.is_ok());
+
+    let balance = service.query_ledger_balance(user1).await.unwrap().remove(0);
+    assert_eq!(balance.ledger_balance, 800);
+    assert_eq!(balance.balance, 800);
+    assert!(balance.consistent);
+
+    let balance = service.query_ledger_balance(user2).await.unwrap().remove(0);
+    assert_eq!(balance.ledger_balance, 250);
+    assert!(balance.consistent);
+
+    // a balance changed outside of the journal is reported as inconsistent
+    service
+        .execute_sql(&format!(
+            "UPDATE Holding SET balance = balance + 1 WHERE account_id = {}",
+            user1
+        ))
+        .await?;
+    let balance = service.query_ledger_balance(user1).await.unwrap().remove(0);
+    assert_eq!(balance.balance, 801);
+    assert_eq!(balance.ledger_balance, 800);
+    assert!(!balance.consistent);
+
+    // every journal entry is balanced, so postings across the ledger sum up to zero
+    assert_eq!(
+        service
+            .execute_sql("SELECT 1 FROM Posting HAVING SUM(amount) = 0")
+            .await?,
+        1
+    );
+
+    // an unbalanced journal entry is rejected when its transaction commits
+    assert!(service
+        .execute_sql(
+            "WITH j AS (INSERT INTO Journal(kind) VALUES ('manual') RETURNING number)
+            INSERT INTO Posting(journal_number, system_account, asset, amount)
+            SELECT number, 'funding', 'TOCOS', 5 FROM j"
+        )
+        .await
+        .is_err());
+
+    let error_response = service.query_ledger_balance(0).await.err().unwrap();
+    assert_eq!(
+        error_response.to_string(),
+        error_codes::SENDER_DOES_NOT_EXIST.to_string()
+    );
+
+    Ok(())
+}
+
+// Simulate overriding the balance policy of an account, e.g. for a customer tier.
+#[tokio::test]
+async fn test_account_balance_policy() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_account_balance_policy").await;
+
+    // create users 1 and 2
+    let user1 = service.create_account(100).await?;
+    let user2 = service.create_account(100).await?;
+    let policy_path = format!("/admin/users/{}/policy", user1);
+    let transfer = |from: u64, to: u64, amount: u64| {
+        format!(
+            r#"{{"from_id": {}, "to_id": {}, "amount": {}}}"#,
+            from, to, amount
+        )
+    };
+
+    // the default policy keeps a minimum balance of 5 and reports what was needed
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/transactions",
+            Some(&transfer(user1, user2, 96)),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::NOT_ENOUGH_BALANCE);
+    assert_eq!(body["details"]["required_balance"], "101");
+    assert!(body["message"].as_str().unwrap().contains("101 TOCOS"));
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::GET, &policy_path, None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["min_balance"], "5");
+    assert_eq!(body["overdraft_limit"], "0");
+    assert!(body["overrides"]["min_balance"].is_null());
+
+    // allow user 1 to overdraw by 50, without a minimum balance
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::PUT,
+            &policy_path,
+            Some(r#"{"min_balance": 0, "overdraft_limit": "50"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["min_balance"], "0");
+    assert_eq!(body["overdraft_limit"], "50");
+
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/transactions",
+            Some(&transfer(user1, user2, 140)),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["from_balance_after"], "-40");
+
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/transactions",
+            Some(&transfer(user1, user2, 20)),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["details"]["required_balance"], "-30");
+
+    // other accounts keep the default policy
+    let response = service.submit_transaction(user2, user1, 236).await;
+    assert_eq!(
+        response.unwrap_err().to_string(),
+        error_codes::NOT_ENOUGH_BALANCE
+    );
+
+    // leaving out the overrides reverts to the default policy
+    let (status, body) = service
+        .send_raw(reqwest::Method::PUT, &policy_path, Some("{}"))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["min_balance"], "5");
+    assert!(body["overrides"]["overdraft_limit"].is_null());
+
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::PUT,
+            &policy_path,
+            Some(r#"{"min_balance": "-1"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::GET, "/admin/users/0/policy", None)
+        .await?;
+    assert_eq!(status.as_u16(), 404);
+    assert_eq!(body["code"], error_codes::SENDER_DOES_NOT_EXIST);
+
+    Ok(())
+}
+
+// Simulate freezing, unfreezing and closing accounts.
+#[tokio::test]
+async fn test_account_lifecycle() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_account_lifecycle").await;
+
+    // create users 1, 2 and 3
+    let user1 = service.create_account(100).await?;
+    let user2 = service.create_account(100).await?;
+    let user3 = service.create_account(50).await?;
+    let path = |id: u64, action: &str| format!("/admin/users/{}/{}", id, action);
+
+    let change = r#"{"actor": "ops", "reason": "suspected compromise"}"#;
+
+    // funds can neither leave nor reach a frozen account
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path(user1, "freeze"), Some(change))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["from_status"], "active");
+    assert_eq!(body["to_status"], "frozen");
+
+    for (from, to) in [(user1, user2), (user2, user1)] {
+        let body = format!(r#"{{"from_id": {}, "to_id": {}, "amount": 10}}"#, from, to);
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 422);
+        assert_eq!(body["code"], error_codes::ACCOUNT_FROZEN);
+        assert_eq!(body["details"]["account_id"], user1);
+    }
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path(user1, "freeze"), Some(change))
+        .await?;
+    assert_eq!(status.as_u16(), 409);
+    assert_eq!(body["code"], error_codes::INVALID_STATUS_TRANSITION);
+
+    let (status, _) = service
+        .send_raw(
+            reqwest::Method::POST,
+            &path(user1, "unfreeze"),
+            Some(change),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert!(service.submit_transaction(user1, user2, 10).await.is_ok());
+
+    // accounts are only closed once they hold nothing
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path(user2, "close"), Some(change))
+        .await?;
+    assert_eq!(status.as_u16(), 409);
+    assert_eq!(body["code"], error_codes::ACCOUNT_NOT_EMPTY);
+
+    let sweep = |to: u64| {
+        format!(
+            r#"{{"actor": "ops", "reason": "customer request", "sweep_to": {}}}"#,
+            to
+        )
+    };
+    for (action, body) in [
+        ("close", sweep(user2)),
+        ("freeze", sweep(user3)),
+        (
+            "close",
+            r#"{"actor": " ", "reason": "customer request"}"#.to_string(),
+        ),
+    ] {
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, &path(user2, action), Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 422);
+        assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+    }
+
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            &path(user2, "close"),
+            Some(&sweep(user3)),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["swept_to"], user3);
+
+    assert_eq!(service.query_user(user2).await.unwrap().balance, 0);
+    assert_eq!(service.query_user(user3).await.unwrap().balance, 50 + 110);
+    assert!(service.query_ledger_balance(user3).await.unwrap()[0].consistent);
+
+    let response = service.submit_transaction(user3, user2, 10).await;
+    assert_eq!(
+        response.unwrap_err().to_string(),
+        error_codes::ACCOUNT_CLOSED
+    );
+
+    let (status, _) = service
+        .send_raw(
+            reqwest::Method::POST,
+            &path(user2, "unfreeze"),
+            Some(change),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 409);
+
+    // every change is recorded along with who made it and why
+    let (status, body) = service
+        .send_raw(reqwest::Method::GET, &path(user1, "status"), None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["status"], "active");
+    let changes = body["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0]["actor"], "ops");
+    assert_eq!(changes[0]["reason"], "suspected compromise");
+    assert_eq!(changes[1]["to_status"], "active");
 
     Ok(())
 }
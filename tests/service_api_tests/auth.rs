@@ -0,0 +1,205 @@
+use anyhow::Result;
+
+use crate::config::constants::error_codes;
+use crate::config::constants::service::ALLOWED_ORIGIN;
+use crate::config::service::Service;
+
+// Simulate API keys issued and revoked from the command line, and requests authenticated with them.
+#[tokio::test]
+async fn test_api_key_authentication() -> Result<()> {
+    // start service binary, which issues the key requests are sent with by default
+    let service = Service::start("test_api_key_authentication").await;
+
+    let (status, _) = service
+        .send_raw(reqwest::Method::GET, "/assets", None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+
+    // requests without a key, or with an unknown one, are rejected
+    for (api_key, path) in [
+        (None, "/assets"),
+        (Some("tocos_unknown"), "/assets"),
+        (Some(""), "/transactions"),
+        (None, "/admin/interest-products"),
+    ] {
+        let (status, body) = service
+            .send_raw_with_key(api_key, reqwest::Method::GET, path, None)
+            .await?;
+        assert_eq!(status.as_u16(), 401);
+        assert_eq!(body["code"], error_codes::UNAUTHORIZED);
+        assert_eq!(body["numeric_code"], 1038);
+        assert!(body["request_id"].is_string());
+    }
+
+    // whereas stray requests are told the path or method does not exist, key or not
+    for (method, path, status, code) in [
+        (
+            reqwest::Method::GET,
+            "/no/such/path",
+            404,
+            error_codes::ROUTE_NOT_FOUND,
+        ),
+        (
+            reqwest::Method::DELETE,
+            "/users",
+            405,
+            error_codes::METHOD_NOT_ALLOWED,
+        ),
+    ] {
+        let (response_status, body) = service.send_raw_with_key(None, method, path, None).await?;
+        assert_eq!(response_status.as_u16(), status, "{}", path);
+        assert_eq!(body["code"], code);
+    }
+
+    // keys are printed once on creation, and only their prefix afterwards
+    let ops = service.run_command(&["api-keys", "create", "--name", "ops"])?;
+    let key = ops["key"].as_str().unwrap();
+    let prefix = ops["prefix"].as_str().unwrap();
+    assert_eq!(ops["name"], "ops");
+    assert!(prefix.starts_with("tocos_"));
+    assert!(key.starts_with(prefix) && key.len() > prefix.len());
+    assert!(ops["revoked_at"].is_null());
+    assert_eq!(ops["admin"], false);
+
+    let (status, _) = service
+        .send_raw_with_key(Some(key), reqwest::Method::GET, "/assets", None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+
+    let keys = service.run_command(&["api-keys", "list"])?;
+    let keys = keys.as_array().unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[1]["id"], ops["id"]);
+    assert!(keys.iter().all(|api_key| api_key.get("key").is_none()));
+
+    // revoked keys are rejected from then on, while other keys keep working
+    let id = ops["id"].to_string();
+    let revoked = service.run_command(&["api-keys", "revoke", &id])?;
+    assert!(revoked["revoked_at"].is_string());
+
+    let (status, body) = service
+        .send_raw_with_key(Some(key), reqwest::Method::GET, "/assets", None)
+        .await?;
+    assert_eq!(status.as_u16(), 401);
+    assert_eq!(body["code"], error_codes::UNAUTHORIZED);
+
+    let (status, _) = service
+        .send_raw(reqwest::Method::GET, "/assets", None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+
+    // revoking a key again keeps the time it was revoked at
+    let again = service.run_command(&["api-keys", "revoke", &id])?;
+    assert_eq!(again["revoked_at"], revoked["revoked_at"]);
+
+    // keys that do not exist cannot be revoked, nor can keys be created without a name
+    assert!(service.run_command(&["api-keys", "revoke", "0"]).is_err());
+    let error = service
+        .run_command(&["api-keys", "create", "--name", " "])
+        .unwrap_err();
+    assert!(error.to_string().contains(error_codes::VALIDATION_FAILED));
+
+    // commands refuse to drop the tables of the live service, and leave its schema alone
+    assert!(service
+        .run_command(&["--start-anew", "api-keys", "list"])
+        .is_err());
+    assert_eq!(
+        service
+            .run_command(&["api-keys", "list"])?
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let accrue_interest = "SELECT 1 FROM pg_proc WHERE proname = 'accrueinterest'";
+    service.execute_sql("DROP PROCEDURE AccrueInterest").await?;
+    service.run_command(&["api-keys", "list"])?;
+    assert_eq!(service.execute_sql(accrue_interest).await?, 0);
+
+    let (status, _) = service
+        .send_raw(reqwest::Method::GET, "/assets", None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+
+    Ok(())
+}
+
+// Simulate client keys calling admin endpoints, which only admin keys reach.
+#[tokio::test]
+async fn test_admin_api_keys() -> Result<()> {
+    // start service binary, which issues an admin key for requests sent by default
+    let service = Service::start("test_admin_api_keys").await;
+
+    let client = service.run_command(&["api-keys", "create", "--name", "client"])?;
+    let key = client["key"].as_str();
+    assert_eq!(client["admin"], false);
+
+    let asset = r#"{ "symbol": "USDC", "decimals": 6, "name": "USD Coin" }"#;
+    for (method, path, body) in [
+        (reqwest::Method::GET, "/admin/users/1/policy", None),
+        (reqwest::Method::PUT, "/admin/users/1/policy", Some("{}")),
+        (reqwest::Method::POST, "/admin/users/1/freeze", Some("{}")),
+        (reqwest::Method::GET, "/admin/interest-products", None),
+        (reqwest::Method::POST, "/admin/interest/accrue", Some("{}")),
+        (reqwest::Method::POST, "/assets", Some(asset)),
+    ] {
+        let (status, body) = service.send_raw_with_key(key, method, path, body).await?;
+        assert_eq!(status.as_u16(), 403, "{}", path);
+        assert_eq!(body["code"], error_codes::FORBIDDEN);
+        assert_eq!(body["numeric_code"], 1039);
+    }
+
+    // client keys keep reaching client endpoints, and paths that do not exist are not found
+    for path in ["/assets", "/users"] {
+        let (status, _) = service
+            .send_raw_with_key(key, reqwest::Method::GET, path, None)
+            .await?;
+        assert_eq!(status.as_u16(), 200, "{}", path);
+    }
+
+    let (status, body) = service
+        .send_raw_with_key(key, reqwest::Method::GET, "/admin/no/such/path", None)
+        .await?;
+    assert_eq!(status.as_u16(), 404);
+    assert_eq!(body["code"], error_codes::ROUTE_NOT_FOUND);
+
+    // admin keys reach both
+    let admin = service.run_command(&["api-keys", "create", "--name", "ops", "--admin"])?;
+    assert_eq!(admin["admin"], true);
+    let key = admin["key"].as_str();
+
+    let (status, _) = service
+        .send_raw_with_key(key, reqwest::Method::POST, "/assets", Some(asset))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+
+    for path in ["/admin/interest-products", "/assets"] {
+        let (status, _) = service
+            .send_raw_with_key(key, reqwest::Method::GET, path, None)
+            .await?;
+        assert_eq!(status.as_u16(), 200, "{}", path);
+    }
+
+    Ok(())
+}
+
+// Simulate browsers calling the service from the origin allowed and from others.
+#[tokio::test]
+async fn test_allowed_origins() -> Result<()> {
+    // start service binary, which allows `ALLOWED_ORIGIN` alone
+    let service = Service::start("test_allowed_origins").await;
+
+    let (status, allowed_origin) = service.send_preflight("/users", ALLOWED_ORIGIN).await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(allowed_origin.as_deref(), Some(ALLOWED_ORIGIN));
+
+    // other origins are refused rather than allowed by default
+    for origin in ["http://localhost:3001", "https://example.com"] {
+        let (status, allowed_origin) = service.send_preflight("/users", origin).await?;
+        assert_eq!(status.as_u16(), 403);
+        assert!(allowed_origin.is_none());
+    }
+
+    Ok(())
+}
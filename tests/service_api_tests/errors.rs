@@ -0,0 +1,105 @@
+use anyhow::Result;
+
+use crate::config::constants::error_codes;
+use crate::config::service::Service;
+use crate::utilities::ErrorResponse;
+
+// Simulate errors raised by handlers and by warp itself, checking the structured body of each.
+#[tokio::test]
+async fn test_structured_error_responses() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_structured_error_responses").await;
+
+    // create users 1 and 2, where 1 cannot afford anything
+    let user1 = service
+        .create_account_with(serde_json::json!({ "external_ref": "user-1", "balance": 5 }))
+        .await?
+        .id;
+    let user2 = service.create_account(10000).await?;
+    let transfer = format!(
+        r#"{{"from_id": {}, "to_id": {}, "amount": 10}}"#,
+        user1, user2
+    );
+
+    let cases = [
+        (
+            reqwest::Method::GET,
+            "/users/3",
+            None,
+            404,
+            error_codes::SENDER_DOES_NOT_EXIST,
+        ),
+        (
+            reqwest::Method::POST,
+            "/users",
+            Some(r#"{"external_ref": "user-1", "balance": 10}"#),
+            409,
+            error_codes::ACCOUNT_EXISTS,
+        ),
+        (
+            reqwest::Method::POST,
+            "/transactions",
+            Some(transfer.as_str()),
+            422,
+            error_codes::NOT_ENOUGH_BALANCE,
+        ),
+        (
+            reqwest::Method::POST,
+            "/transactions",
+            Some(r#"{"from_id": 1, "to_id": "#),
+            400,
+            error_codes::INVALID_BODY,
+        ),
+        (
+            reqwest::Method::DELETE,
+            "/users",
+            None,
+            405,
+            error_codes::METHOD_NOT_ALLOWED,
+        ),
+        (
+            reqwest::Method::GET,
+            "/accounts",
+            None,
+            404,
+            error_codes::ROUTE_NOT_FOUND,
+        ),
+    ];
+
+    for (method, path, body, status, code) in cases {
+        let (response_status, response_body) = service.send_raw(method, path, body).await?;
+        assert_eq!(response_status.as_u16(), status, "{}", path);
+
+        let error: ErrorResponse = serde_json::from_value(response_body)?;
+        assert_eq!(error.code, code);
+        assert!(error.numeric_code > 0);
+        assert!(!error.message.is_empty());
+        assert!(error.details.is_object());
+        assert!(!error.request_id.is_empty());
+    }
+
+    Ok(())
+}
+
+// An unreachable database must surface as a retryable outage, not as a domain error such as
+// `ACCOUNT_EXISTS`.
+#[tokio::test]
+async fn test_database_outage_is_not_reported_as_domain_error() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_database_outage_is_not_reported_as_domain_error").await;
+
+    assert!(service.create_account(100).await.is_ok());
+
+    service.cut_off_database().await?;
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/users", Some(r#"{"balance": 10}"#))
+        .await?;
+    assert_eq!(status.as_u16(), 503);
+
+    let error: ErrorResponse = serde_json::from_value(body)?;
+    assert_eq!(error.code, error_codes::DATABASE_UNAVAILABLE);
+    assert_eq!(error.details["retryable"], true);
+
+    Ok(())
+}
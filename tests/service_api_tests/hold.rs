@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::time::Duration;
+
+use crate::config::constants::error_codes;
+use crate::config::service::Service;
+
+// Simulate holds reserving funds, captured in part and voided.
+#[tokio::test]
+async fn test_hold_capture_and_void() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_hold_capture_and_void").await;
+
+    // create users 1 and 2, and put 600 of user 1 on hold for user 2
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(100).await?;
+    let hold = service.place_hold(user1, user2, 600, None).await?;
+    assert_eq!(hold.status, "pending");
+    assert_eq!(hold.amount, 600);
+    assert!(hold.transaction_id.is_none());
+
+    // funds on hold are no longer available, yet stay on the ledger
+    let user = service.query_user(user1).await?;
+    assert_eq!((user.ledger, user.available), (1000, 400));
+    assert_eq!(user.balance, user.ledger);
+
+    for error_response in [
+        service.submit_transaction(user1, user2, 400).await.err(),
+        service.place_hold(user1, user2, 400, None).await.err(),
+    ] {
+        assert_eq!(
+            error_response.unwrap().to_string(),
+            error_codes::NOT_ENOUGH_BALANCE
+        );
+    }
+
+    // captures cannot exceed the hold
+    let path = format!("/holds/{}/capture", hold.id);
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path, Some(r#"{"amount": 700}"#))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::CAPTURE_EXCEEDS_HOLD);
+    assert_eq!(body["details"]["held_amount"], "600");
+
+    // capturing 250 transfers it and releases the rest
+    let captured = service.capture_hold(hold.id, Some(250)).await?;
+    assert_eq!(captured.status, "captured");
+    assert_eq!(captured.captured_amount.as_deref(), Some("250"));
+    assert!(captured.settled_at.is_some());
+
+    let tx = service
+        .query_transaction(captured.transaction_id.unwrap())
+        .await?;
+    assert_eq!((tx.from_id, tx.to_id, tx.amount), (user1, user2, 250));
+
+    let user = service.query_user(user1).await?;
+    assert_eq!((user.ledger, user.available), (750, 750));
+    assert_eq!(service.query_user(user2).await?.ledger, 350);
+
+    // settled holds cannot be settled again
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path, Some("{}"))
+        .await?;
+    assert_eq!(status.as_u16(), 409);
+    assert_eq!(body["code"], error_codes::HOLD_NOT_PENDING);
+    assert_eq!(body["details"]["status"], "captured");
+
+    // voiding releases the funds without moving them
+    let hold = service.place_hold(user1, user2, 100, None).await?;
+    assert_eq!(service.query_user(user1).await?.available, 650);
+
+    let voided = service.void_hold(hold.id).await?;
+    assert_eq!(voided.status, "voided");
+    assert!(voided.transaction_id.is_none());
+    assert_eq!(service.query_hold(hold.id).await?.status, "voided");
+
+    let user = service.query_user(user1).await?;
+    assert_eq!((user.ledger, user.available), (750, 750));
+
+    let error_response = service.capture_hold(hold.id, None).await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::HOLD_NOT_PENDING
+    );
+
+    // holds that do not exist, and holds that would expire at once
+    for error_response in [
+        service.query_hold(0).await.err(),
+        service.void_hold(0).await.err(),
+    ] {
+        assert_eq!(
+            error_response.unwrap().to_string(),
+            error_codes::HOLD_DOES_NOT_EXIST
+        );
+    }
+
+    let body = format!(
+        r#"{{"from_id": {}, "to_id": {}, "amount": 1, "ttl_secs": 0}}"#,
+        user1, user2
+    );
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/holds", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+
+    // the cached balance still matches the journal
+    assert!(service.query_ledger_balance(user1).await?[0].consistent);
+
+    Ok(())
+}
+
+// Simulate the service releasing holds once they expire.
+#[tokio::test]
+async fn test_hold_expiry() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_hold_expiry").await;
+
+    // create users 1 and 2, and put 500 of user 1 on hold for a second
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(100).await?;
+    let hold = service.place_hold(user1, user2, 500, Some(1)).await?;
+    assert_eq!(service.query_user(user1).await?.available, 500);
+
+    // the service releases the funds shortly after expiry
+    let mut status = hold.status;
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        status = service.query_hold(hold.id).await?.status;
+        if status != "pending" {
+            break;
+        }
+    }
+    assert_eq!(status, "expired");
+
+    let user = service.query_user(user1).await?;
+    assert_eq!((user.ledger, user.available), (1000, 1000));
+
+    let path = format!("/holds/{}/capture", hold.id);
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path, Some("{}"))
+        .await?;
+    assert_eq!(status.as_u16(), 409);
+    assert_eq!(body["details"]["status"], "expired");
+
+    Ok(())
+}
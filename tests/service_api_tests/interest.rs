@@ -0,0 +1,395 @@
+use anyhow::Result;
+
+use crate::config::constants::error_codes;
+use crate::config::constants::service::INTEREST_ACCOUNT_REF;
+use crate::config::service::Service;
+
+// Simulate interest accrued daily on end-of-day balances and paid monthly, compounded either way.
+#[tokio::test]
+async fn test_interest_accrual() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_interest_accrual").await;
+
+    // define products paying 3.65 percent a year on SAVE, i.e. 0.01 percent a day
+    service.create_asset("SAVE", 2).await?;
+    for (code, compounding) in [("monthly-savings", "monthly"), ("daily-savings", "daily")] {
+        let product = serde_json::json!({
+            "code": code,
+            "asset": "SAVE",
+            "annual_rate": "3.65",
+            "compounding": compounding,
+            "accrual_basis": "actual_365",
+        });
+        let (status, body) = service
+            .send_raw(
+                reqwest::Method::POST,
+                "/admin/interest-products",
+                Some(&product.to_string()),
+            )
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(body, product);
+    }
+
+    let (_, body) = service
+        .send_raw(reqwest::Method::GET, "/admin/interest-products", None)
+        .await?;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    // users 1 and 2 hold 10000 SAVE each, earning interest under either product
+    let user1 = service.create_account_with_asset("SAVE", 10000).await?;
+    let user2 = service.create_account_with_asset("SAVE", 10000).await?;
+    let path = |id: u64| format!("/admin/users/{}/interest", id);
+    for (user, product) in [(user1, "monthly-savings"), (user2, "daily-savings")] {
+        let body = format!(r#"{{"product": "{}"}}"#, product);
+        let (status, body) = service
+            .send_raw(reqwest::Method::PUT, &path(user), Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(body["product"], product);
+        assert!(body["since"].is_string());
+        assert!(body["accrued_through"].is_null());
+    }
+
+    // both have held their balance, and earned interest, since the start of 2025
+    service
+        .execute_sql(
+            "UPDATE Journal SET created_at = '2024-12-31T12:00:00Z' WHERE kind = 'opening'",
+        )
+        .await?;
+    service
+        .execute_sql(
+            "UPDATE Account SET interest_since = '2025-01-01' WHERE interest_product IS NOT NULL",
+        )
+        .await?;
+
+    let accrue = |through: &str| format!(r#"{{"through": "{}"}}"#, through);
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest/accrue",
+            Some(&accrue("2025-01-03")),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(
+        body,
+        serde_json::json!({ "through": "2025-01-03", "accruals": 6, "payments": 0 })
+    );
+
+    // interest accrued daily earns interest itself from the next day on
+    for (user, accrued) in [(user1, "3"), (user2, "3.00030001")] {
+        let (_, body) = service
+            .send_raw(reqwest::Method::GET, &path(user), None)
+            .await?;
+        assert_eq!(body["accrued"], accrued);
+        assert_eq!(body["accrued_through"], "2025-01-03");
+        assert_eq!(body["since"], "2025-01-01");
+    }
+
+    // January cannot be paid until the interest account exists, yet the days accrued are kept
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest/accrue",
+            Some(&accrue("2025-01-31")),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 503);
+    assert_eq!(body["code"], error_codes::INTEREST_ACCOUNT_MISSING);
+
+    let interest_account = service
+        .create_account_with(serde_json::json!({
+            "external_ref": INTEREST_ACCOUNT_REF,
+            "asset": "SAVE",
+            "balance": 0,
+        }))
+        .await?
+        .id;
+
+    // running a day again pays nothing twice
+    for (accruals, payments) in [(0, 2), (0, 0)] {
+        let (status, body) = service
+            .send_raw(
+                reqwest::Method::POST,
+                "/admin/interest/accrue",
+                Some(&accrue("2025-01-31")),
+            )
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(body["accruals"], accruals);
+        assert_eq!(body["payments"], payments);
+    }
+
+    // payments are rounded down to the decimals of the asset
+    for (user, accrued, paid, balance) in [
+        (user1, "31", "31", "10031"),
+        (user2, "31.04654495", "31.04", "10031.04"),
+    ] {
+        let (_, body) = service
+            .send_raw(reqwest::Method::GET, &path(user), None)
+            .await?;
+        assert_eq!(body["accrued"], "0");
+        let payment = &body["payments"][0];
+        assert_eq!(payment["period"], "2025-01-01");
+        assert_eq!(payment["asset"], "SAVE");
+        assert_eq!(payment["accrued"], accrued);
+        assert_eq!(payment["amount"], paid);
+
+        let tx_path = format!("/transactions/{}", payment["transaction_id"]);
+        let (_, tx) = service
+            .send_raw(reqwest::Method::GET, &tx_path, None)
+            .await?;
+        assert_eq!(tx["kind"], "interest");
+        assert_eq!(
+            (tx["from_id"].as_u64(), tx["to_id"].as_u64()),
+            (Some(interest_account), Some(user))
+        );
+        assert_eq!(tx["amount"], paid);
+        assert_eq!(tx["to_balance_after"], balance);
+
+        // the cached balance still matches the journal
+        let (_, balances) = service
+            .send_raw(
+                reqwest::Method::GET,
+                &format!("/users/{}/balance", user),
+                None,
+            )
+            .await?;
+        assert!(balances
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|balance| balance["consistent"] == true));
+    }
+
+    // paid interest earns interest from the next month on
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest/accrue",
+            Some(&accrue("2025-02-01")),
+        )
+        .await?;
+    assert_eq!(body["accruals"], 2);
+
+    for (user, accrued) in [(user1, "1.0031"), (user2, "1.003104")] {
+        let (_, body) = service
+            .send_raw(reqwest::Method::GET, &path(user), None)
+            .await?;
+        assert_eq!(body["accrued"], accrued);
+    }
+
+    // detached accounts accrue the days they are still owed up to yesterday, 1.0031 a day as
+    // February is not paid yet, then stop accruing and are still paid what they have accrued
+    let yesterday = chrono::Utc::now().date_naive() - chrono::Duration::days(1);
+    let owed_days = (yesterday - chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap()).num_days();
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::PUT,
+            &path(user1),
+            Some(r#"{"product": null}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert!(body["product"].is_null());
+    assert!(body["since"].is_null());
+    assert_eq!(body["accrued_through"], yesterday.to_string());
+    assert_eq!(body["accrued"], decimal(10031 * (owed_days + 1), 4));
+
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest/accrue",
+            Some(&accrue("2025-02-28")),
+        )
+        .await?;
+    assert_eq!(body["accruals"], 27);
+    assert_eq!(body["payments"], 2);
+
+    let (_, body) = service
+        .send_raw(reqwest::Method::GET, &path(user1), None)
+        .await?;
+    assert_eq!(body["accrued_through"], yesterday.to_string());
+    assert_eq!(body["payments"][0]["period"], "2025-02-01");
+    assert_eq!(body["payments"][0]["amount"], "28.08");
+    assert_eq!(body["payments"].as_array().unwrap().len(), 2);
+
+    Ok(())
+}
+
+// Simulate an account switching products, which accrues the days it is owed under the former first.
+#[tokio::test]
+async fn test_interest_product_switch() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_interest_product_switch").await;
+
+    // products paying 0.01 and 0.02 percent a day on SAVE
+    service.create_asset("SAVE", 2).await?;
+    for (code, annual_rate) in [("savings", "3.65"), ("high-yield", "7.3")] {
+        let product = serde_json::json!({
+            "code": code,
+            "asset": "SAVE",
+            "annual_rate": annual_rate,
+            "compounding": "monthly",
+            "accrual_basis": "actual_365",
+        });
+        let (status, _) = service
+            .send_raw(
+                reqwest::Method::POST,
+                "/admin/interest-products",
+                Some(&product.to_string()),
+            )
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+    }
+
+    // the user has held 10000 SAVE under the first product for the last 3 days, none accrued yet
+    let user = service.create_account_with_asset("SAVE", 10000).await?;
+    let path = format!("/admin/users/{}/interest", user);
+    let (status, _) = service
+        .send_raw(
+            reqwest::Method::PUT,
+            &path,
+            Some(r#"{"product": "savings"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+
+    let today = chrono::Utc::now().date_naive();
+    service
+        .execute_sql(&format!(
+            "UPDATE Journal SET created_at = '{}' WHERE kind = 'opening'",
+            today - chrono::Duration::days(4)
+        ))
+        .await?;
+    service
+        .execute_sql(&format!(
+            "UPDATE Account SET interest_since = '{}' WHERE id = {}",
+            today - chrono::Duration::days(3),
+            user
+        ))
+        .await?;
+
+    // switching accrues those days under the first product, and earns under the second from today
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::PUT,
+            &path,
+            Some(r#"{"product": "high-yield"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["product"], "high-yield");
+    assert_eq!(body["since"], today.to_string());
+    assert_eq!(
+        body["accrued_through"],
+        (today - chrono::Duration::days(1)).to_string()
+    );
+    assert_eq!(body["accrued"], "3");
+
+    // the days accrued are not accrued again
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/admin/interest/accrue", Some("{}"))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["accruals"], 0);
+
+    Ok(())
+}
+
+// Simulate interest products and runs rejected up front.
+#[tokio::test]
+async fn test_interest_validation() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_interest_validation").await;
+
+    let product = r#"{"code": "savings", "annual_rate": "2", "compounding": "monthly", "accrual_basis": "actual_actual"}"#;
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest-products",
+            Some(product),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["asset"], "TOCOS");
+
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest-products",
+            Some(product),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 409);
+    assert_eq!(body["code"], error_codes::INTEREST_PRODUCT_EXISTS);
+
+    let product = r#"{"code": "Savings!", "annual_rate": "101", "compounding": "daily", "accrual_basis": "actual_360"}"#;
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest-products",
+            Some(product),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+    assert_eq!(body["details"]["fields"][0]["field"], "code");
+    assert_eq!(body["details"]["fields"][1]["field"], "annual_rate");
+
+    let product = r#"{"code": "other", "asset": "NOPE", "annual_rate": "1", "compounding": "daily", "accrual_basis": "actual_360"}"#;
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/admin/interest-products",
+            Some(product),
+        )
+        .await?;
+    assert_eq!(body["code"], error_codes::ASSET_DOES_NOT_EXIST);
+
+    // accounts can only be attached to products that exist
+    let user = service.create_account(100).await?;
+    let path = format!("/admin/users/{}/interest", user);
+    let (status, body) = service
+        .send_raw(reqwest::Method::PUT, &path, Some(r#"{"product": "other"}"#))
+        .await?;
+    assert_eq!(status.as_u16(), 404);
+    assert_eq!(body["code"], error_codes::INTEREST_PRODUCT_DOES_NOT_EXIST);
+
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::PUT,
+            "/admin/users/0/interest",
+            Some(r#"{"product": "savings"}"#),
+        )
+        .await?;
+    assert_eq!(body["code"], error_codes::SENDER_DOES_NOT_EXIST);
+
+    // days are only accrued once they are over
+    let today = chrono::Utc::now().date_naive();
+    let body = format!(r#"{{"through": "{}"}}"#, today);
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/admin/interest/accrue", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["details"]["fields"][0]["field"], "through");
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/admin/interest/accrue", Some("{}"))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["accruals"], 0);
+
+    Ok(())
+}
+
+// Formats `units` of 10^-`scale` as the service does, without trailing zeros.
+fn decimal(units: i64, scale: u32) -> String {
+    let factor = 10i64.pow(scale);
+    let fraction = format!("{:0width$}", units % factor, width = scale as usize);
+    match fraction.trim_end_matches('0') {
+        "" => (units / factor).to_string(),
+        fraction => format!("{}.{}", units / factor, fraction),
+    }
+}
@@ -1,3 +1,15 @@
 mod transaction;
 
 mod account;
+
+mod hold;
+
+mod schedule;
+
+mod interest;
+
+mod auth;
+
+mod errors;
+
+mod upgrade;
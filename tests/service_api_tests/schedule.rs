@@ -0,0 +1,404 @@
+use anyhow::Result;
+use std::time::Duration;
+
+use crate::config::constants::error_codes;
+use crate::config::constants::service::{self, SCHEDULE_MAX_RETRIES};
+use crate::config::service::Service;
+use crate::utilities::Schedule;
+
+// Simulate one-off and recurring schedules, run by the service as they come due.
+#[tokio::test]
+async fn test_schedule_lifecycle() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_schedule_lifecycle").await;
+
+    // create users 1 and 2, and schedule a transfer of 100 from user 1 to user 2 right away
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(100).await?;
+    let schedule = service
+        .create_schedule(serde_json::json!({ "from_id": user1, "to_id": user2, "amount": 100 }))
+        .await?;
+    assert_eq!(schedule.status, "active");
+    assert!(schedule.recurrence.is_none());
+
+    // the service runs it shortly, completing the schedule
+    let one_off = wait_for(&service, schedule.id, |s| s.status != "active").await?;
+    assert_eq!(one_off.status, "completed");
+    assert!(one_off.next_run_at.is_none());
+    assert_eq!(one_off.attempts, 0);
+
+    let tx = service
+        .query_transaction(one_off.last_transaction_id.unwrap())
+        .await?;
+    assert_eq!((tx.from_id, tx.to_id, tx.amount), (user1, user2, 100));
+    assert_eq!(tx.schedule_id, Some(one_off.id));
+    assert_eq!(service.query_user(user2).await?.balance, 200);
+
+    // recurrences set off from `start_at`, in UTC
+    for (recurrence, start_at, first_run_at) in [
+        (
+            "0 9 * * 1-5",
+            "2030-01-05T10:00:00Z",
+            "2030-01-07T09:00:00Z",
+        ),
+        (
+            "*/15 * * * *",
+            "2030-01-01T00:00:01Z",
+            "2030-01-01T00:15:00Z",
+        ),
+        (
+            "*/15 * * * *",
+            "2030-01-01T00:15:00Z",
+            "2030-01-01T00:15:00Z",
+        ),
+        ("@monthly", "2030-01-15T00:00:00Z", "2030-02-01T00:00:00Z"),
+        ("0 0 29 2 *", "2030-03-01T00:00:00Z", "2032-02-29T00:00:00Z"),
+    ] {
+        let schedule = service
+            .create_schedule(serde_json::json!({
+                "from_id": user1,
+                "to_id": user2,
+                "amount": 1,
+                "recurrence": recurrence,
+                "start_at": start_at,
+            }))
+            .await?;
+        assert_eq!(schedule.next_run_at.as_deref(), Some(first_run_at));
+    }
+
+    // a schedule recurring every minute runs once it comes due, and stays active
+    let recurring = service
+        .create_schedule(serde_json::json!({
+            "from_id": user1,
+            "to_id": user2,
+            "amount": 10,
+            "recurrence": "* * * * *",
+            "start_at": "2030-01-01T00:00:00Z",
+        }))
+        .await?;
+    let make_due = format!(
+        "UPDATE Schedule SET next_run_at = now() WHERE number = {}",
+        recurring.id
+    );
+    service.execute_sql(&make_due).await?;
+
+    let schedule = wait_for(&service, recurring.id, |s| s.last_transaction_id.is_some()).await?;
+    assert_eq!(schedule.status, "active");
+    assert!(schedule.next_run_at.unwrap().ends_with(":00Z"));
+    assert_eq!(service.query_user(user2).await?.balance, 210);
+
+    let txs = service
+        .query_transactions(&format!("schedule_id={}", recurring.id))
+        .await?;
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].id, schedule.last_transaction_id.unwrap());
+
+    // paused schedules do not run, even once due
+    let path = |id: u64, action: &str| format!("/schedules/{}/{}", id, action);
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path(recurring.id, "pause"), None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["status"], "paused");
+
+    service.execute_sql(&make_due).await?;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let paused = service.query_schedule(recurring.id).await?;
+    assert_eq!(paused.last_transaction_id, schedule.last_transaction_id);
+    assert_eq!(service.query_user(user2).await?.balance, 210);
+
+    // resuming skips the runs missed meanwhile
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path(recurring.id, "resume"), None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["status"], "active");
+    assert!(body["next_run_at"].as_str().unwrap().ends_with(":00Z"));
+
+    // cancelled schedules are done with
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path(recurring.id, "cancel"), None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["status"], "cancelled");
+    assert!(body["next_run_at"].is_null());
+
+    for (id, action, from_status) in [
+        (recurring.id, "resume", "cancelled"),
+        (recurring.id, "cancel", "cancelled"),
+        (one_off.id, "pause", "completed"),
+    ] {
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, &path(id, action), None)
+            .await?;
+        assert_eq!(status.as_u16(), 409);
+        assert_eq!(body["code"], error_codes::INVALID_STATUS_TRANSITION);
+        assert_eq!(body["details"]["status"], from_status);
+    }
+
+    // schedules are listed newest first, narrowed down by sender and status
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::GET,
+            &format!("/schedules?from_id={}&status=cancelled", user1),
+            None,
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+    assert_eq!(body["items"][0]["id"], recurring.id);
+
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::GET,
+            &format!("/schedules?from_id={}", user1),
+            None,
+        )
+        .await?;
+    assert_eq!(body["items"].as_array().unwrap().len(), 7);
+
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::GET,
+            &format!("/schedules?from_id={}", user2),
+            None,
+        )
+        .await?;
+    assert!(body["items"].as_array().unwrap().is_empty());
+
+    // the cached balance still matches the journal
+    assert!(service.query_ledger_balance(user1).await?[0].consistent);
+
+    Ok(())
+}
+
+// Simulate scheduled transfers short of balance, retried with backoff until they go through or fail.
+#[tokio::test]
+async fn test_schedule_retries() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_schedule_retries").await;
+
+    // create users 1, 2 and 3, and schedule more than user 1 has
+    let user1 = service.create_account(100).await?;
+    let user2 = service.create_account(10).await?;
+    let user3 = service.create_account(1000).await?;
+    let schedule = service
+        .create_schedule(serde_json::json!({ "from_id": user1, "to_id": user2, "amount": 500 }))
+        .await?;
+
+    // the run fails, and is retried later on
+    let retried = wait_for(&service, schedule.id, |s| s.attempts > 0).await?;
+    assert_eq!(retried.status, "active");
+    assert_eq!(
+        retried.last_error.as_deref(),
+        Some(error_codes::NOT_ENOUGH_BALANCE)
+    );
+    assert!(retried.last_transaction_id.is_none());
+
+    // once user 1 is funded, a retry goes through
+    service.submit_transaction(user3, user1, 600).await?;
+    let schedule = wait_for(&service, schedule.id, |s| s.status != "active").await?;
+    assert_eq!(schedule.status, "completed");
+    assert_eq!(schedule.attempts, 0);
+    assert!(schedule.last_transaction_id.is_some());
+    assert_eq!(service.query_user(user1).await?.balance, 200);
+    assert_eq!(service.query_user(user2).await?.balance, 510);
+
+    // a schedule that stays short of balance fails once out of retries
+    let schedule = service
+        .create_schedule(serde_json::json!({ "from_id": user3, "to_id": user2, "amount": 10000 }))
+        .await?;
+    let schedule = wait_for(&service, schedule.id, |s| s.status != "active").await?;
+    assert_eq!(schedule.status, "failed");
+    assert_eq!(schedule.attempts, SCHEDULE_MAX_RETRIES + 1);
+    assert_eq!(
+        schedule.last_error.as_deref(),
+        Some(error_codes::NOT_ENOUGH_BALANCE)
+    );
+    assert!(schedule.next_run_at.is_none());
+    assert_eq!(service.query_user(user3).await?.balance, 400);
+
+    // other failures are not retried
+    let schedule = service
+        .create_schedule(serde_json::json!({
+            "from_id": user1,
+            "to_id": user2,
+            "amount": 10,
+            "start_at": "2030-01-01T00:00:00Z",
+        }))
+        .await?;
+    let change = r#"{"actor": "ops", "reason": "suspected compromise"}"#;
+    let (status, _) = service
+        .send_raw(
+            reqwest::Method::POST,
+            &format!("/admin/users/{}/freeze", user2),
+            Some(change),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    service
+        .execute_sql(&format!(
+            "UPDATE Schedule SET next_run_at = now() WHERE number = {}",
+            schedule.id
+        ))
+        .await?;
+
+    let schedule = wait_for(&service, schedule.id, |s| s.status != "active").await?;
+    assert_eq!(schedule.status, "failed");
+    assert_eq!(schedule.attempts, 1);
+    assert_eq!(
+        schedule.last_error.as_deref(),
+        Some(error_codes::ACCOUNT_FROZEN)
+    );
+
+    Ok(())
+}
+
+// Simulate a schedule failing on the service's side, retried with backoff without holding up the others.
+#[tokio::test]
+async fn test_schedule_server_failure() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_schedule_server_failure").await;
+
+    // transfers of the fee asset fail on the service's side, as the fee account is not opened
+    assert!(service.create_asset(service::FEE_ASSET, 2).await.is_ok());
+    let user1 = service
+        .create_account_with_asset(service::FEE_ASSET, 1000)
+        .await?;
+    let user2 = service
+        .create_account_with_asset(service::FEE_ASSET, 0)
+        .await?;
+    let user3 = service.create_account(1000).await?;
+    let user4 = service.create_account(0).await?;
+
+    let mut schedules = Vec::new();
+    for (from_id, to_id, asset) in [(user1, user2, service::FEE_ASSET), (user3, user4, "TOCOS")] {
+        let schedule = service
+            .create_schedule(serde_json::json!({
+                "from_id": from_id,
+                "to_id": to_id,
+                "asset": asset,
+                "amount": 100,
+                "start_at": "2030-01-01T00:00:00Z",
+            }))
+            .await?;
+        schedules.push(schedule.id);
+    }
+
+    // the failing schedule comes due first, ahead of the healthy one
+    service
+        .execute_sql(&format!(
+            "UPDATE Schedule SET next_run_at = now() - interval '1 minute' WHERE number = {}",
+            schedules[0]
+        ))
+        .await?;
+    service
+        .execute_sql(&format!(
+            "UPDATE Schedule SET next_run_at = now() WHERE number = {}",
+            schedules[1]
+        ))
+        .await?;
+
+    let healthy = wait_for(&service, schedules[1], |s| s.status != "active").await?;
+    assert_eq!(healthy.status, "completed");
+    assert_eq!(service.query_user(user4).await?.balance, 100);
+
+    // the failing one is retried with backoff, rather than picked up again on every pass
+    let poisoned = service.query_schedule(schedules[0]).await?;
+    assert_eq!(poisoned.status, "active");
+    assert!(poisoned.attempts > 0);
+    assert_eq!(
+        poisoned.last_error.as_deref(),
+        Some(error_codes::FEE_ACCOUNT_MISSING)
+    );
+    assert_eq!(service.query_user(user2).await?.balance, 0);
+
+    // and fails once out of retries, like a one-off schedule short of balance
+    let poisoned = wait_for(&service, schedules[0], |s| s.status != "active").await?;
+    assert_eq!(poisoned.status, "failed");
+    assert_eq!(poisoned.attempts, SCHEDULE_MAX_RETRIES + 1);
+    assert!(poisoned.next_run_at.is_none());
+    assert_eq!(service.query_user(user2).await?.balance, 0);
+
+    Ok(())
+}
+
+// Simulate schedules rejected up front.
+#[tokio::test]
+async fn test_schedule_validation() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_schedule_validation").await;
+
+    // create users 1 and 2
+    let user1 = service.create_account(100).await?;
+    let user2 = service.create_account(100).await?;
+
+    for (recurrence, amount, rejected) in [
+        (Some("61 * * * *"), 1, vec!["recurrence"]),
+        (Some("0 0 * *"), 1, vec!["recurrence"]),
+        (Some("0 0 30 2 *"), 1, vec!["recurrence"]),
+        (Some("*/0 * * * *"), 0, vec!["amount", "recurrence"]),
+        (None, 0, vec!["amount"]),
+    ] {
+        let body = serde_json::json!({
+            "from_id": user1,
+            "to_id": user2,
+            "amount": amount,
+            "recurrence": recurrence,
+        });
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, "/schedules", Some(&body.to_string()))
+            .await?;
+        assert_eq!(status.as_u16(), 422);
+        assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+
+        let fields: Vec<&str> = body["details"]["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, rejected);
+    }
+
+    // parties must exist, up front
+    let body = format!(r#"{{"from_id": 0, "to_id": {}, "amount": 1}}"#, user2);
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/schedules", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 404);
+    assert_eq!(body["code"], error_codes::SENDER_DOES_NOT_EXIST);
+
+    // schedules that do not exist
+    let error_response = service.query_schedule(0).await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::SCHEDULE_DOES_NOT_EXIST
+    );
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/schedules/0/pause", None)
+        .await?;
+    assert_eq!(status.as_u16(), 404);
+    assert_eq!(body["code"], error_codes::SCHEDULE_DOES_NOT_EXIST);
+
+    Ok(())
+}
+
+// Poll a schedule until `done` holds of it, for up to 15 seconds.
+async fn wait_for(
+    service: &Service,
+    id: u64,
+    done: impl Fn(&Schedule) -> bool,
+) -> Result<Schedule> {
+    let mut schedule = service.query_schedule(id).await?;
+    for _ in 0..15 {
+        if done(&schedule) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        schedule = service.query_schedule(id).await?;
+    }
+
+    Ok(schedule)
+}
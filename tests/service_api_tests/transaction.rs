@@ -1,7 +1,8 @@
 use anyhow::Result;
 
-use crate::config::constants::service;
+use crate::config::constants::{error_codes, service};
 use crate::config::service::Service;
+use crate::utilities::TransactionRecord;
 
 // Simulate transaction success.
 #[tokio::test]
@@ -10,25 +11,23 @@ async fn test_submit_tx_success() -> Result<()> {
     let service = Service::start("test_submit_tx_success").await;
 
     // create user 1
-    let response = service.create_account(1, 10000).await;
-    assert!(response.is_ok());
+    let user1 = service.create_account(10000).await?;
 
     // create user 2
-    let response = service.create_account(2, 10000).await;
-    assert!(response.is_ok());
+    let user2 = service.create_account(10000).await?;
 
     // submit tx from 1 to 2
-    let response = service.submit_transaction(1, 2, 100).await;
+    let response = service.submit_transaction(user1, user2, 100).await;
     assert!(response.is_ok());
 
     // query balance from 1 and check if balance is deducted
-    let response = service.query_user(1).await;
+    let response = service.query_user(user1).await;
     assert!(response.is_ok());
     let user1 = response.unwrap();
     assert_eq!(user1.balance, 10000 - 100);
 
     // query balance from 2 and check if balance is added
-    let response = service.query_user(2).await;
+    let response = service.query_user(user2).await;
     assert!(response.is_ok());
     let user2 = response.unwrap();
     assert_eq!(user2.balance, 10000 + 100);
@@ -36,6 +35,184 @@ async fn test_submit_tx_success() -> Result<()> {
     Ok(())
 }
 
+// Simulate transaction success returning the created record.
+#[tokio::test]
+async fn test_submit_tx_returns_record() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_tx_returns_record").await;
+
+    // create users 1 and 2
+    let user1 = service.create_account(10000).await?;
+    let user2 = service.create_account(10000).await?;
+
+    // submit two txs and check that each is assigned its own id
+    let first = service.submit_transaction(user1, user2, 100).await.unwrap();
+    let second = service.submit_transaction(user2, user1, 50).await.unwrap();
+    assert_eq!(
+        (first.from_id, first.to_id, first.amount),
+        (user1, user2, 100)
+    );
+    assert_eq!(first.status, "completed");
+    assert!(second.id > first.id);
+
+    // the latest txs come first and carry the same fields
+    let txs = service.query_transactions("").await.unwrap();
+    assert_eq!(txs.len(), 2);
+    assert_eq!(txs[0].id, second.id);
+    assert_eq!(txs[1].id, first.id);
+    assert_eq!(txs[1].created_at, first.created_at);
+
+    Ok(())
+}
+
+// Simulate querying transactions with filters.
+#[tokio::test]
+async fn test_query_tx_with_filters() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_query_tx_with_filters").await;
+
+    // no txs yet gives an empty list rather than an error
+    let txs = service.query_transactions("").await.unwrap();
+    assert!(txs.is_empty());
+
+    // create users 1, 2 and 3
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(1000).await?;
+    let user3 = service.create_account(1000).await?;
+
+    let first = service.submit_transaction(user1, user2, 100).await.unwrap();
+    let second = service.submit_transaction(user1, user3, 200).await.unwrap();
+    let third = service.submit_transaction(user2, user3, 300).await.unwrap();
+
+    let ids = |txs: Vec<TransactionRecord>| txs.iter().map(|tx| tx.id).collect::<Vec<_>>();
+
+    // by sender and by recipient
+    let query = format!("from_id={}", user1);
+    let txs = service.query_transactions(&query).await.unwrap();
+    assert_eq!(ids(txs), vec![second.id, first.id]);
+    let query = format!("to_id={}", user3);
+    let txs = service.query_transactions(&query).await.unwrap();
+    assert_eq!(ids(txs), vec![third.id, second.id]);
+
+    // by amount range
+    let txs = service
+        .query_transactions("min_amount=150&max_amount=300")
+        .await
+        .unwrap();
+    assert_eq!(ids(txs), vec![third.id, second.id]);
+
+    // by created-at range, which includes its start and excludes its end
+    let query = format!(
+        "created_after={}&created_before={}",
+        urlencoding::encode(&second.created_at),
+        urlencoding::encode(&third.created_at)
+    );
+    let txs = service.query_transactions(&query).await.unwrap();
+    assert_eq!(ids(txs), vec![second.id]);
+
+    // no match
+    let query = format!("from_id={}", user3);
+    let txs = service.query_transactions(&query).await.unwrap();
+    assert!(txs.is_empty());
+
+    // an inverted range is rejected
+    let response = service
+        .query_transactions("min_amount=300&max_amount=100")
+        .await;
+    assert!(response.is_err());
+    let error_response = response.err().as_ref().unwrap().to_string();
+    assert_eq!(error_response, error_codes::INVALID_FILTER.to_string());
+
+    Ok(())
+}
+
+// Simulate transaction lookup by id.
+#[tokio::test]
+async fn test_query_tx_by_id_success() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_query_tx_by_id_success").await;
+
+    // create users 1 and 2
+    let user1 = service.create_account(10000).await?;
+    let user2 = service.create_account(500).await?;
+
+    // submit tx from 1 to 2
+    let submitted = service.submit_transaction(user1, user2, 100).await.unwrap();
+
+    // look the tx up and check balances of both parties right after it
+    let tx = service.query_transaction(submitted.id).await.unwrap();
+    assert_eq!((tx.from_id, tx.to_id, tx.amount), (user1, user2, 100));
+    assert_eq!(tx.from_balance_after, 10000 - 100);
+    assert_eq!(tx.to_balance_after, 500 + 100);
+    assert_eq!(tx.created_at, submitted.created_at);
+
+    Ok(())
+}
+
+// Simulate non existent transaction query failure.
+#[tokio::test]
+async fn test_query_tx_by_id_failure_non_existent() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_query_tx_by_id_failure_non_existent").await;
+
+    let response = service.query_transaction(1).await;
+    assert!(response.is_err());
+    let error_response = response.err().as_ref().unwrap().to_string();
+    assert_eq!(
+        error_response,
+        error_codes::TRANSACTION_DOES_NOT_EXIST.to_string()
+    );
+
+    Ok(())
+}
+
+// Simulate paging through the transaction history of an account.
+#[tokio::test]
+async fn test_query_account_history_pagination() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_query_account_history_pagination").await;
+
+    // create users 1, 2 and 3
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(1000).await?;
+    let user3 = service.create_account(1000).await?;
+
+    // 1 sends to 2, receives from 3, sends to 3; 2 and 3 trade without 1
+    assert!(service.submit_transaction(user1, user2, 100).await.is_ok());
+    assert!(service.submit_transaction(user3, user1, 50).await.is_ok());
+    assert!(service.submit_transaction(user2, user3, 10).await.is_ok());
+    assert!(service.submit_transaction(user1, user3, 25).await.is_ok());
+
+    // first page holds the two newest txs of 1
+    let page = service.query_account_history(user1, 2, None).await.unwrap();
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.items[0].direction, "sent");
+    assert_eq!(page.items[0].counterparty, user3);
+    assert_eq!(page.items[0].balance_after, 1000 - 100 + 50 - 25);
+    assert_eq!(page.items[1].direction, "received");
+    assert_eq!(page.items[1].counterparty, user3);
+    assert_eq!(page.items[1].balance_after, 1000 - 100 + 50);
+
+    // second page holds the remaining tx and no further cursor
+    let cursor = page.next_cursor.unwrap();
+    let page = service
+        .query_account_history(user1, 2, Some(&cursor))
+        .await
+        .unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].counterparty, user2);
+    assert_eq!(page.items[0].balance_after, 1000 - 100);
+    assert!(page.next_cursor.is_none());
+
+    // a forged cursor is rejected
+    let response = service
+        .query_account_history(user1, 2, Some("not-a-cursor"))
+        .await;
+    assert!(response.is_err());
+
+    Ok(())
+}
+
 // Simulate transaction failure due to insufficien balance.
 #[tokio::test]
 async fn test_submit_tx_failure_insufficient_balance() -> Result<()> {
@@ -43,19 +220,17 @@ async fn test_submit_tx_failure_insufficient_balance() -> Result<()> {
     let service = Service::start("test_submit_tx_failure_insufficient_balance").await;
 
     // create user 1
-    let response = service.create_account(1, 10).await;
-    assert!(response.is_ok());
+    let user1 = service.create_account(10).await?;
 
     // create user 2
-    let response = service.create_account(2, 10000).await;
-    assert!(response.is_ok());
+    let user2 = service.create_account(10000).await?;
 
     // submit tx from 1 to 2
-    let response = service.submit_transaction(1, 2, 100).await;
+    let response = service.submit_transaction(user1, user2, 100).await;
     assert!(response.is_err());
 
     let error_response = response.err().as_ref().unwrap().to_string();
-    assert_eq!(error_response, service::NOT_ENOUGH_BALANCE.to_string());
+    assert_eq!(error_response, error_codes::NOT_ENOUGH_BALANCE.to_string());
 
     Ok(())
 }
@@ -67,15 +242,16 @@ async fn test_submit_tx_failure_non_existent_sender() -> Result<()> {
     let service = Service::start("test_submit_tx_failure_non_existent_sender").await;
 
     // create recipient
-    let response = service.create_account(2, 10000).await;
-    assert!(response.is_ok());
+    let user2 = service.create_account(10000).await?;
 
-    // submit tx from 1 to 2
-    let response: std::result::Result<String, anyhow::Error> =
-        service.submit_transaction(1, 2, 100).await;
+    // submit tx from 1 to 2, where 1 is never allocated
+    let response = service.submit_transaction(0, user2, 100).await;
     assert!(response.is_err());
     let error_response = response.err().as_ref().unwrap().to_string();
-    assert_eq!(error_response, service::SENDER_DOES_NOT_EXIST.to_string());
+    assert_eq!(
+        error_response,
+        error_codes::SENDER_DOES_NOT_EXIST.to_string()
+    );
 
     Ok(())
 }
@@ -87,19 +263,958 @@ async fn test_submit_tx_failure_non_existent_recipient() -> Result<()> {
     let service = Service::start("test_submit_tx_failure_non_existent_recipient").await;
 
     // create sender
-    let response = service.create_account(1, 10000).await;
-    assert!(response.is_ok());
+    let user1 = service.create_account(10000).await?;
 
-    // submit tx from 1 to 2
-    let response = service.submit_transaction(1, 2, 100).await;
+    // submit tx from 1 to 2, where 2 is never allocated
+    let response = service.submit_transaction(user1, 0, 100).await;
     assert!(response.is_err());
 
     // submit tx from 1 to 2
-    let response: std::result::Result<String, anyhow::Error> =
-        service.submit_transaction(1, 2, 100).await;
+    let response = service.submit_transaction(user1, 0, 100).await;
     assert!(response.is_err());
     let error_response = response.err().as_ref().unwrap().to_string();
-    assert_eq!(error_response, service::RECEIVER_DOES_NOT_EXIST.to_string());
+    assert_eq!(
+        error_response,
+        error_codes::RECEIVER_DOES_NOT_EXIST.to_string()
+    );
+
+    Ok(())
+}
+
+// Simulate concurrent transfers racing for the same sender balance.
+#[tokio::test]
+async fn test_concurrent_tx_never_overdraw() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_concurrent_tx_never_overdraw").await;
+
+    // create users 1, 2 and 3, each of which can afford exactly 10 transfers of 10
+    let mut users = Vec::new();
+    for _ in 1..=3 {
+        users.push(
+            service
+                .create_account(100 + service::THRESHOLD_BALANCE)
+                .await?,
+        );
+    }
+
+    // fire transfers from 1 to 2, 1 to 3, 2 to 3 and 3 to 2 all at once, so that every account
+    // is contended and 2 and 3 are locked from both directions. User 1 is never refilled, so
+    // that exactly as many of its transfers go through as it can afford.
+    let transfers = (0..400).map(|i| match i % 4 {
+        0 => service.submit_transaction(users[0], users[1], 10),
+        1 => service.submit_transaction(users[0], users[2], 10),
+        2 => service.submit_transaction(users[1], users[2], 10),
+        _ => service.submit_transaction(users[2], users[1], 10),
+    });
+    let responses = futures::future::join_all(transfers).await;
+
+    // every rejected transfer must have been rejected for lack of balance
+    for response in responses.iter().filter(|response| response.is_err()) {
+        let error_response = response.as_ref().err().unwrap().to_string();
+        assert_eq!(error_response, error_codes::NOT_ENOUGH_BALANCE.to_string());
+    }
+
+    // no sender may have dropped below the threshold at any point, not even for a while
+    let transactions: Vec<_> = responses.iter().filter_map(|r| r.as_ref().ok()).collect();
+    for tx in &transactions {
+        assert!(tx.from_balance_after >= service::THRESHOLD_BALANCE);
+    }
+    let sent_by_user1 = transactions
+        .iter()
+        .filter(|tx| tx.from_id == users[0])
+        .count();
+    assert_eq!(sent_by_user1, 100 / 10);
+
+    // no balance may drop below the threshold, and no funds may be created or lost
+    let mut total = 0;
+    for id in users {
+        let response = service.query_user(id).await;
+        assert!(response.is_ok());
+        let user = response.unwrap();
+        assert!(user.balance >= service::THRESHOLD_BALANCE);
+        total += user.balance;
+    }
+    assert_eq!(total, 3 * (100 + service::THRESHOLD_BALANCE));
+
+    Ok(())
+}
+
+// Simulate a retried transaction carrying the same idempotency key.
+#[tokio::test]
+async fn test_submit_tx_idempotent_replay() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_tx_idempotent_replay").await;
+
+    // create user 1
+    let user1 = service.create_account(10000).await?;
+
+    // create user 2
+    let user2 = service.create_account(10000).await?;
+
+    // submit tx from 1 to 2 three times with the same key
+    for _ in 0..3 {
+        let response = service
+            .submit_transaction_with_idempotency_key(user1, user2, 100, Some("retry-key"))
+            .await;
+        assert!(response.is_ok());
+    }
+
+    // query balance from 1 and check if balance is deducted only once
+    let response = service.query_user(user1).await;
+    assert!(response.is_ok());
+    assert_eq!(response.unwrap().balance, 10000 - 100);
+
+    // reuse the key for a different tx
+    let response = service
+        .submit_transaction_with_idempotency_key(user1, user2, 200, Some("retry-key"))
+        .await;
+    assert!(response.is_err());
+    let error_response = response.err().as_ref().unwrap().to_string();
+    assert_eq!(
+        error_response,
+        error_codes::IDEMPOTENCY_KEY_CONFLICT.to_string()
+    );
+
+    // other API keys pick their keys apart, so that the same key is neither replayed nor in conflict
+    for (name, amount) in [("other", 100), ("another", 200)] {
+        let other = service.run_command(&["api-keys", "create", "--name", name])?;
+        let body = serde_json::json!({ "from_id": user1, "to_id": user2, "amount": amount });
+        let (status, body) = service
+            .send_request(
+                other["key"].as_str(),
+                reqwest::Method::POST,
+                "/transactions",
+                Some(&body.to_string()),
+                Some("retry-key"),
+            )
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(body["amount"], amount.to_string());
+    }
+    assert_eq!(service.query_user(user1).await?.balance, 10000 - 400);
+
+    Ok(())
+}
+
+// Simulate transfers of different assets between accounts holding one or more of them.
+#[tokio::test]
+async fn test_submit_multi_asset_tx() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_multi_asset_tx").await;
+
+    // register GOLD, which cannot be registered twice
+    assert!(service.create_asset("GOLD", 2).await.is_ok());
+    let error_response = service.create_asset("GOLD", 2).await.err().unwrap();
+    assert_eq!(error_response.to_string(), error_codes::ASSET_EXISTS);
+
+    // user 1 holds GOLD only, user 2 the default asset only
+    let user1 = service.create_account_with_asset("GOLD", 1000).await?;
+    let user2 = service.create_account(500).await?;
+
+    let error_response = service
+        .create_account_with_asset("SILVER", 1000)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        error_response.to_string(),
+        error_codes::ASSET_DOES_NOT_EXIST
+    );
+
+    // user 2 starts holding GOLD on receiving it
+    let record = service
+        .submit_asset_transaction(user1, user2, "GOLD", 300)
+        .await
+        .unwrap();
+    assert_eq!(record.asset, "GOLD");
+    assert_eq!(record.from_balance_after, 700);
+    assert_eq!(record.to_balance_after, 300);
+
+    let holdings = service.query_holdings(user2).await.unwrap();
+    let holdings: Vec<(String, u64)> = holdings
+        .into_iter()
+        .map(|user| (user.asset, user.balance))
+        .collect();
+    assert_eq!(
+        holdings,
+        vec![
+            ("GOLD".to_string(), 300),
+            (service::DEFAULT_ASSET.to_string(), 500)
+        ]
+    );
+
+    // user 1 holds no default asset to send
+    let error_response = service
+        .submit_transaction(user1, user2, 10)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error_response.to_string(), error_codes::ASSET_MISMATCH);
+
+    let error_response = service
+        .submit_asset_transaction(user2, user1, "SILVER", 10)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        error_response.to_string(),
+        error_codes::ASSET_DOES_NOT_EXIST
+    );
+
+    // listing users by their GOLD holding includes the new holder
+    let page = service
+        .query_users("asset=GOLD&order=id_asc")
+        .await
+        .unwrap();
+    let ids: Vec<u64> = page.items.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![user1.min(user2), user1.max(user2)]);
+
+    // each asset is reconciled with the journal on its own
+    let balances = service.query_ledger_balance(user2).await.unwrap();
+    assert_eq!(balances.len(), 2);
+    assert!(balances.iter().all(|balance| balance.consistent));
+
+    Ok(())
+}
+
+// Simulate transfers of fractional amounts, and amounts the service must refuse to round or wrap.
+#[tokio::test]
+async fn test_submit_decimal_amount_tx() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_decimal_amount_tx").await;
+
+    // GOLD is divisible into hundredths
+    assert!(service.create_asset("GOLD", 2).await.is_ok());
+
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/users",
+            Some(r#"{"asset": "GOLD", "balance": "100.25"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    let user1 = body["id"].as_u64().unwrap();
+    let user2 = service.create_account_with_asset("GOLD", 10).await?;
+    let transfer = |amount: &str| {
+        format!(
+            r#"{{"from_id": {}, "to_id": {}, "asset": "GOLD", {}}}"#,
+            user1, user2, amount
+        )
+    };
+
+    // amounts are returned as strings, without losing or adding decimal places
+    let (status, record) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/transactions",
+            Some(&transfer(r#""amount": "0.75""#)),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(record["amount"], "0.75");
+    assert_eq!(record["from_balance_after"], "99.5");
+    assert_eq!(record["to_balance_after"], "10.75");
+
+    let cases = [
+        // more decimal places than GOLD has
+        (r#""amount": "0.001""#, 422, error_codes::INVALID_AMOUNT),
+        // JSON numbers with a fraction may already have lost precision
+        (r#""amount": 0.5"#, 400, error_codes::INVALID_BODY),
+        (r#""amount": "-5""#, 422, error_codes::VALIDATION_FAILED),
+        (r#""amount": "1e3""#, 400, error_codes::INVALID_BODY),
+        (
+            r#""amount": "100000000000000000000""#,
+            422,
+            error_codes::VALIDATION_FAILED,
+        ),
+    ];
+    for (amount, status, code) in cases {
+        let body = transfer(amount);
+        let (response_status, response_body) = service
+            .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+            .await?;
+        assert_eq!(response_status.as_u16(), status, "{}", amount);
+        assert_eq!(response_body["code"], code, "{}", amount);
+    }
+
+    // a balance that would exceed the largest amount is refused rather than wrapped around
+    let (status, body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/users",
+            Some(r#"{"asset": "GOLD", "balance": "99999999999999999999.99"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    let user3 = body["id"].as_u64().unwrap();
+    let error_response = service
+        .submit_asset_transaction(user2, user3, "GOLD", 1)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error_response.to_string(), error_codes::AMOUNT_OUT_OF_RANGE);
+
+    // so are ids that do not fit on record
+    let (status, body) = service
+        .send_raw(reqwest::Method::GET, "/users/9223372036854775808", None)
+        .await?;
+    assert_eq!(status.as_u16(), 400);
+    assert_eq!(body["code"], error_codes::ID_OUT_OF_RANGE);
+
+    Ok(())
+}
+
+// Simulate payloads that must be rejected field by field before reaching DB.
+#[tokio::test]
+async fn test_submit_tx_validation_failure() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_tx_validation_failure").await;
+
+    // create users 1 and 2
+    let user1 = service.create_account(10000).await?;
+    let user2 = service.create_account(10000).await?;
+
+    let cases = [
+        (
+            format!(
+                r#"{{"from_id": {}, "to_id": {}, "amount": 0}}"#,
+                user1, user2
+            ),
+            vec!["amount"],
+        ),
+        (
+            format!(
+                r#"{{"from_id": {}, "to_id": {}, "amount": 10}}"#,
+                user1, user1
+            ),
+            vec!["to_id"],
+        ),
+        (
+            format!(
+                r#"{{"from_id": 9223372036854775808, "to_id": {}, "amount": "-1"}}"#,
+                user2
+            ),
+            vec!["from_id", "amount"],
+        ),
+    ];
+    for (body, fields) in cases {
+        let (status, response_body) = service
+            .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 422, "{}", body);
+        assert_eq!(response_body["code"], error_codes::VALIDATION_FAILED);
+
+        let rejected: Vec<&str> = response_body["details"]["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(rejected, fields, "{}", body);
+    }
+
+    let (status, response_body) = service
+        .send_raw(
+            reqwest::Method::POST,
+            "/users",
+            Some(r#"{"balance": "-10"}"#),
+        )
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(response_body["details"]["fields"][0]["field"], "balance");
+
+    // DB refuses such records even when they bypass the Service
+    let insert_tx = "INSERT INTO Transaction(from_id, to_id, asset, amount, from_balance_after, to_balance_after)";
+    for statement in [
+        format!("{} VALUES ({}, {}, 'TOCOS', 0, 0, 0)", insert_tx, user1, user2),
+        format!("{} VALUES ({}, {}, 'TOCOS', 10, 0, 0)", insert_tx, user1, user1),
+        format!(
+            "INSERT INTO Posting(journal_number, account_id, asset, amount) VALUES (1, {}, 'TOCOS', 0)",
+            user1
+        ),
+    ] {
+        assert!(service.execute_sql(&statement).await.is_err(), "{}", statement);
+    }
+
+    // nothing was recorded for the rejected payloads nor the statements
+    assert!(service.query_transactions("").await.unwrap().is_empty());
+
+    Ok(())
+}
+
+// Simulate full and partial refunds of a transaction, linked to it.
+#[tokio::test]
+async fn test_reverse_tx() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_reverse_tx").await;
+
+    // create users 1 and 2, and send 300 from 1 to 2
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(100).await?;
+    let original = service.submit_transaction(user1, user2, 300).await?;
+    assert_eq!(original.kind, "transfer");
+    assert!(original.reverses.is_none());
+
+    // refund 100, which 2 sends back to 1
+    let reversal = service.reverse_transaction(original.id, Some(100)).await?;
+    assert_eq!(reversal.kind, "reversal");
+    assert_eq!(reversal.reverses, Some(original.id));
+    assert_eq!((reversal.from_id, reversal.to_id), (user2, user1));
+    assert_eq!(reversal.amount, 100);
+    assert_eq!(reversal.from_balance_after, 300);
+    assert_eq!(reversal.to_balance_after, 800);
+
+    // no more than what remains of the original can be refunded
+    let path = format!("/transactions/{}/reverse", original.id);
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path, Some(r#"{"amount": 250}"#))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::REVERSAL_EXCEEDS_REMAINING);
+    assert_eq!(body["details"]["remaining_amount"], "200");
+
+    // refunds are paid under the balance policy of the recipient
+    assert!(service.submit_transaction(user2, user1, 290).await.is_ok());
+    let error_response = service.reverse_transaction(original.id, None).await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::NOT_ENOUGH_BALANCE
+    );
+
+    // leaving out the amount refunds whatever remains
+    assert!(service.submit_transaction(user1, user2, 195).await.is_ok());
+    let reversal = service.reverse_transaction(original.id, None).await?;
+    assert_eq!(reversal.amount, 200);
+    assert_eq!(reversal.from_balance_after, service::THRESHOLD_BALANCE);
+
+    let error_response = service.reverse_transaction(original.id, Some(1)).await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::REVERSAL_EXCEEDS_REMAINING
+    );
+
+    // reversals cannot be reversed, nor can transactions that do not exist
+    for (id, code) in [
+        (reversal.id, error_codes::TRANSACTION_NOT_REVERSIBLE),
+        (reversal.id + 100, error_codes::TRANSACTION_DOES_NOT_EXIST),
+    ] {
+        let error_response = service.reverse_transaction(id, None).await;
+        assert_eq!(error_response.err().unwrap().to_string(), code);
+    }
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, &path, Some(r#"{"amount": 0}"#))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+
+    // both parties see reversals in their history
+    for (id, direction) in [(user1, "received"), (user2, "sent")] {
+        let page = service.query_account_history(id, 1, None).await?;
+        assert_eq!(page.items[0].kind, "reversal");
+        assert_eq!(page.items[0].direction, direction);
+        assert_eq!(page.items[0].reverses, Some(original.id));
+    }
+
+    Ok(())
+}
+
+// Simulate batches of transactions that go through all at once or not at all.
+#[tokio::test]
+async fn test_submit_tx_batch() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_tx_batch").await;
+
+    // create users 1, 2 and 3
+    let user1 = service.create_account(1000).await?;
+    let user2 = service.create_account(100).await?;
+    let user3 = service.create_account(100).await?;
+
+    // user 2 can only afford its transfer thanks to the one before it
+    let batch = service
+        .submit_batch(&[
+            (user1, user2, 300),
+            (user1, user3, 300),
+            (user2, user3, 350),
+        ])
+        .await?;
+    let amounts = batch
+        .transactions
+        .iter()
+        .map(|tx| (tx.from_id, tx.to_id, tx.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        amounts,
+        vec![
+            (user1, user2, 300),
+            (user1, user3, 300),
+            (user2, user3, 350)
+        ]
+    );
+    assert_eq!(batch.transactions[2].from_balance_after, 50);
+
+    for (id, balance) in [(user1, 400), (user2, 50), (user3, 750)] {
+        assert_eq!(service.query_user(id).await?.balance, balance);
+    }
+
+    // the second transfer falls short once the first went through, failing the whole batch
+    let body = format!(
+        r#"{{"transactions": [
+            {{"from_id": {0}, "to_id": {1}, "amount": 100}},
+            {{"from_id": {0}, "to_id": {2}, "amount": 300}}
+        ]}}"#,
+        user1, user2, user3
+    );
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions/batch", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::NOT_ENOUGH_BALANCE);
+    assert_eq!(body["details"]["index"], 1);
+
+    assert_eq!(service.query_user(user1).await?.balance, 400);
+    assert_eq!(service.query_transactions("").await?.len(), 3);
+
+    // invalid entries are pointed at before anything is transferred
+    let error_response = service
+        .submit_batch(&[(user1, user2, 1), (user2, 0, 1)])
+        .await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::RECEIVER_DOES_NOT_EXIST
+    );
+
+    let body = format!(
+        r#"{{"transactions": [
+            {{"from_id": {0}, "to_id": {1}, "amount": 1}},
+            {{"from_id": {0}, "to_id": {0}, "amount": 0}}
+        ]}}"#,
+        user1, user2
+    );
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions/batch", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+    assert_eq!(body["details"]["index"], 1);
+    assert_eq!(body["details"]["fields"].as_array().unwrap().len(), 2);
+
+    // batches are neither empty nor larger than configured
+    let too_large = [(user1, user2, 1); service::MAX_BATCH_SIZE + 1];
+    for batch in [&[][..], &too_large[..]] {
+        let error_response = service.submit_batch(batch).await;
+        assert_eq!(
+            error_response.err().unwrap().to_string(),
+            error_codes::VALIDATION_FAILED
+        );
+    }
+    assert_eq!(service.query_user(user1).await?.balance, 400);
+
+    Ok(())
+}
+
+// Simulate a transaction split across fixed and weighted legs.
+#[tokio::test]
+async fn test_submit_split_tx() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_split_tx").await;
+
+    // create a buyer paying a seller, the platform and the tax account at once
+    let buyer = service.create_account(1000).await?;
+    let seller = service.create_account(100).await?;
+    let platform = service.create_account(100).await?;
+    let tax = service.create_account(100).await?;
+
+    // the 91 left after tax is shared 2:1, rounding leaving 1 over for the seller
+    let body = format!(
+        r#"{{"from_id": {}, "amount": 101, "legs": [
+            {{"to_id": {}, "amount": 10}},
+            {{"to_id": {}, "weight": 2}},
+            {{"to_id": {}, "weight": 1}}
+        ]}}"#,
+        buyer, tax, seller, platform
+    );
+    let (status, split) = service
+        .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(split["kind"], "split");
+    assert!(split["to_id"].is_null());
+    assert_eq!(split["from_balance_after"], "899");
+
+    let legs = split["legs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|leg| {
+            (
+                leg["to_id"].as_u64().unwrap(),
+                leg["amount"].as_str().unwrap().to_string(),
+                leg["to_balance_after"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        legs,
+        vec![
+            (tax, "10".to_string(), "110".to_string()),
+            (seller, "61".to_string(), "161".to_string()),
+            (platform, "30".to_string(), "130".to_string()),
+        ]
+    );
+
+    for (id, balance) in [(buyer, 899), (seller, 161), (platform, 130), (tax, 110)] {
+        assert_eq!(service.query_user(id).await?.balance, balance);
+    }
+    assert!(service.query_ledger_balance(seller).await?[0].consistent);
+
+    // the sender sees the whole split, and each recipient its own share, both with every leg
+    for (id, direction, counterparty, amount) in [
+        (buyer, "sent", serde_json::Value::Null, "101"),
+        (seller, "received", serde_json::json!(buyer), "61"),
+    ] {
+        let path = format!("/users/{}/transactions", id);
+        let (_, page) = service.send_raw(reqwest::Method::GET, &path, None).await?;
+        let entry = &page["items"][0];
+        assert_eq!(entry["direction"], direction);
+        assert_eq!(entry["counterparty"], counterparty);
+        assert_eq!(entry["amount"], amount);
+        assert_eq!(entry["legs"].as_array().unwrap().len(), 3);
+    }
+
+    let path = format!("/transactions?to_id={}", platform);
+    let (_, txs) = service.send_raw(reqwest::Method::GET, &path, None).await?;
+    assert_eq!(txs[0]["id"], split["id"]);
+
+    // splits cannot be reversed as a whole
+    let error_response = service
+        .reverse_transaction(split["id"].as_u64().unwrap(), None)
+        .await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::TRANSACTION_NOT_REVERSIBLE
+    );
+
+    // legs have to add up, credit at least 2 distinct recipients other than the sender, and be
+    // either fixed or weighted
+    for legs in [
+        format!(
+            r#"[{{"to_id": {}, "amount": 50}}, {{"to_id": {}, "amount": 40}}]"#,
+            seller, tax
+        ),
+        format!(
+            r#"[{{"to_id": {}, "amount": 100}}, {{"to_id": {}, "weight": 1}}]"#,
+            seller, tax
+        ),
+        format!(
+            r#"[{{"to_id": {0}, "weight": 1}}, {{"to_id": {0}, "weight": 1}}]"#,
+            seller
+        ),
+        format!(r#"[{{"to_id": {}, "weight": 1, "amount": 100}}]"#, seller),
+        format!(r#"[{{"to_id": {}, "weight": 1}}]"#, buyer),
+    ] {
+        let body = format!(
+            r#"{{"from_id": {}, "amount": 100, "legs": {}}}"#,
+            buyer, legs
+        );
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 422);
+        assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+    }
+
+    // a single recipient is a plain transfer, not a split
+    let body = format!(
+        r#"{{"from_id": {}, "amount": 100, "legs": [{{"to_id": {}, "amount": 100}}]}}"#,
+        buyer, seller
+    );
+    let (_, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+        .await?;
+    assert_eq!(body["details"]["fields"][0]["field"], "legs");
+    assert_eq!(
+        body["details"]["fields"][0]["reason"],
+        "must hold at least 2 legs"
+    );
+
+    // the sender is checked against the whole amount, and every recipient has to exist
+    for (amount, to_id, code) in [
+        (900, tax, error_codes::NOT_ENOUGH_BALANCE),
+        (100, 0, error_codes::RECEIVER_DOES_NOT_EXIST),
+    ] {
+        let body = format!(
+            r#"{{"from_id": {}, "amount": {}, "legs": [
+                {{"to_id": {}, "weight": 1}},
+                {{"to_id": {}, "weight": 1}}
+            ]}}"#,
+            buyer, amount, seller, to_id
+        );
+        let (_, body) = service
+            .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+            .await?;
+        assert_eq!(body["code"], code);
+    }
+    assert_eq!(service.query_user(buyer).await?.balance, 899);
+
+    Ok(())
+}
+
+// Simulate fees charged on transfers according to the asset and the tier of the sender.
+#[tokio::test]
+async fn test_submit_tx_with_fee() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_submit_tx_with_fee").await;
+
+    assert!(service.create_asset(service::FEE_ASSET, 2).await.is_ok());
+    let user1 = service
+        .create_account_with_asset(service::FEE_ASSET, 1000)
+        .await?;
+    let user2 = service
+        .create_account_with_asset(service::FEE_ASSET, 10)
+        .await?;
+    let premium = service
+        .create_account_with(serde_json::json!({
+            "asset": service::FEE_ASSET,
+            "balance": 2000,
+            "tier": "premium",
+        }))
+        .await?;
+    assert_eq!(premium.tier, "premium");
+    assert_eq!(service.query_user(user1).await?.tier, "standard");
+
+    // fees cannot be charged until the fee account is opened
+    let error_response = service
+        .submit_asset_transaction(user1, user2, service::FEE_ASSET, 100)
+        .await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::FEE_ACCOUNT_MISSING
+    );
+    assert_eq!(service.query_user(user1).await?.balance, 1000);
+
+    let fee_account = service
+        .create_account_with(serde_json::json!({
+            "asset": service::FEE_ASSET,
+            "balance": 0,
+            "external_ref": service::FEE_ACCOUNT_REF,
+        }))
+        .await?;
+
+    // quotes charge a flat fee plus a percentage rounded up to the asset, within the caps
+    for (from_id, amount, fee, total) in [
+        (user1, "100", "3", "103"),
+        (user1, "0.33", "1.01", "1.34"),
+        (user1, "5000", "50", "5050"),
+        (premium.id, "100", "0.5", "100.5"),
+        (premium.id, "10", "0.25", "10.25"),
+        (premium.id, "1500", "2", "1502"),
+    ] {
+        let body = format!(
+            r#"{{"from_id": {}, "to_id": {}, "asset": "{}", "amount": "{}"}}"#,
+            from_id,
+            user2,
+            service::FEE_ASSET,
+            amount
+        );
+        let (status, body) = service
+            .send_raw(reqwest::Method::POST, "/transactions/quote", Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(
+            (body["fee"].as_str(), body["total"].as_str()),
+            (Some(fee), Some(total))
+        );
+    }
+
+    // the fee is taken from the sender on top of the amount and credited to the fee account
+    let body = format!(
+        r#"{{"from_id": {}, "to_id": {}, "asset": "{}", "amount": 100}}"#,
+        user1,
+        user2,
+        service::FEE_ASSET
+    );
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["fee"], "3");
+    assert_eq!(body["from_balance_after"], "897");
+    assert_eq!(service.query_user(user2).await?.balance, 110);
+    assert_eq!(service.query_user(fee_account.id).await?.balance, 3);
+
+    // only the sender sees the fee in its history
+    for (id, fee) in [(user1, "3"), (user2, "0")] {
+        let path = format!("/users/{}/transactions", id);
+        let (_, body) = service.send_raw(reqwest::Method::GET, &path, None).await?;
+        assert_eq!(body["items"][0]["fee"], fee);
+    }
+
+    // 880 alone is affordable, 880 along with its fee of 18.6 is not
+    let error_response = service
+        .submit_asset_transaction(user1, user2, service::FEE_ASSET, 880)
+        .await;
+    assert_eq!(
+        error_response.err().unwrap().to_string(),
+        error_codes::NOT_ENOUGH_BALANCE
+    );
+
+    // other assets are not charged any fee
+    let user3 = service.create_account(100).await?;
+    let user4 = service.create_account(100).await?;
+    let record = service.submit_transaction(user3, user4, 50).await?;
+    let (_, body) = service
+        .send_raw(
+            reqwest::Method::GET,
+            &format!("/transactions/{}", record.id),
+            None,
+        )
+        .await?;
+    assert_eq!(body["fee"], "0");
+
+    // quotes are checked as transfers are
+    let body = format!(
+        r#"{{"from_id": 0, "to_id": {}, "asset": "{}", "amount": 1}}"#,
+        user2,
+        service::FEE_ASSET
+    );
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions/quote", Some(&body))
+        .await?;
+    assert_eq!(status.as_u16(), 404);
+    assert_eq!(body["code"], error_codes::SENDER_DOES_NOT_EXIST);
+
+    // the fee account is reconciled with the journal as any other
+    for id in [user1, fee_account.id] {
+        assert!(service.query_ledger_balance(id).await?[0].consistent);
+    }
+
+    Ok(())
+}
+
+// Simulate fees charged on splits and on captured holds, as on transfers.
+#[tokio::test]
+async fn test_split_and_capture_with_fee() -> Result<()> {
+    // start service binary
+    let service = Service::start("test_split_and_capture_with_fee").await;
+
+    assert!(service.create_asset(service::FEE_ASSET, 2).await.is_ok());
+    let buyer = service
+        .create_account_with_asset(service::FEE_ASSET, 1000)
+        .await?;
+    let seller = service
+        .create_account_with_asset(service::FEE_ASSET, 0)
+        .await?;
+    let tax = service
+        .create_account_with_asset(service::FEE_ASSET, 0)
+        .await?;
+    let fee_account = service
+        .create_account_with(serde_json::json!({
+            "asset": service::FEE_ASSET,
+            "balance": 0,
+            "external_ref": service::FEE_ACCOUNT_REF,
+        }))
+        .await?;
+
+    // splits are charged on the whole amount debited, across every leg
+    let split = format!(
+        r#"{{"from_id": {}, "asset": "{}", "amount": 100, "legs": [
+            {{"to_id": {}, "weight": 1}},
+            {{"to_id": {}, "weight": 1}}
+        ]}}"#,
+        buyer,
+        service::FEE_ASSET,
+        seller,
+        tax
+    );
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions/quote", Some(&split))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["fee"], "3");
+
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/transactions", Some(&split))
+        .await?;
+    assert_eq!(status.as_u16(), 200);
+    assert_eq!(body["fee"], "3");
+    assert_eq!(body["from_balance_after"], "897");
+    assert_eq!(service.query_user(seller).await?.balance, 50);
+    assert_eq!(service.query_user(tax).await?.balance, 50);
+    assert_eq!(service.query_user(fee_account.id).await?.balance, 3);
+
+    // captures are charged on the amount captured, whether part of the hold or all of it
+    for (hold_amount, capture, fee, buyer_balance, fee_balance) in
+        [(200, Some(100), "3", 794, 6), (50, None, "2", 742, 8)]
+    {
+        let body = format!(
+            r#"{{"from_id": {}, "to_id": {}, "asset": "{}", "amount": {}}}"#,
+            buyer,
+            seller,
+            service::FEE_ASSET,
+            hold_amount
+        );
+        let (status, hold) = service
+            .send_raw(reqwest::Method::POST, "/holds", Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+
+        let path = format!("/holds/{}/capture", hold["id"]);
+        let body = serde_json::json!({ "amount": capture }).to_string();
+        let (status, hold) = service
+            .send_raw(reqwest::Method::POST, &path, Some(&body))
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert_eq!(hold["status"], "captured");
+
+        let path = format!("/transactions/{}", hold["transaction_id"]);
+        let (_, transaction) = service.send_raw(reqwest::Method::GET, &path, None).await?;
+        assert_eq!(transaction["fee"], fee);
+        assert_eq!(service.query_user(buyer).await?.balance, buyer_balance);
+        assert_eq!(
+            service.query_user(fee_account.id).await?.balance,
+            fee_balance
+        );
+    }
+
+    // the fee account is reconciled with the journal as any other
+    for id in [buyer, seller, fee_account.id] {
+        assert!(service.query_ledger_balance(id).await?[0].consistent);
+    }
+
+    Ok(())
+}
+
+// Simulate assets whose decimals are coarser than the fee schedules charging them.
+#[tokio::test]
+async fn test_fee_schedule_decimals() -> Result<()> {
+    // start service binary, charging a min_fee of 0.25 on the fee asset for premium accounts
+    let service = Service::start("test_fee_schedule_decimals").await;
+
+    // registering the fee asset with fewer decimals than its fees is rejected
+    let asset = serde_json::json!({ "symbol": service::FEE_ASSET, "decimals": 1, "name": "fee" });
+    let (status, body) = service
+        .send_raw(reqwest::Method::POST, "/assets", Some(&asset.to_string()))
+        .await?;
+    assert_eq!(status.as_u16(), 422);
+    assert_eq!(body["code"], error_codes::VALIDATION_FAILED);
+    assert_eq!(body["details"]["fields"][0]["field"], "decimals");
+
+    // and the service refuses to start on an asset registered as such beforehand
+    service
+        .execute_sql(&format!(
+            "INSERT INTO Asset(symbol, decimals, name) VALUES ('{}', 1, 'fee')",
+            service::FEE_ASSET
+        ))
+        .await?;
+    let error = service.start_again()?.expect("service started");
+    assert!(error.contains("fee_schedules in config.toml are invalid"));
+
+    let (status, _) = service
+        .send_raw(reqwest::Method::GET, "/assets", None)
+        .await?;
+    assert_eq!(status.as_u16(), 200);
 
     Ok(())
 }
@@ -0,0 +1,78 @@
+use anyhow::Result;
+
+use crate::config::service::Service;
+
+// Schema and rows of a database set up by the first release, which kept balances on Account.
+const FIRST_RELEASE_DATABASE: &str = "
+CREATE TABLE Account(
+    number BIGSERIAL,
+    id BIGINT,
+    balance BIGINT,
+
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE Transaction(
+    number BIGSERIAL,
+    from_id BIGINT,
+    to_id BIGINT,
+    amount BIGINT,
+
+    PRIMARY KEY (number)
+);
+
+-- opened with 120, 40 and 0 respectively
+INSERT INTO Account(id, balance) VALUES (1, 110), (2, 50), (3, 0);
+INSERT INTO Transaction(from_id, to_id, amount) VALUES (1, 2, 20), (2, 3, 10), (3, 1, 10);
+";
+
+// Simulate the Service starting on a database set up by the first release.
+#[tokio::test]
+async fn test_upgrade_first_release_database() -> Result<()> {
+    // start service binary on the old database, which the command issuing its key upgrades again
+    let service = Service::start_on(
+        "test_upgrade_first_release_database",
+        FIRST_RELEASE_DATABASE,
+    )
+    .await;
+
+    // balances are carried over to the ledger
+    for (id, balance) in [(1, 110), (2, 50), (3, 0)] {
+        let user = service.query_user(id).await?;
+        assert_eq!(user.asset, "TOCOS");
+        assert_eq!(user.balance, balance);
+
+        let (status, body) = service
+            .send_raw(
+                reqwest::Method::GET,
+                &format!("/users/{}/balance", id),
+                None,
+            )
+            .await?;
+        assert_eq!(status.as_u16(), 200);
+        assert!(body
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|b| b["consistent"] == true));
+    }
+
+    // earlier transactions tell the balances they left behind
+    for (number, from_balance_after, to_balance_after) in [(1, 100, 60), (2, 50, 10), (3, 0, 110)] {
+        let transaction = service.query_transaction(number).await?;
+        assert_eq!(transaction.kind, "transfer");
+        assert_eq!(transaction.asset, "TOCOS");
+        assert_eq!(transaction.from_balance_after, from_balance_after);
+        assert_eq!(transaction.to_balance_after, to_balance_after);
+    }
+
+    // and transfers carry on from there
+    let transaction = service.submit_transaction(1, 2, 10).await?;
+    assert_eq!(transaction.from_balance_after, 100);
+    assert_eq!(transaction.to_balance_after, 60);
+
+    let history = service.query_account_history(2, 10, None).await?;
+    assert_eq!(history.items.len(), 3);
+
+    Ok(())
+}
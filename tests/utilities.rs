@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::path::PathBuf;
 
 pub(crate) fn get_test_binary_path(
@@ -32,15 +32,141 @@ pub(crate) fn get_test_config_path(test_dir: &str, test_name: &str) -> String {
     config_dir_per_test.display().to_string()
 }
 
+// Amounts are serialized as strings. Tests moving whole units only read them back as integers.
+fn amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Transaction {
     pub from_id: u64,
     pub to_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransactionRecord {
+    pub id: u64,
+    pub kind: String,
+    pub reverses: Option<u64>,
+    pub from_id: u64,
+    pub to_id: u64,
+    pub asset: String,
+    #[serde(deserialize_with = "amount")]
+    pub amount: u64,
+    #[serde(deserialize_with = "amount")]
+    pub from_balance_after: u64,
+    #[serde(deserialize_with = "amount")]
+    pub to_balance_after: u64,
+    pub status: String,
+    pub schedule_id: Option<u64>,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransactionBatch {
+    pub transactions: Vec<TransactionRecord>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub kind: String,
+    pub reverses: Option<u64>,
+    pub direction: String,
+    pub counterparty: u64,
+    pub asset: String,
+    #[serde(deserialize_with = "amount")]
     pub amount: u64,
+    #[serde(deserialize_with = "amount")]
+    pub balance_after: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct User {
     pub id: u64,
+    pub external_ref: Option<String>,
+    pub display_name: Option<String>,
+    pub owner_ref: Option<String>,
+    pub metadata: serde_json::Value,
+    pub tier: String,
+    pub created_at: String,
+    pub asset: String,
+    #[serde(deserialize_with = "amount")]
+    pub balance: u64,
+    #[serde(deserialize_with = "amount")]
+    pub ledger: u64,
+    #[serde(deserialize_with = "amount")]
+    pub available: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Hold {
+    pub id: u64,
+    pub from_id: u64,
+    pub to_id: u64,
+    pub asset: String,
+    #[serde(deserialize_with = "amount")]
+    pub amount: u64,
+    pub status: String,
+    pub captured_amount: Option<String>,
+    pub transaction_id: Option<u64>,
+    pub expires_at: String,
+    pub created_at: String,
+    pub settled_at: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Schedule {
+    pub id: u64,
+    pub from_id: u64,
+    pub to_id: u64,
+    pub asset: String,
+    #[serde(deserialize_with = "amount")]
+    pub amount: u64,
+    pub recurrence: Option<String>,
+    pub status: String,
+    pub next_run_at: Option<String>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub last_transaction_id: Option<u64>,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Asset {
+    pub symbol: String,
+    pub decimals: u8,
+    pub name: String,
+    pub issuer: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LedgerBalance {
+    pub id: u64,
+    pub asset: String,
+    #[serde(deserialize_with = "amount")]
     pub balance: u64,
+    #[serde(deserialize_with = "amount")]
+    pub ledger_balance: u64,
+    pub consistent: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    pub code: String,
+    pub numeric_code: u16,
+    pub message: String,
+    pub details: serde_json::Value,
+    pub request_id: String,
 }